@@ -6,6 +6,7 @@ use crate::utils::gitops::set_git_remote_url;
 use crate::utils::reqwest_client::get_client;
 use crate::utils::bitbucket_auth_info::BitbucketAuthInfo;
 use crate::utils::review::Review;
+use crate::logger::redact::register_secret;
 
 pub async fn get_access_token_from_bitbucket(code: &str) -> Option<BitbucketAuthInfo> {
     let client = get_client();
@@ -46,6 +47,8 @@ pub async fn get_access_token_from_bitbucket(code: &str) -> Option<BitbucketAuth
         return None;
     }
     let mut response_json = parse_res.expect("Uncaught error in parse_res for BitbucketAuthInfo");
+    register_secret("bitbucket_access_token", response_json.access_token());
+    register_secret("bitbucket_refresh_token", response_json.refresh_token());
     save_bitbucket_auth_info_to_db(&mut response_json);
     return Some(response_json);
 }
@@ -134,6 +137,8 @@ async fn bitbucket_refresh_token(refresh_token: &str) -> Option<BitbucketAuthInf
         log::error!("[update_access_token] Unable to deserialize refresh token response: {}", e);
         return None;
     }
-    let refresh_token_resbody = parse_res.expect("Uncaught error in parse_res");
+    let refresh_token_resbody: BitbucketAuthInfo = parse_res.expect("Uncaught error in parse_res");
+    register_secret("bitbucket_access_token", refresh_token_resbody.access_token());
+    register_secret("bitbucket_refresh_token", refresh_token_resbody.refresh_token());
     return Some(refresh_token_resbody);
 }
\ No newline at end of file
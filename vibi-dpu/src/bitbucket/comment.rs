@@ -1,5 +1,12 @@
+use std::time::Instant;
+
+use reqwest::StatusCode;
 use serde::Serialize;
+use serde_json::Value;
 
+use crate::db::comment::{get_comment_id_from_db, save_comment_id_to_db};
+use crate::metrics::registry::{record_comment_edited, record_comment_posted, record_provider_response};
+use crate::utils::comment::{CommentEdit, CommentKind};
 use crate::utils::review::Review;
 use crate::utils::reqwest_client::get_client;
 use super::config::{bitbucket_base_url, prepare_headers};
@@ -13,25 +20,87 @@ struct Comment {
 struct Content {
     raw: String,
 }
-pub async fn add_comment(comment_text: &str, review: &Review, access_token: &str) {
+
+// Edits the comment of this kind already posted on the PR, or posts a new one
+// if there is none or it was deleted.
+pub async fn add_comment(comment_text: &str, review: &Review, access_token: &str, kind: &CommentKind) {
+    if let Some(comment_id) = get_comment_id_from_db(review, kind) {
+        let edit = edit_comment(&comment_id, comment_text, review, access_token).await;
+        if edit != CommentEdit::Missing {
+            return;
+        }
+        log::info!("[add_comment] {} comment {} was deleted, posting a new one", kind, &comment_id);
+    }
+    let comment_id_opt = post_comment(comment_text, review, access_token).await;
+    if let Some(comment_id) = comment_id_opt {
+        save_comment_id_to_db(review, kind, &comment_id);
+    }
+}
+
+async fn post_comment(comment_text: &str, review: &Review, access_token: &str) -> Option<String> {
     let url = prepare_add_comment_url(review);
     let comment_payload = prepare_body(comment_text);
     let client = get_client();
-    let headers_opt = prepare_headers(&access_token);
+    let headers_opt = prepare_headers(access_token);
     if headers_opt.is_none() {
-        log::error!("[add_comment] Unable to prepare_headers_comment, empty headers_opt");
-        return;
+        log::error!("[post_comment] Unable to prepare_headers_comment, empty headers_opt");
+        return None;
     }
     let headers = headers_opt.expect("Empty headers_opt");
+    let request_start = Instant::now();
     let response_res = client.post(&url).
         headers(headers).json(&comment_payload).send().await;
+    record_provider_response("bitbucket", &response_res, request_start.elapsed());
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[post_comment] Error in post request for adding comment - {:?}", e);
+        return None;
+    }
+    let response = response_res.expect("Error in getting response");
+    log::debug!("[post_comment] response from comment post request = {:?}", &response);
+    if !response.status().is_success() {
+        log::error!("[post_comment] Unable to add comment, status: {}", response.status());
+        return None;
+    }
+    record_comment_posted("bitbucket");
+    let response_json = response.json::<Value>().await.unwrap_or_default();
+    return response_json["id"].as_u64().map(|id| id.to_string());
+}
+
+async fn edit_comment(comment_id: &str, comment_text: &str, review: &Review, access_token: &str) -> CommentEdit {
+    let url = prepare_edit_comment_url(review, comment_id);
+    let comment_payload = prepare_body(comment_text);
+    let headers_opt = prepare_headers(access_token);
+    if headers_opt.is_none() {
+        log::error!("[edit_comment] Unable to prepare_headers_comment, empty headers_opt");
+        return CommentEdit::Failed;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let request_start = Instant::now();
+    let response_res = get_client().put(&url).
+        headers(headers).json(&comment_payload).send().await;
+    record_provider_response("bitbucket", &response_res, request_start.elapsed());
     if response_res.is_err() {
         let e = response_res.expect_err("No error in response_res");
-        log::error!("[add_comment] Error in post request for adding comment - {:?}", e);
-        return;
+        log::error!("[edit_comment] Error in put request for editing comment - {:?}", e);
+        return CommentEdit::Failed;
     }
     let response = response_res.expect("Error in getting response");
-    log::debug!("[add_comment] response from comment post request = {:?}", &response);
+    log::debug!("[edit_comment] response from comment put request = {:?}", &response);
+    if response.status() == StatusCode::NOT_FOUND {
+        return CommentEdit::Missing;
+    }
+    if !response.status().is_success() {
+        log::error!("[edit_comment] Unable to edit comment {}, status: {}", comment_id, response.status());
+        return CommentEdit::Failed;
+    }
+    // Bitbucket keeps deleted comments around with a deleted flag
+    let response_json = response.json::<Value>().await.unwrap_or_default();
+    if response_json["deleted"].as_bool().unwrap_or(false) {
+        return CommentEdit::Missing;
+    }
+    record_comment_edited("bitbucket");
+    return CommentEdit::Edited;
 }
 
 fn prepare_add_comment_url(review: &Review) -> String {
@@ -45,6 +114,13 @@ fn prepare_add_comment_url(review: &Review) -> String {
     log::debug!("[prepare_add_comment_url] comment url = {}", &url);
     return url;
 }
+
+fn prepare_edit_comment_url(review: &Review, comment_id: &str) -> String {
+    let url = format!("{}/{}", prepare_add_comment_url(review), comment_id);
+    log::debug!("[prepare_edit_comment_url] comment url = {}", &url);
+    return url;
+}
+
 fn prepare_body(comment_text: &str) -> Comment {
     let comment_payload = Comment {
         content: Content {
@@ -52,4 +128,4 @@ fn prepare_body(comment_text: &str) -> Comment {
         },
     };
     return comment_payload;
-}
\ No newline at end of file
+}
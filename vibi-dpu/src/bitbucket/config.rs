@@ -1,7 +1,8 @@
-use std::env;
+use std::{env, time::Instant};
 
 use reqwest::{Response, header::{HeaderMap, HeaderValue}};
 use serde_json::Value;
+use crate::metrics::registry::record_provider_response;
 use crate::utils::reqwest_client::get_client;
 
 pub fn bitbucket_base_url() -> String {
@@ -31,7 +32,9 @@ pub async fn get_api_response(url: &str, headers_opt: Option<reqwest::header::He
         headers = headers_opt.expect("Empty headers_opt");
     }
     let client = get_client();
+    let request_start = Instant::now();
     let get_res = client.get(url).headers(headers).send().await;
+    record_provider_response("bitbucket", &get_res, request_start.elapsed());
     if get_res.is_err() {
         let e = get_res.expect_err("No error in get_res");
         log::error!("[get_api_response] Error sending GET request without params to {}, error: {}", url, e);
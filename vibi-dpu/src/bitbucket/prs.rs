@@ -1,9 +1,11 @@
 use crate::db::prs::update_pr_info_in_db;
+use crate::metrics::registry::record_provider_response;
 use crate::utils::{pr_info::PrInfo, reqwest_client::get_client};
 use reqwest::header::HeaderMap;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::str;
+use std::time::Instant;
 
 use super::config::{bitbucket_base_url, prepare_auth_headers};
 
@@ -102,9 +104,112 @@ fn parse_pr_info(pr_data: &Value) -> Option<PrInfo> {
     }
 }
 
+// Identifiers of every participant who has approved the PR. Aliases can be
+// mapped to any of uuid, account_id, nickname or display_name on the server,
+// so all of them are returned for matching against relevance handles.
+pub async fn pr_approver_handles(workspace_slug: &str, repo_slug: &str,
+        pr_number: &str, access_token: &str) -> Option<Vec<String>> {
+    let headers_opt = prepare_auth_headers(access_token);
+    if headers_opt.is_none() {
+        log::error!("[pr_approver_handles] Unable to prepare auth headers: {}", repo_slug);
+        return None;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let url = format!(
+        "{}/repositories/{}/{}/pullrequests/{}",
+        bitbucket_base_url(), workspace_slug, repo_slug, pr_number
+    );
+    let request_start = Instant::now();
+    let response_res = get_client()
+        .get(&url)
+        .headers(headers)
+        .header("Accept", "application/json")
+        .send()
+        .await;
+    record_provider_response("bitbucket", &response_res, request_start.elapsed());
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[pr_approver_handles] Failed to send the request: {:?}", e);
+        return None;
+    }
+    let response = response_res.expect("Uncaught error in response_res");
+    if !response.status().is_success() {
+        log::error!("[pr_approver_handles] Request failed with status: {:?}", response.status());
+        return None;
+    }
+    let pr_data_res = response.json::<Value>().await;
+    if pr_data_res.is_err() {
+        let e = pr_data_res.expect_err("No error in pr_data_res");
+        log::error!("[pr_approver_handles] Failed to parse JSON: {:?}", e);
+        return None;
+    }
+    let pr_data = pr_data_res.expect("Uncaught error in pr_data_res");
+    return Some(parse_approver_handles(&pr_data));
+}
+
+fn parse_approver_handles(pr_data: &Value) -> Vec<String> {
+    let mut approver_handles = Vec::<String>::new();
+    let participants = pr_data["participants"].as_array().cloned().unwrap_or_default();
+    for participant in participants {
+        if !participant["approved"].as_bool().unwrap_or(false) {
+            continue;
+        }
+        for field in ["uuid", "account_id", "nickname", "display_name"] {
+            if let Some(handle) = participant["user"][field].as_str() {
+                if !handle.is_empty() && !approver_handles.contains(&handle.to_string()) {
+                    approver_handles.push(handle.to_string());
+                }
+            }
+        }
+    }
+    return approver_handles;
+}
+
+// Open PRs in the repo with this user, by uuid, among the reviewers
+pub async fn open_review_request_count(workspace_slug: &str, repo_slug: &str,
+        reviewer_uuid: &str, access_token: &str) -> Option<usize> {
+    let headers_opt = prepare_auth_headers(access_token);
+    if headers_opt.is_none() {
+        log::error!("[open_review_request_count] Unable to prepare auth headers: {}", repo_slug);
+        return None;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let url = format!("{}/repositories/{}/{}/pullrequests", bitbucket_base_url(), workspace_slug, repo_slug);
+    let query = format!("state=\"OPEN\" AND reviewers.uuid=\"{}\"", reviewer_uuid);
+    let request_start = Instant::now();
+    let response_res = get_client()
+        .get(&url)
+        .headers(headers)
+        .query(&[("q", query.as_str()), ("pagelen", "50"), ("fields", "size,values.id")])
+        .send()
+        .await;
+    record_provider_response("bitbucket", &response_res, request_start.elapsed());
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[open_review_request_count] Failed to send the request: {:?}", e);
+        return None;
+    }
+    let response = response_res.expect("Uncaught error in response_res");
+    if !response.status().is_success() {
+        log::error!("[open_review_request_count] Request failed with status: {:?}", response.status());
+        return None;
+    }
+    let prs_res = response.json::<Value>().await;
+    if prs_res.is_err() {
+        let e = prs_res.expect_err("No error in prs_res");
+        log::error!("[open_review_request_count] Unable to deserialize response: {:?}", e);
+        return None;
+    }
+    let prs = prs_res.expect("Uncaught error in prs_res");
+    if let Some(size) = prs["size"].as_u64() {
+        return Some(size as usize);
+    }
+    return prs["values"].as_array().map(|values| values.len());
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_pr_info;
+    use super::{parse_approver_handles, parse_pr_info};
     use serde_json::json;
 
     #[test]
@@ -132,6 +237,24 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn parse_approver_handles_only_returns_approved_participants() {
+        let pr_data = json!({
+            "participants": [
+                {
+                    "approved": true,
+                    "user": { "uuid": "{abc}", "account_id": "557058:1", "nickname": "alice", "display_name": "Alice" }
+                },
+                {
+                    "approved": false,
+                    "user": { "uuid": "{def}", "account_id": "557058:2", "nickname": "bob", "display_name": "Bob" }
+                }
+            ]
+        });
+        assert_eq!(parse_approver_handles(&pr_data), vec!["{abc}", "557058:1", "alice", "Alice"]);
+        assert!(parse_approver_handles(&json!({})).is_empty());
+    }
 }
 
 pub async fn get_pr_info(workspace_slug: &str,repo_slug: &str,access_token: &str,pr_number: &str) -> Option<PrInfo> {
@@ -1,6 +1,9 @@
+use std::time::Instant;
+
 use reqwest::Response;
 use serde_json::Value;
 
+use crate::metrics::registry::{record_provider_response, record_reviewers_assigned};
 use crate::utils::review::Review;
 use crate::utils::user::BitbucketUser;
 use crate::utils::reqwest_client::get_client;
@@ -97,6 +100,7 @@ async fn put_reviewers(url: &str, access_token: &str, put_body_opt: &Option<Valu
     let put_body = put_body_opt.to_owned().expect("Empty put_body_opt");
     // Make the PUT API call
     let client = get_client();
+    let request_start = Instant::now();
     let response_res = client
         .put(url)
         .bearer_auth(&access_token)
@@ -104,6 +108,10 @@ async fn put_reviewers(url: &str, access_token: &str, put_body_opt: &Option<Valu
         .header("Content-Type", "application/json")
         .json(&put_body)
         .send().await;
+    record_provider_response("bitbucket", &response_res, request_start.elapsed());
+    if response_res.as_ref().is_ok_and(|response| response.status().is_success()) {
+        record_reviewers_assigned("bitbucket", 1);
+    }
 
     // Handle the response_res as necessary
     log::debug!("[put_reviewers] response_res = {:?}", &response_res);
@@ -3,7 +3,7 @@ use std::env;
 use reqwest::{header::HeaderValue, Response, Error};
 use serde_json::json;
 
-use crate::{db::webhook::save_webhook_to_db, utils::bitbucket_webhook::{Webhook, WebhookResponse}, bitbucket::config::{bitbucket_base_url, get_api_values}};
+use crate::{db::webhook::save_bitbucket_webhook_to_db, utils::bitbucket_webhook::{Webhook, WebhookResponse}, bitbucket::config::{bitbucket_base_url, get_api_values}};
 use crate::utils::reqwest_client::get_client;
 use super::config::prepare_auth_headers;
 
@@ -85,5 +85,5 @@ async fn process_add_webhook_response(response: Result<Response, Error>){
 		webhook.links()["self"]["href"].clone(),
 		webhook.url().to_string(),
 	);
-	save_webhook_to_db(&webhook_data); 
+	save_bitbucket_webhook_to_db(&webhook_data); 
 }
\ No newline at end of file
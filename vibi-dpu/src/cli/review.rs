@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::{
+	core::{relevance::{relevance_by_alias, RelevanceScoring}, review::{calculate_hunkmap, get_included_and_excluded_files}},
+	utils::{blame_settings::BlameSettings, exclusion::AuthorExclusions, file_exclusion::{excluded_files_text, FileExclusionRules}, gitops::{commit_exists, StatItem}, hunk::HunkMap, relevance::Relevance, repo_config::RepoConfig, review::Review},
+};
+
+const LOCAL_PROVIDER: &str = "local";
+const USAGE: &str = "Usage: vibi-dpu review --repo <path> --base <sha> --head <sha> [--format json|markdown] [--config <repo-config.json>]";
+
+#[derive(Debug, PartialEq)]
+pub struct ReviewArgs {
+	repo: String,
+	base: String,
+	head: String,
+	format: OutputFormat,
+	// Repo config file with the same keys the server sends
+	config: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum OutputFormat {
+	Json,
+	Markdown,
+}
+
+// Runs the hunk and relevance pipeline on a local clone without talking to
+// any provider, the Vibinex server or the database. Returns the exit code.
+pub async fn run_review_command(args: &[String]) -> i32 {
+	let args_res = parse_review_args(args);
+	if args_res.is_err() {
+		let e = args_res.expect_err("No error in args_res");
+		eprintln!("{}\n{}", e, USAGE);
+		return 2;
+	}
+	let review_args = args_res.expect("Uncaught error in args_res");
+	let repo_config_res = load_repo_config(&review_args.config);
+	if repo_config_res.is_err() {
+		let e = repo_config_res.expect_err("No error in repo_config_res");
+		eprintln!("{}", e);
+		return 2;
+	}
+	let repo_config = repo_config_res.expect("Uncaught error in repo_config_res");
+	let file_rules = FileExclusionRules::from_repo_config(&repo_config);
+	let blame_settings = BlameSettings::from_repo_config(&repo_config);
+	for commit in [&review_args.base, &review_args.head] {
+		if !commit_exists(commit, &review_args.repo) {
+			eprintln!("Commit {} not found in {}", commit, &review_args.repo);
+			return 1;
+		}
+	}
+	let review = local_review(&review_args);
+	let files_opt = get_included_and_excluded_files(&review, &file_rules, &blame_settings);
+	if files_opt.is_none() {
+		eprintln!("Unable to diff {}...{} in {}", &review_args.base, &review_args.head, &review_args.repo);
+		return 1;
+	}
+	let (excluded_files, smallfiles) = files_opt.expect("Empty files_opt");
+	let blame_settings = blame_settings.with_ignore_revs_file(&review);
+	let hunkmap_opt = calculate_hunkmap(&review, &smallfiles, &blame_settings).await;
+	if hunkmap_opt.is_none() {
+		eprintln!("Unable to calculate hunks for {}...{}", &review_args.base, &review_args.head);
+		return 1;
+	}
+	let hunkmap = hunkmap_opt.expect("Empty hunkmap_opt");
+	let relevance_vec = local_relevance(&hunkmap, &RelevanceScoring::from_repo_config(&repo_config),
+		&AuthorExclusions::from_repo_config(&repo_config));
+	let output = match review_args.format {
+		OutputFormat::Json => json_output(&hunkmap, &relevance_vec, &excluded_files),
+		OutputFormat::Markdown => markdown_output(&hunkmap, &relevance_vec, &excluded_files),
+	};
+	println!("{}", output);
+	return 0;
+}
+
+fn parse_review_args(args: &[String]) -> Result<ReviewArgs, String> {
+	let mut repo = None;
+	let mut base = None;
+	let mut head = None;
+	let mut format = OutputFormat::Json;
+	let mut config = None;
+	let mut args_iter = args.iter();
+	while let Some(flag) = args_iter.next() {
+		let value = args_iter.next()
+			.ok_or_else(|| format!("Missing value for {}", flag))?;
+		match flag.as_str() {
+			"--repo" => repo = Some(value.to_owned()),
+			"--base" => base = Some(value.to_owned()),
+			"--head" => head = Some(value.to_owned()),
+			"--format" => {
+				format = match value.as_str() {
+					"json" => OutputFormat::Json,
+					"markdown" | "md" => OutputFormat::Markdown,
+					_ => return Err(format!("Unknown format: {}", value)),
+				};
+			}
+			"--config" => config = Some(value.to_owned()),
+			_ => return Err(format!("Unknown argument: {}", flag)),
+		}
+	}
+	return Ok(ReviewArgs {
+		repo: repo.ok_or("Missing --repo")?,
+		base: base.ok_or("Missing --base")?,
+		head: head.ok_or("Missing --head")?,
+		format,
+		config,
+	});
+}
+
+// Keys missing from the file keep their defaults, so it only needs the
+// settings it changes
+fn load_repo_config(config_path_opt: &Option<String>) -> Result<RepoConfig, String> {
+	if config_path_opt.is_none() {
+		return Ok(RepoConfig::default());
+	}
+	let config_path = config_path_opt.to_owned().expect("Empty config_path_opt");
+	let contents = fs::read_to_string(&config_path)
+		.map_err(|e| format!("Unable to read {}: {}", &config_path, e))?;
+	let overrides = serde_json::from_str::<serde_json::Value>(&contents)
+		.map_err(|e| format!("Invalid JSON in {}: {}", &config_path, e))?;
+	let overrides_map = overrides.as_object()
+		.ok_or_else(|| format!("Expected a JSON object in {}", &config_path))?;
+	let mut repo_config = serde_json::to_value(RepoConfig::default())
+		.expect("repo config is always serializable");
+	for (key, value) in overrides_map {
+		repo_config[key] = value.to_owned();
+	}
+	return serde_json::from_value(repo_config)
+		.map_err(|e| format!("Invalid repo config in {}: {}", &config_path, e));
+}
+
+fn local_review(review_args: &ReviewArgs) -> Review {
+	let repo_name = Path::new(&review_args.repo)
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or_else(|| review_args.repo.to_owned());
+	return Review::new(
+		review_args.base.to_owned(),
+		review_args.head.to_owned(),
+		review_args.head.to_owned(),
+		repo_name.to_owned(),
+		LOCAL_PROVIDER.to_string(),
+		LOCAL_PROVIDER.to_string(),
+		format!("{}/{}/{}", LOCAL_PROVIDER, repo_name, &review_args.head),
+		review_args.repo.to_owned(),
+		String::new(),
+		String::new(),
+		None,
+	);
+}
+
+fn local_relevance(hunkmap: &HunkMap, scoring: &RelevanceScoring, exclusions: &AuthorExclusions) -> Vec<Relevance> {
+	let mut relevance_vec = Vec::<Relevance>::new();
+	let now_secs = chrono::Utc::now().timestamp();
+	for prhunk in hunkmap.prhunkvec() {
+		// No provider handles locally, so only git aliases and author names are matched
+		let excluded_aliases: HashSet<String> = prhunk.blamevec().iter()
+			.filter(|blame| exclusions.matches(blame.author()) || exclusions.matches(blame.author_name()))
+			.map(|blame| blame.author().to_owned())
+			.collect();
+		let relevance_map_opt = relevance_by_alias(prhunk, scoring, &excluded_aliases, now_secs);
+		if relevance_map_opt.is_none() {
+			continue;
+		}
+		let relevance_map = relevance_map_opt.expect("Empty relevance_map_opt");
+		for (git_alias, (raw_relevance, relevance)) in relevance_map {
+			relevance_vec.push(Relevance::new(
+				LOCAL_PROVIDER.to_string(),
+				git_alias,
+				format!("{:.2}", relevance),
+				relevance,
+				raw_relevance,
+				None,
+			));
+		}
+	}
+	relevance_vec.sort_by(|a, b| b.relevance_num().total_cmp(&a.relevance_num())
+		.then_with(|| a.git_alias().cmp(b.git_alias())));
+	return relevance_vec;
+}
+
+fn json_output(hunkmap: &HunkMap, relevance_vec: &Vec<Relevance>, excluded_files: &Vec<StatItem>) -> String {
+	let output = json!({
+		"hunkmap": hunkmap,
+		"relevance": relevance_vec,
+		"excluded_files": excluded_files,
+	});
+	return serde_json::to_string_pretty(&output).expect("review output is always serializable");
+}
+
+fn markdown_output(hunkmap: &HunkMap, relevance_vec: &Vec<Relevance>, excluded_files: &Vec<StatItem>) -> String {
+	let mut output = "## Relevance\n\n".to_string();
+	output += "| Contributor Alias | Relevance | Blamed lines |\n";
+	output += "| -------------- | --------------- | --------------- |\n";
+	for relevance in relevance_vec {
+		output += &format!("| {} | {}% | {:.2}% |\n",
+			relevance.git_alias(), relevance.relevance_str(), relevance.raw_relevance_num());
+	}
+	output += "\n## Hunks\n\n";
+	output += "| File | Lines | Author |\n";
+	output += "| -------------- | --------------- | --------------- |\n";
+	for prhunk in hunkmap.prhunkvec() {
+		for blame in prhunk.blamevec() {
+			output += &format!("| {} | {}-{} | {} <{}> |\n",
+				blame.filepath_raw(), blame.line_start(), blame.line_end(), blame.author_name(), blame.author());
+		}
+	}
+	if !excluded_files.is_empty() {
+		output += "\n## Excluded files\n\n";
+		output += &excluded_files_text(excluded_files);
+	}
+	return output;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn to_args(args: &[&str]) -> Vec<String> {
+		args.iter().map(|arg| arg.to_string()).collect()
+	}
+
+	fn required_args(extra: &[&str]) -> Vec<String> {
+		let mut args = to_args(&["--repo", "r", "--base", "a", "--head", "b"]);
+		args.extend(to_args(extra));
+		return args;
+	}
+
+	#[test]
+	fn repo_base_and_head_are_required() {
+		assert_eq!(parse_review_args(&to_args(&["--repo", "/tmp/repo", "--base", "abc", "--head", "def"])), Ok(ReviewArgs {
+			repo: "/tmp/repo".to_string(),
+			base: "abc".to_string(),
+			head: "def".to_string(),
+			format: OutputFormat::Json,
+			config: None,
+		}));
+		assert!(parse_review_args(&to_args(&["--repo", "r", "--base", "a"])).is_err());
+		assert!(parse_review_args(&to_args(&["--repo"])).is_err());
+		assert!(parse_review_args(&required_args(&["--exclude", "*[bot]"])).is_err());
+	}
+
+	#[test]
+	fn format_is_parsed() {
+		let markdown = parse_review_args(&required_args(&["--format", "markdown"]));
+		assert_eq!(markdown.map(|args| args.format), Ok(OutputFormat::Markdown));
+		assert!(parse_review_args(&required_args(&["--format", "xml"])).is_err());
+	}
+
+	#[test]
+	fn config_file_overrides_repo_config_defaults() {
+		let configured = parse_review_args(&required_args(&["--config", "vibi.json"]));
+		assert_eq!(configured.map(|args| args.config), Ok(Some("vibi.json".to_string())));
+		assert!(load_repo_config(&None).is_ok());
+
+		let config_path = std::env::temp_dir().join(format!("vibi-dpu-review-config-{}.json", std::process::id()));
+		let config_path_str = config_path.to_string_lossy().to_string();
+		fs::write(&config_path, r#"{"relevance_exclusions": ["*[bot]"], "blame_ignore_revs": ["f00d"]}"#).expect("write config");
+		let repo_config = load_repo_config(&Some(config_path_str.to_owned())).expect("repo config");
+		assert!(repo_config.comment());
+		assert_eq!(AuthorExclusions::from_repo_config(&repo_config), AuthorExclusions::new(&["*[bot]".to_string()]));
+		assert_eq!(BlameSettings::from_repo_config(&repo_config).ignore_revs(), &vec!["f00d".to_string()]);
+
+		fs::write(&config_path, r#"{"blame_ignore_revs": "f00d"}"#).expect("write config");
+		assert!(load_repo_config(&Some(config_path_str.to_owned())).is_err());
+		let _ = fs::remove_file(&config_path);
+		assert!(load_repo_config(&Some(config_path_str)).is_err());
+	}
+}
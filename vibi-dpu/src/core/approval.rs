@@ -3,11 +3,12 @@ use serde_json::Value;
 use crate::core::utils::get_access_token;
 use crate::db::repo_config::save_repo_config_to_db;
 use crate::utils::coverage::CoverageMap;
-use crate::github;
+use crate::{bitbucket, github};
 use crate::core;
 use crate::utils::relevance::Relevance;
 use crate::utils::repo_config::RepoConfig;
 use crate::{db::review::get_review_from_db, utils::user::ProviderEnum};
+use crate::utils::comment::CommentKind;
 
 
 pub async fn process_approval(deserialised_msg_data: &Value, repo_config_val: &Value,
@@ -27,8 +28,6 @@ pub async fn process_approval(deserialised_msg_data: &Value, repo_config_val: &V
         log::info!("Comment setting is turned off, not adding comment...");
         return;
     }
-    let pr_head_commit = deserialised_msg_data["review"]["commit_id"]
-        .to_string().trim_matches('"').to_string();
     let review_opt = get_review_from_db(&repo_name,
         &repo_owner, &repo_provider, &pr_number);
     if review_opt.is_none() {
@@ -45,17 +44,13 @@ pub async fn process_approval(deserialised_msg_data: &Value, repo_config_val: &V
 	let final_access_token = access_token.expect("Empty final access token opt");
 
     // get reviewer login array by getting pr all reviewer info from gh/bb
-    let mut reviewer_handles = Vec::<String>::new();
-    if repo_provider == ProviderEnum::Github.to_string().to_lowercase() {
-        let reviewer_handles_opt = github::prs::pr_reviewer_handles(
-            &repo_owner, &repo_name, &pr_number, &pr_head_commit, &final_access_token).await;
-        if reviewer_handles_opt.is_none(){
-            log::error!("[process_approval] no reviewers handles opt");
-            return;
-        }
-        reviewer_handles = reviewer_handles_opt.expect("Empty reviewer_handles_opt");
-
-    } //TODO: create a similar function for bitbucket too
+    let reviewer_handles_opt = approved_reviewer_handles(deserialised_msg_data,
+        repo_owner, repo_name, pr_number, repo_provider, &final_access_token).await;
+    if reviewer_handles_opt.is_none(){
+        log::error!("[process_approval] no reviewers handles opt");
+        return;
+    }
+    let reviewer_handles = reviewer_handles_opt.expect("Empty reviewer_handles_opt");
     // get coverage map aliases and their corresponding logins from db/server
     let relevance_vec_opt = review.relevance();
     if relevance_vec_opt.is_none() {
@@ -69,7 +64,28 @@ pub async fn process_approval(deserialised_msg_data: &Value, repo_config_val: &V
     // add comment
     let comment_text = approval_comment_text(&coverage_map_obj, relevance_vec, reviewer_handles);
     // get access token and call add_comment in gh/bb
-    core::github::comment::add_comment(&comment_text, &review, &final_access_token).await;
+    if repo_provider == ProviderEnum::Github.to_string().to_lowercase() {
+        core::github::comment::add_comment(&comment_text, &review, &final_access_token, &CommentKind::Coverage).await;
+    }
+    if repo_provider == ProviderEnum::Bitbucket.to_string().to_lowercase() {
+        bitbucket::comment::add_comment(&comment_text, &review, &final_access_token, &CommentKind::Coverage).await;
+    }
+}
+
+async fn approved_reviewer_handles(deserialised_msg_data: &Value, repo_owner: &str, repo_name: &str,
+        pr_number: &str, repo_provider: &str, access_token: &str) -> Option<Vec<String>> {
+    if repo_provider == ProviderEnum::Github.to_string().to_lowercase() {
+        let pr_head_commit = deserialised_msg_data["review"]["commit_id"]
+            .to_string().trim_matches('"').to_string();
+        return github::prs::pr_reviewer_handles(
+            repo_owner, repo_name, pr_number, &pr_head_commit, access_token).await;
+    }
+    if repo_provider == ProviderEnum::Bitbucket.to_string().to_lowercase() {
+        return bitbucket::prs::pr_approver_handles(
+            repo_owner, repo_name, pr_number, access_token).await;
+    }
+    log::error!("[approved_reviewer_handles] Approvals are not supported for provider: {}", repo_provider);
+    return None;
 }
 
 fn approval_comment_text(coverage_map: &CoverageMap, relevance_vec: Vec<Relevance>, reviewer_handles: Vec<String>) -> String {
@@ -0,0 +1,416 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::bitbucket::{self, user::author_from_commit};
+use crate::core::{github, gitlab};
+use crate::utils::exclusion::AuthorExclusions;
+use crate::utils::hunk::PrHunkItem;
+use crate::utils::relevance::Relevance;
+use crate::utils::repo_config::RepoConfig;
+use crate::utils::review::Review;
+use crate::utils::user::{BitbucketUser, ProviderEnum};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AssignmentLimits {
+    max_reviewers: Option<usize>,
+    max_open_reviews: Option<usize>,
+    min_relevance: f32,
+}
+
+impl AssignmentLimits {
+    pub fn new(max_reviewers: Option<usize>, max_open_reviews: Option<usize>, min_relevance: Option<f32>) -> Self {
+        return Self { max_reviewers, max_open_reviews, min_relevance: min_relevance.unwrap_or(0.0) };
+    }
+
+    pub fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        return Self::new(repo_config.auto_assign_max_reviewers(),
+            repo_config.auto_assign_max_open_reviews(),
+            repo_config.auto_assign_min_relevance());
+    }
+
+    // Checks that don't need the provider, in the order they are applied
+    fn skip_reason(&self, candidate: &Candidate, assigned_count: usize) -> Option<SkipReason> {
+        if candidate.relevance < self.min_relevance {
+            return Some(SkipReason::BelowThreshold(self.min_relevance));
+        }
+        if let Some(max_reviewers) = self.max_reviewers {
+            if assigned_count >= max_reviewers {
+                return Some(SkipReason::CapReached(max_reviewers));
+            }
+        }
+        return None;
+    }
+
+    fn load_skip_reason(&self, open_reviews_opt: Option<usize>) -> Option<SkipReason> {
+        let max_open_reviews = self.max_open_reviews?;
+        let open_reviews = open_reviews_opt?;
+        if open_reviews > max_open_reviews {
+            return Some(SkipReason::Overloaded(open_reviews, max_open_reviews));
+        }
+        return None;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    BelowThreshold(f32),
+    CapReached(usize),
+    Overloaded(usize, usize),
+    NoHandle,
+    Author,
+    Excluded,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::BelowThreshold(min_relevance) => write!(f, "relevance below {:.2}%", min_relevance),
+            SkipReason::CapReached(max_reviewers) => write!(f, "limit of {} reviewers reached", max_reviewers),
+            SkipReason::Overloaded(open_reviews, max_open_reviews) =>
+                write!(f, "{} open review requests, limit {}", open_reviews, max_open_reviews),
+            SkipReason::NoHandle => write!(f, "no assignable profile handle"),
+            SkipReason::Author => write!(f, "PR author"),
+            SkipReason::Excluded => write!(f, "excluded"),
+        }
+    }
+}
+
+// Relevance entries grouped by the account that would be assigned
+#[derive(Debug, Clone, PartialEq)]
+struct Candidate {
+    name: String,
+    reviewer_id: Option<String>,
+    git_aliases: Vec<String>,
+    relevance: f32,
+    code_owner: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectedReviewer {
+    name: String,
+    reviewer_id: String,
+    relevance: f32,
+    open_reviews: Option<usize>,
+    bitbucket_user: Option<BitbucketUser>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReviewerSelection {
+    assigned: Vec<SelectedReviewer>,
+    skipped: Vec<(String, SkipReason)>,
+}
+
+impl ReviewerSelection {
+    pub fn assigned_ids(&self) -> Vec<String> {
+        return self.assigned.iter().map(|reviewer| reviewer.reviewer_id.to_owned()).collect();
+    }
+
+    // Explains who was picked and why the others were not
+    pub fn comment_text(&self) -> String {
+        if self.assigned.is_empty() {
+            let mut comment = "No reviewers were auto-assigned.".to_string();
+            comment += &self.skipped_text();
+            return comment;
+        }
+        let assigned: Vec<String> = self.assigned.iter().map(|reviewer| {
+            match reviewer.open_reviews {
+                Some(open_reviews) => format!("{} ({:.2}%, {} open review requests)",
+                    reviewer.name, reviewer.relevance, open_reviews),
+                None => format!("{} ({:.2}%)", reviewer.name, reviewer.relevance),
+            }
+        }).collect();
+        let mut comment = format!("Auto-assigned to the most relevant reviewers: {}.", assigned.join(", "));
+        comment += &self.skipped_text();
+        return comment;
+    }
+
+    fn skipped_text(&self) -> String {
+        if self.skipped.is_empty() {
+            return String::new();
+        }
+        let skipped: Vec<String> = self.skipped.iter()
+            .map(|(name, reason)| format!("{} ({})", name, reason))
+            .collect();
+        return format!(" Not assigned: {}.", skipped.join(", "));
+    }
+}
+
+// GitHub and GitLab can only request teams and usernames, not emails
+pub(crate) fn assignable_handle(relevance_obj: &Relevance) -> Option<String> {
+    return relevance_obj.handles().as_ref()?
+        .iter()
+        .find(|handle| !handle.contains('/') && !handle.contains('@'))
+        .map(|handle| handle.to_owned());
+}
+
+// Profile handles are case-insensitive on GitHub and GitLab
+fn handle_skip_reason(candidate: &Candidate, author: &str) -> Option<SkipReason> {
+    let reviewer_id = match &candidate.reviewer_id {
+        Some(reviewer_id) => reviewer_id,
+        None => return Some(SkipReason::NoHandle),
+    };
+    if reviewer_id.eq_ignore_ascii_case(author) {
+        return Some(SkipReason::Author);
+    }
+    return None;
+}
+
+// Candidates ranked by relevance, code owners first on ties and then by name
+// so the same relevance always picks the same reviewers. Bitbucket reviewers
+// are resolved from blamed commits later, so they are grouped by git alias.
+fn ranked_candidates(relevance_vec: &[Relevance], provider: &str) -> Vec<Candidate> {
+    let resolve_later = provider == ProviderEnum::Bitbucket.to_string();
+    let mut candidates = Vec::<Candidate>::new();
+    for relevance_obj in relevance_vec {
+        let reviewer_id = match resolve_later {
+            true => None,
+            false => assignable_handle(relevance_obj),
+        };
+        let name = reviewer_id.to_owned().unwrap_or(relevance_obj.git_alias().to_owned());
+        let existing_opt = candidates.iter_mut().find(|candidate| candidate.name == name);
+        if let Some(existing) = existing_opt {
+            existing.relevance += relevance_obj.relevance_num();
+            existing.code_owner |= relevance_obj.code_owner();
+            existing.git_aliases.push(relevance_obj.git_alias().to_owned());
+            continue;
+        }
+        candidates.push(Candidate {
+            name,
+            reviewer_id,
+            git_aliases: vec![relevance_obj.git_alias().to_owned()],
+            relevance: relevance_obj.relevance_num(),
+            code_owner: relevance_obj.code_owner(),
+        });
+    }
+    candidates.sort_by(|a, b| b.relevance.partial_cmp(&a.relevance)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| b.code_owner.cmp(&a.code_owner))
+        .then_with(|| a.name.cmp(&b.name)));
+    return candidates;
+}
+
+pub async fn select_reviewers(relevance_vec: &[Relevance], prhunk: &PrHunkItem, review: &Review,
+        limits: &AssignmentLimits, exclusions: &AuthorExclusions, access_token: &str) -> ReviewerSelection {
+    let mut selection = ReviewerSelection::default();
+    let mut bitbucket_users = HashMap::<String, BitbucketUser>::new();
+    for candidate in ranked_candidates(relevance_vec, review.provider()) {
+        if let Some(reason) = limits.skip_reason(&candidate, selection.assigned.len()) {
+            selection.skipped.push((candidate.name, reason));
+            continue;
+        }
+        let mut candidate_name = candidate.name.to_owned();
+        let mut bitbucket_user_opt = None;
+        let reviewer_id;
+        if review.provider().to_string() == ProviderEnum::Bitbucket.to_string() {
+            let user_opt = bitbucket_user_for(&candidate, prhunk, review, &mut bitbucket_users).await;
+            if user_opt.is_none() {
+                selection.skipped.push((candidate.name, SkipReason::NoHandle));
+                continue;
+            }
+            let user = user_opt.expect("Empty user_opt");
+            if user.uuid() == prhunk.author() {
+                selection.skipped.push((candidate.name, SkipReason::Author));
+                continue;
+            }
+            if exclusions.matches(user.uuid()) || exclusions.matches(user.display_name()) {
+                selection.skipped.push((candidate.name, SkipReason::Excluded));
+                continue;
+            }
+            reviewer_id = user.uuid().to_owned();
+            candidate_name = user.display_name().to_owned();
+            bitbucket_user_opt = Some(user);
+        } else {
+            if let Some(reason) = handle_skip_reason(&candidate, prhunk.author()) {
+                selection.skipped.push((candidate.name, reason));
+                continue;
+            }
+            reviewer_id = candidate.reviewer_id.to_owned().expect("Empty reviewer_id");
+        }
+        // Several git aliases can resolve to the same Bitbucket account
+        if let Some(assigned) = selection.assigned.iter_mut().find(|assigned| assigned.reviewer_id == reviewer_id) {
+            assigned.relevance += candidate.relevance;
+            continue;
+        }
+        let open_reviews_opt = match limits.max_open_reviews {
+            Some(_) => open_review_requests(&reviewer_id, review, access_token).await,
+            None => None,
+        };
+        if let Some(reason) = limits.load_skip_reason(open_reviews_opt) {
+            selection.skipped.push((candidate_name, reason));
+            continue;
+        }
+        selection.assigned.push(SelectedReviewer {
+            name: candidate_name,
+            reviewer_id,
+            relevance: candidate.relevance,
+            open_reviews: open_reviews_opt,
+            bitbucket_user: bitbucket_user_opt,
+        });
+    }
+    log::debug!("[select_reviewers] selection = {:?}", &selection);
+    return selection;
+}
+
+// Bitbucket account of the first blamed commit by any of the candidate's aliases
+async fn bitbucket_user_for(candidate: &Candidate, prhunk: &PrHunkItem, review: &Review,
+        bitbucket_users: &mut HashMap<String, BitbucketUser>) -> Option<BitbucketUser> {
+    let blame_opt = prhunk.blamevec().iter()
+        .find(|blame| candidate.git_aliases.contains(blame.author()));
+    let blame = blame_opt?;
+    if let Some(user) = bitbucket_users.get(blame.author()) {
+        return Some(user.to_owned());
+    }
+    let user_opt = author_from_commit(blame.commit(), review.repo_name(), review.repo_owner()).await;
+    if user_opt.is_none() {
+        log::error!("[bitbucket_user_for] Unable to get blame author from bb for commit: {}", blame.commit());
+        return None;
+    }
+    let user = user_opt.expect("Empty user_opt");
+    bitbucket_users.insert(blame.author().to_owned(), user.to_owned());
+    return Some(user);
+}
+
+async fn open_review_requests(reviewer_id: &str, review: &Review, access_token: &str) -> Option<usize> {
+    let open_reviews_opt = if review.provider().to_string() == ProviderEnum::Github.to_string() {
+        crate::github::prs::open_review_request_count(reviewer_id, review.repo_owner(), access_token).await
+    } else if review.provider().to_string() == ProviderEnum::Gitlab.to_string() {
+        crate::gitlab::prs::open_review_request_count(reviewer_id, access_token).await
+    } else {
+        bitbucket::prs::open_review_request_count(review.repo_owner(), review.repo_name(),
+            reviewer_id, access_token).await
+    };
+    if open_reviews_opt.is_none() {
+        // Don't hold back a reviewer just because their load is unknown
+        log::error!("[open_review_requests] Unable to get open review requests for {}", reviewer_id);
+    }
+    return open_reviews_opt;
+}
+
+pub async fn assign_reviewers(selection: &ReviewerSelection, review: &Review, access_token: &str) {
+    if selection.assigned.is_empty() {
+        log::info!("No reviewers selected for auto-assignment on repo {}...", review.repo_name());
+        return;
+    }
+    if review.provider().to_string() == ProviderEnum::Github.to_string() {
+        github::reviewer::add_reviewers(&selection.assigned_ids(), review, access_token).await;
+    } else if review.provider().to_string() == ProviderEnum::Gitlab.to_string() {
+        gitlab::reviewer::add_reviewers(&selection.assigned_ids(), review, access_token).await;
+    } else if review.provider().to_string() == ProviderEnum::Bitbucket.to_string() {
+        for reviewer in &selection.assigned {
+            if let Some(user) = &reviewer.bitbucket_user {
+                bitbucket::reviewer::add_reviewers(user, review, access_token).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relevance(alias: &str, handles: &[&str], num: f32, code_owner: bool) -> Relevance {
+        let handles = handles.iter().map(|handle| handle.to_string()).collect();
+        let mut relevance_obj = Relevance::new("github".to_string(), alias.to_string(),
+            format!("{:.2}", num), num, num, Some(handles));
+        relevance_obj.set_code_owner(code_owner);
+        return relevance_obj;
+    }
+
+    #[test]
+    fn candidates_are_ranked_deterministically_and_limited() {
+        let relevance_vec = vec![
+            relevance("carol@example.com", &["carol"], 20.0, false),
+            relevance("bob@example.com", &["bob"], 20.0, true),
+            relevance("alice@example.com", &["alice"], 30.0, false),
+            relevance("alice@work.com", &["alice"], 15.0, false),
+            relevance("dan@example.com", &["dan@example.com"], 10.0, false),
+            relevance("erin@example.com", &["erin"], 5.0, false),
+        ];
+        let candidates = ranked_candidates(&relevance_vec, "github");
+        let ranked: Vec<(&str, f32)> = candidates.iter()
+            .map(|candidate| (candidate.name.as_str(), candidate.relevance)).collect();
+        assert_eq!(ranked, vec![("alice", 45.0), ("bob", 20.0), ("carol", 20.0),
+            ("dan@example.com", 10.0), ("erin", 5.0)]);
+        assert_eq!(candidates[3].reviewer_id, None);
+
+        let limits = AssignmentLimits::new(Some(2), Some(5), Some(8.0));
+        assert_eq!(limits.skip_reason(&candidates[1], 1), None);
+        assert_eq!(limits.skip_reason(&candidates[2], 2), Some(SkipReason::CapReached(2)));
+        assert_eq!(limits.skip_reason(&candidates[4], 0), Some(SkipReason::BelowThreshold(8.0)));
+        assert_eq!(limits.load_skip_reason(Some(5)), None);
+        assert_eq!(limits.load_skip_reason(Some(6)), Some(SkipReason::Overloaded(6, 5)));
+        assert_eq!(limits.load_skip_reason(None), None);
+        assert_eq!(AssignmentLimits::default().skip_reason(&candidates[4], 10), None);
+    }
+
+    #[test]
+    fn pr_author_is_not_assigned() {
+        let relevance_vec = vec![
+            relevance("alice@example.com", &["Alice"], 60.0, false),
+            relevance("bob@example.com", &["bob"], 30.0, false),
+            relevance("dan@example.com", &["dan@example.com"], 10.0, false),
+        ];
+        let candidates = ranked_candidates(&relevance_vec, "github");
+        assert_eq!(handle_skip_reason(&candidates[0], "alice"), Some(SkipReason::Author));
+        assert_eq!(handle_skip_reason(&candidates[1], "alice"), None);
+        assert_eq!(handle_skip_reason(&candidates[2], "alice"), Some(SkipReason::NoHandle));
+    }
+
+    #[tokio::test]
+    async fn github_webhook_author_is_skipped() {
+        let payload = serde_json::json!({
+            "repositoryProvider": "github",
+            "eventPayload": {
+                "repository": {"name": "repo", "owner": {"login": "org"}},
+                "pull_request": {
+                    "number": 7,
+                    "base": {"sha": "base"},
+                    "head": {"sha": "head"},
+                    "user": {"id": 1001, "login": "alice"}
+                }
+            }
+        });
+        let review = crate::core::review::github_review(&payload, String::new(), String::new());
+        let prhunk = PrHunkItem::new(review.id().to_string(), review.author().to_string(), Vec::new());
+        let relevance_vec = vec![
+            relevance("alice@example.com", &["Alice"], 60.0, false),
+            relevance("bob@example.com", &["bob"], 40.0, false),
+        ];
+        let selection = select_reviewers(&relevance_vec, &prhunk, &review, &AssignmentLimits::default(),
+            &AuthorExclusions::default(), "").await;
+        assert_eq!(selection.assigned_ids(), vec!["bob".to_string()]);
+        assert_eq!(selection.skipped.iter().map(|(name, reason)| (name.as_str(), reason.clone())).collect::<Vec<_>>(),
+            vec![("Alice", SkipReason::Author)]);
+    }
+
+    #[tokio::test]
+    async fn gitlab_webhook_author_is_skipped() {
+        let repo = crate::utils::repo::Repository::new("repo".to_string(), "42".to_string(), "group".to_string(),
+            true, "git@gitlab.com:group/repo.git".to_string(), None, None, "group".to_string(),
+            Some("/tmp/group/repo".to_string()), "gitlab".to_string());
+        crate::db::repo::save_repo_to_db(&repo);
+        let pr_info = crate::utils::pr_info::PrInfo { base_head_commit: "base".to_string(),
+            pr_head_commit: "head".to_string(), state: "opened".to_string(), pr_branch: "feature".to_string(),
+            author: Some("alice".to_string()) };
+        crate::db::prs::update_pr_info_in_db("group", "repo", &pr_info, "7", "gitlab").await;
+        let payload = serde_json::json!({
+            "repositoryProvider": "gitlab",
+            "eventPayload": {
+                "project": {"path_with_namespace": "group/repo"},
+                "object_attributes": {"iid": 7, "author_id": 1001}
+            }
+        });
+        let (review_opt, _) = crate::core::review::create_and_save_gitlab_review_object(&payload);
+        let review = review_opt.expect("gitlab review");
+        assert_eq!(review.author(), "alice");
+        let prhunk = PrHunkItem::new(review.id().to_string(), review.author().to_string(), Vec::new());
+        let relevance_vec = vec![
+            relevance("alice@example.com", &["alice"], 60.0, false),
+            relevance("bob@example.com", &["bob"], 40.0, false),
+        ];
+        let selection = select_reviewers(&relevance_vec, &prhunk, &review, &AssignmentLimits::default(),
+            &AuthorExclusions::default(), "").await;
+        assert_eq!(selection.assigned_ids(), vec!["bob".to_string()]);
+    }
+}
@@ -11,7 +11,7 @@ use crate::bitbucket::prs::{list_prs_bitbucket, get_and_store_pr_info};
 use crate::core::utils::send_aliases;
 use crate::core::utils::user_selected_repos;
 use crate::core::utils::UserSelectedRepo;
-use crate::db::webhook::save_webhook_to_db;
+use crate::db::webhook::save_bitbucket_webhook_to_db;
 use crate::utils::gitops::get_git_aliases;
 use crate::utils::repo::Repository;
 use crate::utils::setup_info::SetupInfo;
@@ -149,5 +149,5 @@ async fn process_webhooks(workspace_slug: String, repo_name: String, access_toke
 	}
 	let webhook = matching_webhook.expect("no matching webhook");
 	log::info!("Not adding webhook, already exists: {:?}...", webhook.url());
-	save_webhook_to_db(&webhook);
+	save_bitbucket_webhook_to_db(&webhook);
 }
\ No newline at end of file
@@ -1,37 +1,79 @@
-use crate::graph::mermaid_elements::generate_mermaid_flowchart;
+use std::env;
+
+use base64ct::{Base64Url, Encoding};
+
+use crate::bitbucket;
+use crate::graph::code_parser::GraphBackend;
+use crate::graph::elements::MermaidGraphElements;
+use crate::graph::mermaid_elements::{generate_graph_elements, generate_mermaid_flowchart, mermaid_flowchart};
+use crate::utils::repo_config::RepoConfig;
 use crate::utils::user::ProviderEnum;
 use crate::utils::review::Review;
-use crate::core::github;
+use crate::core::{github, gitlab};
 use crate::utils::gitops::StatItem;
+use crate::utils::comment::CommentKind;
+
+// Longer image urls get rejected by browsers and image proxies, fall back to the table
+const MAX_IMAGE_URL_LEN: usize = 8000;
+
+// How the graph is posted on providers that don't render mermaid
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiffGraphFormat {
+    Table,
+    Text,
+    Image,
+}
 
-pub async fn send_diff_graph(review: &Review, excluded_files: &Vec<StatItem>, small_files: &Vec<StatItem>, access_token: &str) {
-	let comment = diff_graph_comment_text(excluded_files, small_files, review).await;
+impl DiffGraphFormat {
+    fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        if let Some(format) = repo_config.diff_graph_format() {
+            if format.eq_ignore_ascii_case("text") {
+                return DiffGraphFormat::Text;
+            }
+            if format.eq_ignore_ascii_case("image") {
+                return DiffGraphFormat::Image;
+            }
+        }
+        return DiffGraphFormat::Table;
+    }
+}
+
+pub async fn send_diff_graph(review: &Review, repo_config: &RepoConfig, excluded_files: &Vec<StatItem>, small_files: &Vec<StatItem>, access_token: &str) {
+	let backend = GraphBackend::from_repo_config(repo_config);
 	// add comment for GitHub
 	if review.provider().to_string() == ProviderEnum::Github.to_string() {
+		let comment = diff_graph_comment_text(excluded_files, small_files, review, &backend).await;
 		log::info!("Inserting comment on repo {}...", review.repo_name());
-		github::comment::add_comment(&comment, review, &access_token).await;
+		github::comment::add_comment(&comment, review, &access_token, &CommentKind::DiffGraph).await;
+	}
+	// GitLab renders mermaid natively
+	if review.provider().to_string() == ProviderEnum::Gitlab.to_string() {
+		let comment = diff_graph_comment_text(excluded_files, small_files, review, &backend).await;
+		log::info!("Inserting comment on repo {}...", review.repo_name());
+		gitlab::comment::add_comment(&comment, review, &access_token, &CommentKind::DiffGraph).await;
+	}
+	// Bitbucket Cloud doesn't render mermaid
+	if review.provider().to_string() == ProviderEnum::Bitbucket.to_string() {
+		let format = DiffGraphFormat::from_repo_config(repo_config);
+		let comment = bitbucket_diff_graph_comment_text(excluded_files, small_files, review, &backend, format).await;
+		log::info!("Inserting comment on repo {}...", review.repo_name());
+		bitbucket::comment::add_comment(&comment, review, &access_token, &CommentKind::DiffGraph).await;
 	}
-
-	// TODO: add comment for Bitbucket
 }
 
-async fn diff_graph_comment_text(excluded_files: &Vec<StatItem>, small_files: &Vec<StatItem>, review: &Review) -> String {
+async fn diff_graph_comment_text(excluded_files: &Vec<StatItem>, small_files: &Vec<StatItem>, review: &Review, backend: &GraphBackend) -> String {
     let mut comment = "Diff Graph:\n\n".to_string();  
     
-    let all_diff_files: Vec<StatItem> = excluded_files
-        .iter()
-        .chain(small_files.iter())
-        .cloned()  // Clone the StatItem instances since `iter` returns references
-        .collect(); // Collect into a new vector
-    if let Some(mermaid_text) = mermaid_comment(&all_diff_files, review).await {
+    let all_diff_files = all_diff_files(excluded_files, small_files);
+    if let Some(mermaid_text) = mermaid_comment(&all_diff_files, review, backend).await {
         comment += mermaid_text.as_str();
     }
     comment += "\nTo modify DiffGraph settings, go to [your Vibinex settings page.](https://vibinex.com/settings)\n";
     return comment;
 }
 
-async fn mermaid_comment(diff_files: &Vec<StatItem>, review: &Review) -> Option<String> {
-    let flowchart_str_opt = generate_mermaid_flowchart(diff_files, review).await;
+async fn mermaid_comment(diff_files: &Vec<StatItem>, review: &Review, backend: &GraphBackend) -> Option<String> {
+    let flowchart_str_opt = generate_mermaid_flowchart(diff_files, review, backend).await;
     if flowchart_str_opt.is_none() {
         log::error!("[mermaid_comment] Unable to generate flowchart for review: {}", review.id());
         return None;
@@ -44,3 +86,55 @@ async fn mermaid_comment(diff_files: &Vec<StatItem>, review: &Review) -> Option<
     return Some(mermaid_comment);
 }
 
+async fn bitbucket_diff_graph_comment_text(excluded_files: &Vec<StatItem>, small_files: &Vec<StatItem>,
+        review: &Review, backend: &GraphBackend, format: DiffGraphFormat) -> String {
+    let mut comment = "Diff Graph:\n\n".to_string();
+    let all_diff_files = all_diff_files(excluded_files, small_files);
+    if let Some(graph_elems) = generate_graph_elements(&all_diff_files, review, backend).await {
+        comment += "### Call Stack Diff\n";
+        comment += &render_graph(&graph_elems, review, format);
+    }
+    comment += "\nTo modify DiffGraph settings, go to [your Vibinex settings page.](https://vibinex.com/settings)\n";
+    return comment;
+}
+
+fn render_graph(graph_elems: &MermaidGraphElements, review: &Review, format: DiffGraphFormat) -> String {
+    match format {
+        DiffGraphFormat::Table => graph_elems.render_table(),
+        DiffGraphFormat::Text => graph_elems.render_text(),
+        DiffGraphFormat::Image => {
+            let base_url_opt = image_base_url();
+            if base_url_opt.is_none() {
+                log::warn!("[render_graph] DIFF_GRAPH_IMAGE_BASE_URL is not set, posting table for review: {}", review.id());
+                return graph_elems.render_table();
+            }
+            let base_url = base_url_opt.expect("Empty base_url_opt");
+            let image_url = mermaid_image_url(&base_url, &mermaid_flowchart(graph_elems, review));
+            if image_url.len() > MAX_IMAGE_URL_LEN {
+                log::warn!("[render_graph] Diff graph too large for an image url, posting table for review: {}", review.id());
+                return graph_elems.render_table();
+            }
+            format!("![Call Stack Diff]({})\n", image_url)
+        }
+    }
+}
+
+// The flowchart source is sent to the renderer, so there is no default one:
+// images are only posted once DIFF_GRAPH_IMAGE_BASE_URL names a renderer the
+// operator trusts, such as a self-hosted mermaid.ink
+fn image_base_url() -> Option<String> {
+    return env::var("DIFF_GRAPH_IMAGE_BASE_URL").ok()
+        .filter(|url| !url.trim().is_empty());
+}
+
+fn mermaid_image_url(base_url: &str, flowchart: &str) -> String {
+    return format!("{}/{}", base_url.trim_end_matches('/'), Base64Url::encode_string(flowchart.as_bytes()));
+}
+
+fn all_diff_files(excluded_files: &[StatItem], small_files: &[StatItem]) -> Vec<StatItem> {
+    return excluded_files
+        .iter()
+        .chain(small_files.iter())
+        .cloned()  // Clone the StatItem instances since `iter` returns references
+        .collect(); // Collect into a new vector
+}
@@ -1,6 +1,8 @@
+use std::time::Instant;
+
 use serde_json::json;
 
-use crate::{utils::{review::Review, reqwest_client::get_client}, github::config::{github_base_url, prepare_headers}};
+use crate::{metrics::registry::{record_provider_response, record_reviewers_assigned}, utils::{review::Review, reqwest_client::get_client}, github::config::{github_base_url, prepare_headers}};
 
 pub async fn add_reviewers(reviewers: &Vec<String>, review: &Review, access_token: &str) {
     let url = format!("{}/repos/{}/{}/pulls/{}/requested_reviewers",
@@ -18,12 +20,17 @@ pub async fn add_reviewers(reviewers: &Vec<String>, review: &Review, access_toke
     });
     let body = body_json.to_string();
     let client = get_client();
+    let request_start = Instant::now();
     let response_res = client.post(url).headers(headers).body(body).send().await;
+    record_provider_response("github", &response_res, request_start.elapsed());
     if response_res.is_err() {
         let e = response_res.expect_err("No error in response_res");
         log::error!("[add_reviewers] Unable to add reviewers: {:?}, {:?}", e, &reviewers);
         return;
     }
     let response = response_res.expect("Uncaught error in response_res");
+    if response.status().is_success() {
+        record_reviewers_assigned("github", reviewers.len());
+    }
     log::debug!("[add_reviewers] Added reviewers, response: {:?}", response.text().await);
 }
\ No newline at end of file
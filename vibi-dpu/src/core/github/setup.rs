@@ -16,7 +16,7 @@ use crate::utils::setup_info::SetupInfo;
 use crate::github::repos::get_github_app_installed_repos;
 use crate::utils::gitops::clone_git_repo;
 use crate::github::webhook::{get_webhooks_in_repo, add_webhook};
-use crate::db::webhook::save_webhook_to_db;
+use crate::db::webhook::save_github_webhook_to_db;
 use crate::core::utils::send_setup_info;
 
 pub async fn handle_install_github(installation_code: &str) {
@@ -108,7 +108,7 @@ async fn process_webhooks(repo_owner: String, repo_name: String, access_token: S
 	}
 	let webhook = matching_webhook.expect("no matching webhook");
 	log::debug!("[process_webhooks] Webhook already exists: {:?}", &webhook);
-	save_webhook_to_db(&webhook);
+	save_github_webhook_to_db(&webhook);
 }
 
 async fn process_prs(repo_owner_async: &String, repo_name_async: &String, access_token_async: &String) {
@@ -196,7 +196,7 @@ pub async fn setup_self_host_user_repos_github(access_token: &str) {
 	send_setup_info(&pubreq_vec).await;
 }
 
-fn parse_pat_repos(message_data: &[u8]) -> Option<Vec<Repository>> {
+pub fn parse_pat_repos(message_data: &[u8]) -> Option<Vec<Repository>> {
 	let data_res = serde_json::from_slice::<Vec<Value>>(&message_data);
 	if data_res.is_err() {
 		let e = data_res.expect_err("No error in data_res");
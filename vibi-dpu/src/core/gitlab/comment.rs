@@ -0,0 +1,109 @@
+use std::time::Instant;
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::db::comment::{get_comment_id_from_db, save_comment_id_to_db};
+use crate::metrics::registry::{record_comment_edited, record_comment_posted, record_provider_response};
+use crate::utils::comment::{CommentEdit, CommentKind};
+use crate::{gitlab::config::{gitlab_base_url, prepare_headers, project_path_id}, utils::{review::Review, reqwest_client::get_client}};
+
+// Edits the note of this kind already posted on the MR, or posts a new one
+// if there is none or it was deleted.
+pub async fn add_comment(comment_text: &str, review: &Review, access_token: &str, kind: &CommentKind) {
+    if let Some(comment_id) = get_comment_id_from_db(review, kind) {
+        let edit = edit_comment(&comment_id, comment_text, review, access_token).await;
+        if edit != CommentEdit::Missing {
+            return;
+        }
+        log::info!("[gitlab/add_comment] {} note {} was deleted, posting a new one", kind, &comment_id);
+    }
+    let comment_id_opt = post_comment(comment_text, review, access_token).await;
+    if let Some(comment_id) = comment_id_opt {
+        save_comment_id_to_db(review, kind, &comment_id);
+    }
+}
+
+async fn post_comment(comment_text: &str, review: &Review, access_token: &str) -> Option<String> {
+    let url = prepare_add_comment_url(review);
+    let comment_payload = prepare_body(comment_text);
+    let client = get_client();
+    let headers_opt = prepare_headers(access_token);
+    if headers_opt.is_none() {
+        log::error!("[gitlab/post_comment] Unable to prepare_headers_comment, empty headers_opt");
+        return None;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let request_start = Instant::now();
+    let response_res = client.post(&url).
+        headers(headers).json(&comment_payload).send().await;
+    record_provider_response("gitlab", &response_res, request_start.elapsed());
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[gitlab/post_comment] Error in post request for adding comment - {:?}", e);
+        return None;
+    }
+    let response = response_res.expect("Error in getting response");
+    log::debug!("[gitlab/post_comment] response from comment post request = {:?}", &response);
+    if !response.status().is_success() {
+        log::error!("[gitlab/post_comment] Unable to add comment, status: {}", response.status());
+        return None;
+    }
+    record_comment_posted("gitlab");
+    let response_json = response.json::<Value>().await.unwrap_or_default();
+    return response_json["id"].as_u64().map(|id| id.to_string());
+}
+
+async fn edit_comment(comment_id: &str, comment_text: &str, review: &Review, access_token: &str) -> CommentEdit {
+    let url = prepare_edit_comment_url(review, comment_id);
+    let comment_payload = prepare_body(comment_text);
+    let headers_opt = prepare_headers(access_token);
+    if headers_opt.is_none() {
+        log::error!("[gitlab/edit_comment] Unable to prepare_headers_comment, empty headers_opt");
+        return CommentEdit::Failed;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let request_start = Instant::now();
+    let response_res = get_client().put(&url).
+        headers(headers).json(&comment_payload).send().await;
+    record_provider_response("gitlab", &response_res, request_start.elapsed());
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[gitlab/edit_comment] Error in put request for editing comment - {:?}", e);
+        return CommentEdit::Failed;
+    }
+    let response = response_res.expect("Error in getting response");
+    log::debug!("[gitlab/edit_comment] response from comment put request = {:?}", &response);
+    if response.status() == StatusCode::NOT_FOUND {
+        return CommentEdit::Missing;
+    }
+    if !response.status().is_success() {
+        log::error!("[gitlab/edit_comment] Unable to edit note {}, status: {}", comment_id, response.status());
+        return CommentEdit::Failed;
+    }
+    record_comment_edited("gitlab");
+    return CommentEdit::Edited;
+}
+
+fn prepare_add_comment_url(review: &Review) -> String {
+    let url = format!(
+        "{}/projects/{}/merge_requests/{}/notes",
+        gitlab_base_url(),
+        project_path_id(review.repo_owner(), review.repo_name()),
+        review.id()
+    );
+    log::debug!("[gitlab/prepare_add_comment_url] comment url = {}", &url);
+    return url;
+}
+
+fn prepare_edit_comment_url(review: &Review, comment_id: &str) -> String {
+    let url = format!("{}/{}", prepare_add_comment_url(review), comment_id);
+    log::debug!("[gitlab/prepare_edit_comment_url] comment url = {}", &url);
+    return url;
+}
+
+fn prepare_body(comment_text: &str) -> Value {
+    return json!({
+        "body": comment_text
+    });
+}
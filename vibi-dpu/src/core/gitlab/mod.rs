@@ -0,0 +1,3 @@
+pub mod setup;
+pub mod comment;
+pub mod reviewer;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::{metrics::registry::{record_provider_response, record_reviewers_assigned}, gitlab::{config::{get_api_response, gitlab_base_url, prepare_headers, project_path_id}, user::get_user_id}, utils::{review::Review, reqwest_client::get_client}};
+
+pub async fn add_reviewers(reviewers: &Vec<String>, review: &Review, access_token: &str) {
+    let url = format!("{}/projects/{}/merge_requests/{}",
+        gitlab_base_url(),
+        project_path_id(review.repo_owner(), review.repo_name()),
+        review.id());
+    // GitLab assigns reviewers by user id, handles are usernames
+    let mut new_reviewer_ids = Vec::<u64>::new();
+    for reviewer in reviewers {
+        if let Some(user_id) = get_user_id(reviewer, access_token).await {
+            new_reviewer_ids.push(user_id);
+        } else { log::error!("[gitlab/add_reviewers] Unable to get user id for {}", reviewer); }
+    }
+    if new_reviewer_ids.is_empty() {
+        return;
+    }
+    // reviewer_ids replaces the whole list, so keep the reviewers already on the MR
+    let mut reviewer_ids = existing_reviewer_ids(&url, access_token).await;
+    let mut added_count = 0;
+    for user_id in new_reviewer_ids {
+        if !reviewer_ids.contains(&user_id) {
+            reviewer_ids.push(user_id);
+            added_count += 1;
+        }
+    }
+    let headers_opt = prepare_headers(access_token);
+    if headers_opt.is_none() {
+        return;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let body = json!({
+        "reviewer_ids": reviewer_ids
+    });
+    let client = get_client();
+    let request_start = Instant::now();
+    let response_res = client.put(url).headers(headers).json(&body).send().await;
+    record_provider_response("gitlab", &response_res, request_start.elapsed());
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[gitlab/add_reviewers] Unable to add reviewers: {:?}, {:?}", e, &reviewers);
+        return;
+    }
+    let response = response_res.expect("Uncaught error in response_res");
+    if response.status().is_success() {
+        record_reviewers_assigned("gitlab", added_count);
+    }
+    log::debug!("[gitlab/add_reviewers] Added reviewers, response: {:?}", response.text().await);
+}
+
+async fn existing_reviewer_ids(mr_url: &str, access_token: &str) -> Vec<u64> {
+    let response_opt = get_api_response(mr_url, access_token, &None).await;
+    if response_opt.is_none() {
+        log::error!("[gitlab/existing_reviewer_ids] Unable to get merge request: {}", mr_url);
+        return Vec::new();
+    }
+    let response = response_opt.expect("Empty response_opt");
+    let mr_res = response.json::<Value>().await;
+    if mr_res.is_err() {
+        let e = mr_res.expect_err("No error in mr_res");
+        log::error!("[gitlab/existing_reviewer_ids] Unable to deserialize merge request: {:?}", e);
+        return Vec::new();
+    }
+    let mr = mr_res.expect("Uncaught error in mr_res");
+    return mr["reviewers"].as_array()
+        .map(|reviewers| reviewers.iter().filter_map(|reviewer| reviewer["id"].as_u64()).collect())
+        .unwrap_or_default();
+}
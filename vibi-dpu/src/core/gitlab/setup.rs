@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::env;
+use tokio::task;
+
+use crate::core::github::setup::parse_pat_repos;
+use crate::core::utils::{send_aliases, send_setup_info};
+use crate::db::webhook::save_gitlab_webhook_to_db;
+use crate::gitlab::prs::{get_and_store_pr_info, list_prs_gitlab};
+use crate::gitlab::repos::get_user_gitlab_repos;
+use crate::gitlab::webhook::{add_webhook, get_webhooks_in_repo};
+use crate::utils::gitops::{clone_git_repo, get_git_aliases};
+use crate::utils::setup_info::SetupInfo;
+use crate::utils::user::ProviderEnum;
+
+pub async fn setup_self_host_user_repos_gitlab(access_token: &str) {
+	log::info!("Getting all user's Gitlab projects...");
+	let repos_opt = get_user_gitlab_repos(access_token).await;
+	if repos_opt.is_none() {
+		log::error!("[setup_self_host_user_repos_gitlab] No projects found for the user");
+		return;
+	}
+	let repos = repos_opt.expect("Empty repos option");
+	log::debug!("[setup_self_host_user_repos_gitlab] Got repos: {:?}", repos);
+	let mut repo_owner_map: HashMap<String, Vec<String>> = HashMap::new();
+	for repo in repos {
+		repo_owner_map
+			.entry(repo.owner().to_string())
+			.or_default()
+			.push(repo.name().to_string());
+	}
+	let mut pubreq_vec = Vec::<SetupInfo>::new();
+	for (repo_owner, repo_names) in repo_owner_map {
+		pubreq_vec.push(SetupInfo {
+			provider: ProviderEnum::Gitlab.to_string(),
+			owner: repo_owner,
+			repos: repo_names,
+		});
+	}
+	send_setup_info(&pubreq_vec).await;
+}
+
+pub async fn process_pat_repos(message_data: &[u8]) {
+	let repos_opt = parse_pat_repos(message_data);
+	if repos_opt.is_none() {
+		log::error!("[gitlab/process_pat_repos] Failed to parse PAT repos data");
+		return;
+	}
+	let repos = repos_opt.expect("Empty repos option");
+	let repo_provider = ProviderEnum::Gitlab.to_string();
+	let access_token = env::var("GITLAB_PAT").expect("GITLAB_PAT must be set");
+	for repo in repos {
+		let mut repo_copy = repo.clone();
+		clone_git_repo(&mut repo_copy, &access_token, &repo_provider).await;
+		if let Some(aliases) = get_git_aliases(&repo_copy) {
+			send_aliases(&repo, &aliases).await;
+		} else { log::error!("[gitlab/process_pat_repos] Unable to get aliases for repo: {}", repo.name()); }
+		let repo_name = repo.name();
+		let repo_owner = repo.owner();
+		process_webhooks(repo_owner, repo_name, &access_token).await;
+		let repo_name_async = repo_name.clone();
+		let repo_owner_async = repo_owner.clone();
+		let access_token_async = access_token.clone();
+		task::spawn(async move {
+			process_prs(&repo_owner_async, &repo_name_async, &access_token_async).await;
+		});
+	}
+}
+
+async fn process_webhooks(repo_owner: &str, repo_name: &str, access_token: &str) {
+	log::info!("Processing webhooks for : {}...", repo_name);
+	let webhooks_data_opt = get_webhooks_in_repo(repo_owner, repo_name, access_token).await;
+	if webhooks_data_opt.is_none() {
+		log::error!("[gitlab/process_webhooks] Unable to get webhooks for repo: {}/{}", repo_owner, repo_name);
+		return;
+	}
+	let webhooks_data = webhooks_data_opt.expect("Empty webhooks_data_opt");
+	let webhook_callback_url = format!("{}/api/gitlab/callbacks/webhook",
+		env::var("SERVER_URL").expect("SERVER_URL must be set"));
+	let matching_webhook = webhooks_data.into_iter()
+		.find(|w| *w.url() == webhook_callback_url && w.merge_requests_events());
+	if matching_webhook.is_none() {
+		add_webhook(repo_owner, repo_name, access_token).await;
+		return;
+	}
+	let webhook = matching_webhook.expect("no matching webhook");
+	log::debug!("[gitlab/process_webhooks] Webhook already exists: {:?}", &webhook);
+	save_gitlab_webhook_to_db(&webhook);
+}
+
+async fn process_prs(repo_owner: &str, repo_name: &str, access_token: &str) {
+	log::info!("Processing all open merge requests...");
+	let pr_list_opt = list_prs_gitlab(repo_owner, repo_name, access_token, "opened").await;
+	if pr_list_opt.is_none() {
+		log::warn!("Unable to get any open merge requests for processing.");
+		return;
+	}
+	let pr_list = pr_list_opt.expect("Empty pr_list_opt");
+	for pr_id in pr_list {
+		let repo_owner_async = repo_owner.to_string();
+		let repo_name_async = repo_name.to_string();
+		let access_token_async = access_token.to_string();
+		task::spawn(async move {
+			get_and_store_pr_info(&repo_owner_async, &repo_name_async, &access_token_async, &pr_id).await;
+		});
+	}
+}
@@ -2,7 +2,9 @@ pub mod review;
 pub mod relevance;
 pub mod utils;
 pub mod approval;
+pub mod assignment;
 pub mod bitbucket;
 pub mod github;
+pub mod gitlab;
 pub mod trigger;
 pub mod diff_graph;
\ No newline at end of file
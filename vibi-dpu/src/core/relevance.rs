@@ -1,49 +1,65 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::{bitbucket::{self, user::author_from_commit}, core::github, db::review::save_review_to_db, utils::{aliases::get_login_handles, gitops::StatItem, hunk::{HunkMap, PrHunkItem}, relevance::Relevance, user::ProviderEnum}};
+use chrono::Utc;
+
+use crate::{bitbucket, core::{github, gitlab}, db::review::save_review_to_db, utils::{aliases::get_login_handles, gitops::StatItem, hunk::{HunkMap, LineKind, PrHunkItem}, relevance::Relevance, user::ProviderEnum}};
 use crate::utils::review::Review;
 use crate::utils::repo_config::RepoConfig;
+use crate::utils::comment::CommentKind;
+use crate::core::assignment::{assign_reviewers, select_reviewers, AssignmentLimits, ReviewerSelection};
+use crate::utils::codeowners::review_code_owners;
+use crate::utils::exclusion::AuthorExclusions;
+use crate::utils::file_exclusion::excluded_files_text;
 
-pub async fn process_relevance(hunkmap: &HunkMap, excluded_files: &Vec<StatItem>, review: &Review,
+pub async fn process_relevance(hunkmap: &HunkMap, excluded_files: &Vec<StatItem>, code_owners: &[String], review: &Review,
 	repo_config: &mut RepoConfig, access_token: &str, old_review_opt: &Option<Review>,
 ) {
 	log::info!("Processing relevance of code authors...");
+	let scoring = RelevanceScoring::from_repo_config(repo_config);
+	let exclusions = AuthorExclusions::from_repo_config(repo_config);
+	let code_owners_policy = CodeOwnersPolicy::from_repo_config(repo_config);
+	let limits = AssignmentLimits::from_repo_config(repo_config);
     log::debug!("Process relevence for PR: {}, repo config: {:?}", review.id(), repo_config);
 	for prhunk in hunkmap.prhunkvec() {
 		// calculate number of hunks for each userid
 		let mut review_mut = review.clone();
 		// old review needs to be accesed before calculate_relevance,
 		// which changes relevance vector in db
-		let relevance_vec_opt = calculate_relevance(prhunk, &mut review_mut).await;
+		let excluded_aliases = excluded_aliases(prhunk, &exclusions, review).await;
+		let relevance_vec_opt = calculate_relevance(prhunk, &mut review_mut, &scoring, &excluded_aliases,
+			code_owners, code_owners_policy).await;
 		if relevance_vec_opt.is_none() {
 			log::error!("[process_relevance] Unable to calculate coverage obj");
 			continue;
 		}
 		let relevance_vec = relevance_vec_opt.expect("Empty coverage_obj_opt");
+		let selection_opt = match repo_config.auto_assign() {
+			true => Some(select_reviewers(&relevance_vec, prhunk, review, &limits, &exclusions, access_token).await),
+			false => None,
+		};
 		if repo_config.comment() {
 			if did_comment_change(&relevance_vec, &old_review_opt) {
                 // create comment text
-                let comment = relevant_reviewers_comment_text(&relevance_vec, repo_config.auto_assign(), excluded_files).await;
+                let comment = relevant_reviewers_comment_text(&relevance_vec, &selection_opt, excluded_files, &scoring, &excluded_aliases, code_owners_policy).await;
                 // add comment
                 if review.provider().to_string() == ProviderEnum::Bitbucket.to_string() {
                         log::info!("Inserting comment on repo {}...", review.repo_name());
-                        bitbucket::comment::add_comment(&comment, review, &access_token).await;
+                        bitbucket::comment::add_comment(&comment, review, &access_token, &CommentKind::Relevance).await;
                 }
-                if review.provider().to_string() == ProviderEnum::Github.to_string() {
+                else if review.provider().to_string() == ProviderEnum::Github.to_string() {
                     log::info!("Inserting comment on repo {}...", review.repo_name());
-                    github::comment::add_comment(&comment, review, &access_token).await;
-                } else { log::info!("No changes in author relevance, not adding comment...");}
-            }
+                    github::comment::add_comment(&comment, review, &access_token, &CommentKind::Relevance).await;
+                }
+                else if review.provider().to_string() == ProviderEnum::Gitlab.to_string() {
+                    log::info!("Inserting comment on repo {}...", review.repo_name());
+                    gitlab::comment::add_comment(&comment, review, &access_token, &CommentKind::Relevance).await;
+                }
+            } else { log::info!("No changes in author relevance, not adding comment...");}
 		}
-		if repo_config.auto_assign() {
+		if let Some(selection) = &selection_opt {
 			log::info!("Auto assigning reviewers for repo {}...", review.repo_name());
 			log::debug!("[process_relevance] review.provider() = {:?}", review.provider());
-			if review.provider().to_string() == ProviderEnum::Bitbucket.to_string() {
-				add_bitbucket_reviewers(&prhunk, hunkmap, review, &access_token).await;
-			}
-			if review.provider().to_string() == ProviderEnum::Github.to_string() {
-				add_github_reviewers(review, &relevance_vec, &access_token).await;
-			}
+			assign_reviewers(selection, review, access_token).await;
 		}
 	}
 }
@@ -101,72 +117,39 @@ fn did_relevance_change(relevance_new: &[Relevance], relevance_old: &[Relevance]
     return false;
 }
 
-async fn add_github_reviewers(review: &Review, relevance_vec: &Vec<Relevance>, access_token: &str) {
-    let mut reviewers: HashSet<String> = HashSet::new();
-    for relevance_obj in relevance_vec {
-        let provider_ids_opt = relevance_obj.handles();
-        if provider_ids_opt.is_none() {
-            continue;
-        }
-        let provider_ids = provider_ids_opt.to_owned().expect("Empty provider_ids_opt");
-        let provider_id_opt = provider_ids.first();
-        if provider_id_opt.is_none() {
-            continue;
-        }
-        let provider_id = provider_id_opt.expect("Empty provider_id_opt");
-        reviewers.insert(provider_id.to_owned());
-    }
-    if reviewers.is_empty() {
-        return;
+// Aliases in the hunk matched by the exclusions, directly, by author name or
+// through their handles
+async fn excluded_aliases(prhunk: &PrHunkItem, exclusions: &AuthorExclusions, review: &Review) -> HashSet<String> {
+    let mut excluded_aliases = HashSet::<String>::new();
+    if exclusions.is_empty() {
+        return excluded_aliases;
     }
-    let reviewers_vec: Vec<String> = reviewers.into_iter().collect();
-    github::reviewer::add_reviewers(&reviewers_vec, review, access_token).await;
-}
-
-async fn add_bitbucket_reviewers(prhunk: &PrHunkItem, hunkmap: &HunkMap, review: &Review, access_token: &str) {
-    let mut author_set: HashSet<String> = HashSet::new();
-    author_set.insert(prhunk.author().to_string());
-    for blame in prhunk.blamevec() {
-        let blame_author_opt = author_from_commit(blame.commit(),
-            hunkmap.repo_name(), hunkmap.repo_owner()).await;
-        if blame_author_opt.is_none() {
-            log::error!("[process_relevance] Unable to get blame author from bb for commit: {}", &blame.commit());
+    let git_aliases: HashMap<&String, &String> = prhunk.blamevec().iter()
+        .map(|blame| (blame.author(), blame.author_name()))
+        .collect();
+    for (git_alias, author_name) in git_aliases {
+        if exclusions.matches(git_alias) || exclusions.matches(author_name) {
+            excluded_aliases.insert(git_alias.to_owned());
             continue;
         }
-        let blame_author = blame_author_opt.expect("Empty blame_author_opt");
-        let author_uuid = blame_author.uuid();
-        if author_set.contains(author_uuid) {
-            continue;
+        let handles = get_login_handles(git_alias, review).await;
+        if exclusions.excludes(git_alias, &handles) {
+            excluded_aliases.insert(git_alias.to_owned());
         }
-        bitbucket::reviewer::add_reviewers(&blame_author, review, &access_token).await;
-        author_set.insert(author_uuid.to_string());
     }
+    if !excluded_aliases.is_empty() {
+        log::info!("Excluding {} aliases from relevance...", excluded_aliases.len());
+        log::debug!("[excluded_aliases] excluded_aliases = {:?}", &excluded_aliases);
+    }
+    return excluded_aliases;
 }
 
-async fn calculate_relevance(prhunk: &PrHunkItem, review: &mut Review) -> Option<Vec<Relevance>>{
-    let mut relevance_floatmap = HashMap::<String, f32>::new();
-    let mut total = 0.0;
-    for blame in prhunk.blamevec() {
-        let author_id = blame.author().to_owned();
-        let num_lines: f32 = blame.line_end().parse::<f32>().expect("lines_end invalid float")
-            - blame.line_start().parse::<f32>().expect("lines_end invalid float")
-            + 1.0;
-        total += num_lines;
-        if relevance_floatmap.contains_key(&author_id) {
-            let relevance = relevance_floatmap.get(&author_id).expect("unable to find coverage for author")
-                + num_lines;
-            relevance_floatmap.insert(author_id, relevance);
-        }
-        else {
-            relevance_floatmap.insert(author_id, num_lines);
-        }
-    }
+async fn calculate_relevance(prhunk: &PrHunkItem, review: &mut Review, scoring: &RelevanceScoring,
+        excluded_aliases: &HashSet<String>, code_owners: &[String], code_owners_policy: CodeOwnersPolicy) -> Option<Vec<Relevance>>{
+    let relevance_floatmap = relevance_by_alias(prhunk, scoring, excluded_aliases, Utc::now().timestamp())
+        .unwrap_or_default();
     let mut relevance_vec = Vec::<Relevance>::new();
-    if total <= 0.0 {
-        return None;
-    } 
-    for (blame_author, relevance) in relevance_floatmap.iter_mut() {
-        *relevance = *relevance / total * 100.0;
+    for (blame_author, (raw_relevance, relevance)) in relevance_floatmap.iter() {
         let formatted_value = format!("{:.2}", *relevance);
         let provider_ids = get_login_handles(blame_author, review).await;
         let relevance_obj = Relevance::new(
@@ -174,30 +157,285 @@ async fn calculate_relevance(prhunk: &PrHunkItem, review: &mut Review) -> Option
             blame_author.to_owned(), 
             formatted_value.to_owned(), 
             *relevance, 
+            *raw_relevance,
             provider_ids);
         relevance_vec.push(relevance_obj);
     }
+    let relevance_vec = merge_code_owners(relevance_vec, code_owners, code_owners_policy, review.provider());
+    if relevance_vec.is_empty() {
+        return None;
+    }
     review.set_relevance(Some(relevance_vec.clone()));
     save_review_to_db(review);
     return Some(relevance_vec);
 }
 
-async fn relevant_reviewers_comment_text(relevance_vec: &Vec<Relevance>, auto_assign: bool,
-    excluded_files: &Vec<StatItem>) -> String {
+// How CODEOWNERS of the changed files are combined with blame based relevance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodeOwnersPolicy {
+    Off,
+    // Owners are always listed and assigned, even without blamed lines
+    Always,
+    // Owners win ties between equally relevant authors
+    Tiebreaker,
+    // Owners are used only when blame finds nobody
+    Fallback,
+}
+
+impl CodeOwnersPolicy {
+    pub fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        let policy = repo_config.codeowners_policy().to_owned().unwrap_or_default().to_lowercase();
+        match policy.as_str() {
+            "always" => CodeOwnersPolicy::Always,
+            "tiebreaker" => CodeOwnersPolicy::Tiebreaker,
+            "fallback" => CodeOwnersPolicy::Fallback,
+            _ => CodeOwnersPolicy::Off,
+        }
+    }
+
+    // Whether owners make it to the comment regardless of their relevance
+    fn lists_all_owners(&self) -> bool {
+        return *self == CodeOwnersPolicy::Always || *self == CodeOwnersPolicy::Fallback;
+    }
+}
+
+// Owners of every changed file, read only when the repo uses them
+pub fn changed_file_owners(review: &Review, repo_config: &RepoConfig,
+        excluded_files: &[StatItem], small_files: &[StatItem]) -> Vec<String> {
+    if CodeOwnersPolicy::from_repo_config(repo_config) == CodeOwnersPolicy::Off {
+        return Vec::new();
+    }
+    let changed_files: Vec<&StatItem> = excluded_files.iter().chain(small_files.iter()).collect();
+    let exclusions = AuthorExclusions::from_repo_config(repo_config);
+    return review_code_owners(review, &changed_files).into_iter()
+        .filter(|owner| !exclusions.matches(owner))
+        .collect();
+}
+
+fn merge_code_owners(mut relevance_vec: Vec<Relevance>, code_owners: &[String],
+        policy: CodeOwnersPolicy, provider: &str) -> Vec<Relevance> {
+    if policy == CodeOwnersPolicy::Off || (policy == CodeOwnersPolicy::Fallback && !relevance_vec.is_empty()) {
+        return relevance_vec;
+    }
+    for owner in code_owners {
+        let mut matched = false;
+        for relevance_obj in relevance_vec.iter_mut() {
+            if is_owner_entry(relevance_obj, owner) {
+                relevance_obj.set_code_owner(true);
+                matched = true;
+            }
+        }
+        if matched || policy == CodeOwnersPolicy::Tiebreaker {
+            continue;
+        }
+        let mut owner_relevance = Relevance::new(provider.to_string(), owner.to_owned(),
+            format!("{:.2}", 0.0), 0.0, 0.0, Some(vec![owner.to_owned()]));
+        owner_relevance.set_code_owner(true);
+        relevance_vec.push(owner_relevance);
+    }
+    return relevance_vec;
+}
+
+// CODEOWNERS lists handles or emails, blame entries have emails as git alias
+fn is_owner_entry(relevance_obj: &Relevance, owner: &str) -> bool {
+    if relevance_obj.git_alias().eq_ignore_ascii_case(owner) {
+        return true;
+    }
+    return relevance_obj.handles().as_ref()
+        .is_some_and(|handles| handles.iter().any(|handle| handle.eq_ignore_ascii_case(owner)));
+}
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+// Weight of a blamed line by why it was blamed, so lines the PR only sits
+// next to count for less than the ones it changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineWeights {
+    changed: f64,
+    context: f64,
+    function: f64,
+}
+
+impl LineWeights {
+    pub fn new(changed: Option<f64>, context: Option<f64>, function: Option<f64>) -> Self {
+        let valid = |weight: Option<f64>, default: f64| weight.filter(|weight| *weight >= 0.0).unwrap_or(default);
+        Self {
+            changed: valid(changed, 1.0),
+            context: valid(context, 0.5),
+            function: valid(function, 0.25),
+        }
+    }
+
+    fn weight(&self, line_kind: LineKind) -> f64 {
+        match line_kind {
+            LineKind::Changed => self.changed,
+            LineKind::Context => self.context,
+            LineKind::Function => self.function,
+        }
+    }
+}
+
+impl Default for LineWeights {
+    fn default() -> Self {
+        return Self::new(None, None, None);
+    }
+}
+
+// How blamed lines are weighted into relevance. Without a half-life every line
+// of a kind counts the same, and without an inactivity cutoff every author is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RelevanceScoring {
+    half_life_days: Option<f64>,
+    inactive_days: Option<f64>,
+    line_weights: LineWeights,
+}
+
+impl RelevanceScoring {
+    pub fn new(half_life_days: Option<f64>, inactive_days: Option<f64>) -> Self {
+        Self {
+            half_life_days: half_life_days.filter(|days| *days > 0.0),
+            inactive_days: inactive_days.filter(|days| *days > 0.0),
+            line_weights: LineWeights::default(),
+        }
+    }
+
+    pub fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        return Self::new(
+            repo_config.relevance_half_life_days().map(f64::from),
+            repo_config.relevance_inactive_days().map(f64::from))
+            .with_line_weights(LineWeights::new(
+                repo_config.relevance_changed_weight().map(f64::from),
+                repo_config.relevance_context_weight().map(f64::from),
+                repo_config.relevance_function_weight().map(f64::from)));
+    }
+
+    pub fn with_line_weights(mut self, line_weights: LineWeights) -> Self {
+        self.line_weights = line_weights;
+        return self;
+    }
+
+    // Weighted relevance differs from the raw share of blamed lines
+    pub fn is_weighted(&self) -> bool {
+        return self.half_life_days.is_some() || self.line_weights != LineWeights::default();
+    }
+
+    fn line_weight(&self, age_days: f64, line_kind: LineKind) -> f64 {
+        let kind_weight = self.line_weights.weight(line_kind);
+        match self.half_life_days {
+            Some(half_life_days) => kind_weight * 0.5f64.powf(age_days / half_life_days),
+            None => kind_weight,
+        }
+    }
+
+    fn is_inactive(&self, newest_age_days: f64) -> bool {
+        return self.inactive_days.is_some_and(|inactive_days| newest_age_days > inactive_days);
+    }
+}
+
+// Days since the blamed commit, blame timestamps are unix seconds (--date=unix).
+// Unparseable timestamps count as fresh so they don't get decayed away.
+fn blame_age_days(timestamp: &str, now_secs: i64) -> f64 {
+    let timestamp_res = timestamp.trim().parse::<i64>();
+    if timestamp_res.is_err() {
+        log::debug!("[blame_age_days] Unable to parse blame timestamp: {}", timestamp);
+        return 0.0;
+    }
+    let age_secs = now_secs - timestamp_res.expect("Uncaught error in timestamp_res");
+    return age_secs.max(0) as f64 / SECONDS_PER_DAY;
+}
+
+// Raw and weighted percentage of blamed lines per git alias, without any handle
+// lookups or db writes. Lines of excluded aliases are dropped, so their share
+// goes to everyone else. The raw share is over all remaining blamed lines, the
+// weighted share, by age and line kind, only over authors who are still active.
+pub fn relevance_by_alias(prhunk: &PrHunkItem, scoring: &RelevanceScoring,
+        excluded_aliases: &HashSet<String>, now_secs: i64) -> Option<HashMap<String, (f32, f32)>> {
+    let mut raw_lines = HashMap::<String, f64>::new();
+    let mut weighted_lines = HashMap::<String, f64>::new();
+    let mut newest_age_days = HashMap::<String, f64>::new();
+    let mut raw_total = 0.0;
+    for blame in prhunk.blamevec() {
+        if excluded_aliases.contains(blame.author()) {
+            continue;
+        }
+        let author_id = blame.author().to_owned();
+        let num_lines: f64 = blame.line_end().parse::<f64>().expect("lines_end invalid float")
+            - blame.line_start().parse::<f64>().expect("lines_end invalid float")
+            + 1.0;
+        let age_days = blame_age_days(blame.timestamp(), now_secs);
+        raw_total += num_lines;
+        *raw_lines.entry(author_id.to_owned()).or_insert(0.0) += num_lines;
+        *weighted_lines.entry(author_id.to_owned()).or_insert(0.0) += num_lines * scoring.line_weight(age_days, blame.line_kind());
+        let newest_age = newest_age_days.entry(author_id).or_insert(age_days);
+        *newest_age = newest_age.min(age_days);
+    }
+    if raw_total <= 0.0 {
+        return None;
+    }
+    let mut active_authors: Vec<&String> = raw_lines.keys()
+        .filter(|author| !scoring.is_inactive(newest_age_days[*author]))
+        .collect();
+    if active_authors.is_empty() {
+        log::debug!("[relevance_by_alias] All authors are inactive, keeping them all");
+        active_authors = raw_lines.keys().collect();
+    }
+    let mut weighted_total: f64 = active_authors.iter().map(|author| weighted_lines[*author]).sum();
+    if weighted_total <= 0.0 {
+        // Every line decayed to nothing, fall back to plain line counts
+        weighted_lines = raw_lines.clone();
+        weighted_total = active_authors.iter().map(|author| raw_lines[*author]).sum();
+    }
+    let mut relevance_floatmap = HashMap::<String, (f32, f32)>::new();
+    for author in active_authors {
+        let raw = raw_lines[author] / raw_total * 100.0;
+        let weighted = weighted_lines[author] / weighted_total * 100.0;
+        relevance_floatmap.insert(author.to_owned(), (raw as f32, weighted as f32));
+    }
+    return Some(relevance_floatmap);
+}
+
+async fn relevant_reviewers_comment_text(relevance_vec: &Vec<Relevance>, selection_opt: &Option<ReviewerSelection>,
+    excluded_files: &Vec<StatItem>, scoring: &RelevanceScoring, excluded_aliases: &HashSet<String>,
+    code_owners_policy: CodeOwnersPolicy) -> String {
     let mut comment = "Relevant users for this PR:\n\n".to_string();  // Added two newlines
-    comment += "| Contributor Name/Alias  | Relevance |\n";  // Added a newline at the end
-    comment += "| -------------- | --------------- |\n";  // Added a newline at the end
+    let mut header = vec!["Contributor Name/Alias ", "Relevance"];
+    if scoring.is_weighted() {
+        header.push("Blamed lines");
+    }
+    if code_owners_policy != CodeOwnersPolicy::Off {
+        header.push("Reason");
+    }
+    comment += &format!("| {} |\n", header.join(" | "));
+    comment += &format!("|{}\n", " -------------- |".repeat(header.len()));
 
     let (deduplicated_relevance_map, unmapped_aliases) = deduplicated_relevance_vec_for_comment(relevance_vec);
+    let is_code_owner = |provider_ids: &Vec<String>| relevance_entries_for(provider_ids, relevance_vec)
+        .any(|relevance_obj| relevance_obj.code_owner());
     let (filtered_relevance_vec, remaining_relevance_vec) = filter_deduplicated_relevance_map(
-        &deduplicated_relevance_map, 4, 0.1);
+        &deduplicated_relevance_map, 4, 0.1, is_code_owner, code_owners_policy.lists_all_owners());
     
     for (provider_ids, relevance) in &filtered_relevance_vec {
         let provider_id_opt = provider_ids.iter().next();
         if provider_id_opt.is_some() {
             let provider_id = provider_id_opt.expect("Empty provider_id_opt");
-            let formatted_relevance_value = format!("{:.2}", *relevance);
-            comment += &format!("| {} | {}% |\n", provider_id, formatted_relevance_value);
+            let mut row = vec![provider_id.to_owned(), format!("{:.2}%", *relevance)];
+            let raw_relevance: f32 = relevance_entries_for(provider_ids, relevance_vec)
+                .map(|relevance_obj| relevance_obj.raw_relevance_num())
+                .sum();
+            if scoring.is_weighted() {
+                row.push(format!("{:.2}%", raw_relevance));
+            }
+            if code_owners_policy != CodeOwnersPolicy::Off {
+                let mut reasons = Vec::<&str>::new();
+                if raw_relevance > 0.0 || **relevance > 0.0 {
+                    reasons.push("blame");
+                }
+                if is_code_owner(provider_ids) {
+                    reasons.push("code owner");
+                }
+                row.push(reasons.join(", "));
+            }
+            comment += &format!("| {} |\n", row.join(" | "));
         }
     }
 
@@ -206,17 +444,23 @@ async fn relevant_reviewers_comment_text(relevance_vec: &Vec<Relevance>, auto_as
         comment += &format!("Missing profile handles for {} aliases. [Go to your Vibinex settings page](https://vibinex.com/settings) to map aliases to profile handles.", unmapped_aliases.len());
     }
 
+    if !excluded_aliases.is_empty() {
+        let mut excluded_aliases_vec: Vec<&String> = excluded_aliases.iter().collect();
+        excluded_aliases_vec.sort();
+        comment += "\n\n";
+        comment += &format!("Excluded from relevance and auto-assignment: {}",
+            excluded_aliases_vec.iter().map(|alias| alias.as_str()).collect::<Vec<&str>>().join(", "));
+    }
+
     if !excluded_files.is_empty() {
         comment += "\n\n";
-        comment += "Ignoring following files due to large size: ";
-        for file_item in excluded_files {
-            comment += &format!("- {}\n", file_item.filepath.as_str());
-        }
+        comment += "Ignoring following files:\n";
+        comment += &excluded_files_text(excluded_files);
     }
 
-    if auto_assign {
+    if let Some(selection) = selection_opt {
         comment += "\n\n";
-        comment += "Auto assigning to relevant reviewers.";
+        comment += &selection.comment_text();
     }
     comment += "\n\n";
     comment += "If you are a relevant reviewer, you can use the [Vibinex browser extension](https://chromewebstore.google.com/detail/vibinex-code-review/jafgelpkkkopeaefadkdjcmnicgpcncc) to see parts of the PR relevant to you\n";  // Added a newline at the end
@@ -225,27 +469,36 @@ async fn relevant_reviewers_comment_text(relevance_vec: &Vec<Relevance>, auto_as
     return comment;
 }
 
+// Relevance entries merged under these handles for the comment
+fn relevance_entries_for<'a>(handles: &'a [String], relevance_vec: &'a [Relevance]) -> impl Iterator<Item = &'a Relevance> {
+    return relevance_vec.iter()
+        .filter(move |relevance_obj| match relevance_obj.handles() {
+            Some(provider_ids) => provider_ids.iter().any(|id| handles.contains(id)),
+            None => handles.contains(relevance_obj.git_alias()),
+        });
+}
+
 fn filter_deduplicated_relevance_map(deduplicated_relevance_map: &HashMap<Vec<String>, f32>,
-    k: usize, threshold: f32)
+    k: usize, threshold: f32, is_code_owner: impl Fn(&Vec<String>) -> bool, include_owners: bool)
 -> (Vec<(&Vec<String>, &f32)>, Vec<(&Vec<String>, &f32)>) {
-     // Step 1: Collect and sort by relevance (f32) in descending order
+     // Step 1: Collect and sort by relevance (f32) in descending order, code owners first on ties
      let mut deduplicated_relevance_vec: Vec<(&Vec<String>, &f32)> = deduplicated_relevance_map.iter().collect();
-     deduplicated_relevance_vec.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
- 
-     // Step 2: Split into top-k elements and everything else
-     let top_k_and_filtered: Vec<_> = deduplicated_relevance_vec
-         .iter()
-         .take(k)
-         .filter(|&&(_, &value)| value > threshold)
-         .cloned()
-         .collect();
+     deduplicated_relevance_vec.sort_by(|(a_ids, a), (b_ids, b)| b.partial_cmp(a)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then_with(|| is_code_owner(b_ids).cmp(&is_code_owner(a_ids))));
  
-     let remaining: Vec<_> = deduplicated_relevance_vec
-         .iter()
-         .skip(k)
-         .chain(deduplicated_relevance_vec.iter().take(k).filter(|&&(_, &value)| value <= threshold))
-         .cloned()
-         .collect();
+     // Step 2: Split into top-k elements above the threshold, plus owners if
+     // the policy lists them all, and everything else
+     let mut top_k_and_filtered = Vec::new();
+     let mut remaining = Vec::new();
+     for (idx, entry) in deduplicated_relevance_vec.into_iter().enumerate() {
+         let (provider_ids, value) = entry;
+         if (idx < k && *value > threshold) || (include_owners && is_code_owner(provider_ids)) {
+             top_k_and_filtered.push(entry);
+         } else {
+             remaining.push(entry);
+         }
+     }
  
      (top_k_and_filtered, remaining)
 }
@@ -285,3 +538,100 @@ pub fn deduplicated_relevance_vec_for_comment(relevance_vec: &Vec<Relevance>) ->
 
     (combined_relevance_map, unmapped_aliases)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hunk::BlameItem;
+    use crate::utils::lineitem::LineItem;
+    use crate::core::assignment::assignable_handle;
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 86400;
+
+    fn blame(author: &str, days_ago: i64, line_start: usize, line_end: usize) -> BlameItem {
+        let line_item = LineItem::new(author.to_string(), author.to_string(), (NOW - days_ago * DAY).to_string(),
+            "commit".to_string(), author.to_string(), line_start, "src/lib.rs".to_string());
+        return BlameItem::new(&line_item, line_start, line_end, "digest".to_string(), "src/lib.rs".to_string());
+    }
+
+    #[test]
+    fn old_lines_decay_and_inactive_authors_drop_off() {
+        let prhunk = PrHunkItem::new("1".to_string(), "author".to_string(), vec![
+            blame("old@example.com", 365, 1, 10),
+            blame("new@example.com", 0, 11, 20),
+            blame("gone@example.com", 1000, 21, 40),
+        ]);
+        let no_exclusions = HashSet::new();
+        let unweighted = relevance_by_alias(&prhunk, &RelevanceScoring::default(), &no_exclusions, NOW).expect("relevance");
+        assert_eq!(unweighted["gone@example.com"], (50.0, 50.0));
+
+        let scoring = RelevanceScoring::new(Some(365.0), Some(730.0));
+        let decayed = relevance_by_alias(&prhunk, &scoring, &no_exclusions, NOW).expect("relevance");
+        assert!(!decayed.contains_key("gone@example.com"));
+        let (old_raw, old_weighted) = decayed["old@example.com"];
+        let (new_raw, new_weighted) = decayed["new@example.com"];
+        assert_eq!((old_raw, new_raw), (25.0, 25.0));
+        // a year old line with a one year half-life weighs half a fresh one
+        assert!((new_weighted - 2.0 * old_weighted).abs() < 0.01);
+        assert!((old_weighted + new_weighted - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn excluded_aliases_lines_are_redistributed() {
+        let prhunk = PrHunkItem::new("1".to_string(), "author".to_string(), vec![
+            blame("alice@example.com", 0, 1, 10),
+            blame("49699333+dependabot[bot]@users.noreply.github.com", 0, 11, 40),
+            blame("bob@example.com", 0, 41, 50),
+        ]);
+        let excluded = HashSet::from(["49699333+dependabot[bot]@users.noreply.github.com".to_string()]);
+        let relevance = relevance_by_alias(&prhunk, &RelevanceScoring::default(), &excluded, NOW).expect("relevance");
+        assert_eq!(relevance.len(), 2);
+        assert_eq!(relevance["alice@example.com"], (50.0, 50.0));
+        assert_eq!(relevance["bob@example.com"], (50.0, 50.0));
+    }
+
+    #[test]
+    fn surrounding_lines_weigh_less_than_changed_ones() {
+        let prhunk = PrHunkItem::new("1".to_string(), "author".to_string(), vec![
+            blame("alice@example.com", 0, 1, 10),
+            blame("bob@example.com", 0, 11, 30).with_line_kind(LineKind::Context),
+            blame("carol@example.com", 0, 31, 70).with_line_kind(LineKind::Function),
+        ]);
+        let no_exclusions = HashSet::new();
+        let relevance = relevance_by_alias(&prhunk, &RelevanceScoring::default(), &no_exclusions, NOW).expect("relevance");
+        let close = |(raw, weighted): (f32, f32), expected: (f32, f32)|
+            (raw - expected.0).abs() < 0.01 && (weighted - expected.1).abs() < 0.01;
+        assert!(close(relevance["alice@example.com"], (10.0 / 70.0 * 100.0, 100.0 / 3.0)));
+        assert!(close(relevance["bob@example.com"], (20.0 / 70.0 * 100.0, 100.0 / 3.0)));
+        let only_changed = RelevanceScoring::default().with_line_weights(LineWeights::new(None, Some(0.0), Some(0.0)));
+        assert!(!RelevanceScoring::default().is_weighted());
+        assert!(only_changed.is_weighted());
+        let relevance = relevance_by_alias(&prhunk, &only_changed, &no_exclusions, NOW).expect("relevance");
+        assert_eq!(relevance["alice@example.com"].1, 100.0);
+        assert_eq!(relevance["carol@example.com"].1, 0.0);
+    }
+
+    #[test]
+    fn code_owners_merge_by_policy() {
+        let blamed = |alias: &str, handle: &str, num: f32| Relevance::new("github".to_string(), alias.to_string(),
+            format!("{:.2}", num), num, num, Some(vec![handle.to_string()]));
+        let relevance_vec = vec![blamed("alice@example.com", "alice", 60.0), blamed("bob@example.com", "bob", 40.0)];
+        let owners = vec!["Bob".to_string(), "org/docs-team".to_string()];
+
+        let always = merge_code_owners(relevance_vec.clone(), &owners, CodeOwnersPolicy::Always, "github");
+        let flags: Vec<(&str, bool)> = always.iter().map(|r| (r.git_alias().as_str(), r.code_owner())).collect();
+        assert_eq!(flags, vec![("alice@example.com", false), ("bob@example.com", true), ("org/docs-team", true)]);
+
+        let tiebreaker = merge_code_owners(relevance_vec.clone(), &owners, CodeOwnersPolicy::Tiebreaker, "github");
+        assert_eq!(tiebreaker.len(), 2);
+        assert!(tiebreaker[1].code_owner());
+
+        let fallback = merge_code_owners(relevance_vec.clone(), &owners, CodeOwnersPolicy::Fallback, "github");
+        assert!(fallback.iter().all(|r| !r.code_owner()));
+        let fallback_empty = merge_code_owners(Vec::new(), &owners, CodeOwnersPolicy::Fallback, "github");
+        assert_eq!(fallback_empty.len(), 2);
+        let handles: Vec<String> = fallback_empty.iter().filter_map(assignable_handle).collect();
+        assert_eq!(handles, vec!["Bob".to_string()]);
+    }
+}
@@ -3,15 +3,19 @@ use std::{env, thread, time::Duration};
 use serde_json::Value;
 
 use crate::{
-    core::{relevance::process_relevance, diff_graph::send_diff_graph, utils::get_access_token},
+    core::{relevance::{changed_file_owners, process_relevance}, diff_graph::send_diff_graph, utils::get_access_token},
+    metrics::registry::ReviewTimer,
     db::{
         hunk::{get_hunk_from_db, store_hunkmap_to_db},
+        prs::get_saved_pr_info,
         repo::get_clone_url_clone_dir,
         repo_config::save_repo_config_to_db,
         review::{get_review_from_db, save_review_to_db},
     },
     utils::{
-        gitops::{commit_exists, generate_blame, generate_diff, get_excluded_files, git_pull, process_diffmap, StatItem},
+        blame_settings::BlameSettings,
+        file_exclusion::FileExclusionRules,
+        gitops::{commit_exists, generate_blame, generate_diff, get_excluded_files, git_pull, process_diffmap, repo_lock, StatItem},
         hunk::{HunkMap, PrHunkItem},
         repo_config::RepoConfig,
         reqwest_client::get_client,
@@ -21,6 +25,7 @@ use crate::{
 };
 
 pub async fn process_review(message_data: &Vec<u8>) {
+	let _review_timer = ReviewTimer::start();
 	let (review_opt, old_review_opt) = parse_review(message_data);
 	if review_opt.is_none() {
 		log::error!("[process_review] Unable to deserialize review message and repo config");
@@ -46,19 +51,22 @@ pub async fn process_review(message_data: &Vec<u8>) {
 
 pub async fn process_review_changes(review: &Review, repo_config: &RepoConfig, access_token: &str, old_review_opt: &Option<Review>) {
 	log::info!("Processing changes in code...");
-	if let Some((excluded_files, smallfiles)) = get_included_and_excluded_files(review) {
-		let hunkmap_opt = calculate_hunkmap(review, &smallfiles).await;
-		send_hunkmap(&hunkmap_opt, &excluded_files, review, repo_config, access_token, old_review_opt).await;
+	let file_rules = FileExclusionRules::from_repo_config(repo_config);
+	let blame_settings = BlameSettings::from_repo_config(repo_config).with_ignore_revs_file(review);
+	if let Some((excluded_files, smallfiles)) = get_included_and_excluded_files(review, &file_rules, &blame_settings) {
+		let hunkmap_opt = calculate_hunkmap(review, &smallfiles, &blame_settings).await;
+		let code_owners = changed_file_owners(review, repo_config, &excluded_files, &smallfiles);
+		send_hunkmap(&hunkmap_opt, &excluded_files, &code_owners, review, repo_config, access_token, old_review_opt).await;
 		
 		if repo_config.diff_graph() {
-			send_diff_graph(review, &excluded_files, &smallfiles, access_token).await;
+			send_diff_graph(review, repo_config, &excluded_files, &smallfiles, access_token).await;
 		}
 	} else {
 		log::error!("Failed to get included and excluded files");
 	}
 }
 
-pub async fn send_hunkmap(hunkmap_opt: &Option<HunkMap>, excluded_files: &Vec<StatItem>, review: &Review,
+pub async fn send_hunkmap(hunkmap_opt: &Option<HunkMap>, excluded_files: &Vec<StatItem>, code_owners: &[String], review: &Review,
 	repo_config: &RepoConfig, access_token: &str, old_review_opt: &Option<Review>) {
 	if hunkmap_opt.is_none() {
 		log::error!("[send_hunkmap] Empty hunkmap in send_hunkmap");
@@ -71,7 +79,7 @@ pub async fn send_hunkmap(hunkmap_opt: &Option<HunkMap>, excluded_files: &Vec<St
 	let hunkmap_async = hunkmap.clone();
 	let review_async = review.clone();
 	let mut repo_config_clone = repo_config.clone();
-	process_relevance(&hunkmap_async, &excluded_files, &review_async,
+	process_relevance(&hunkmap_async, &excluded_files, code_owners, &review_async,
 		&mut repo_config_clone, access_token, old_review_opt).await;
 }
 
@@ -87,8 +95,9 @@ fn hunk_already_exists(review: &Review) -> bool {
 	return true;
 }
 
-fn get_included_and_excluded_files(review: &Review) -> Option<(Vec<StatItem>, Vec<StatItem>)> {
-	let fileopt = get_excluded_files(&review);
+pub fn get_included_and_excluded_files(review: &Review, file_rules: &FileExclusionRules,
+	blame_settings: &BlameSettings) -> Option<(Vec<StatItem>, Vec<StatItem>)> {
+	let fileopt = get_excluded_files(&review, file_rules, blame_settings);
 	log::debug!("[process_review_changes] fileopt = {:?}", &fileopt);
 	if fileopt.is_none() {
 		log::error!("[process_review_changes] No files to review for PR {}", review.id());
@@ -98,13 +107,13 @@ fn get_included_and_excluded_files(review: &Review) -> Option<(Vec<StatItem>, Ve
 	return Some(( excluded_files, smallfiles));
 }
 
-async fn calculate_hunkmap(review: &Review, smallfiles: &Vec<StatItem>) -> Option<HunkMap> {
+pub async fn calculate_hunkmap(review: &Review, smallfiles: &Vec<StatItem>, blame_settings: &BlameSettings) -> Option<HunkMap> {
 	let mut prvec = Vec::<PrHunkItem>::new();
-	let diffmap = generate_diff(&review, &smallfiles);
+	let diffmap = generate_diff(&review, &smallfiles, blame_settings);
 	log::debug!("[process_review_changes] diffmap = {:?}", &diffmap);
-	let linemap = process_diffmap(&diffmap);
+	let linemap = process_diffmap(&review, &diffmap, blame_settings).await;
 	log::debug!("[process_review_changes] linemap = {:?}", &linemap);
-	let blamevec = generate_blame(&review, &linemap).await;
+	let blamevec = generate_blame(&review, &linemap, blame_settings).await;
 	log::debug!("[process_review_changes] blamevec = {:?}", &blamevec);
 	let hmapitem = PrHunkItem::new(
 		review.id().to_string(),
@@ -117,12 +126,16 @@ async fn calculate_hunkmap(review: &Review, smallfiles: &Vec<StatItem>) -> Optio
 		review.repo_name().to_string(), 
 		prvec,
 		format!("{}/hunkmap", review.db_key()),
+		blame_settings.clone(),
 	);
 	log::debug!("[process_review_changes] hunkmap: {:?}", hunkmap);
 	return Some(hunkmap);
 }
 
 pub async fn commit_check(review: &Review, access_token: &str) {
+	// Another review of this repo may be pulling, its commits could be ours
+	let repo_lock = repo_lock(review.clone_dir());
+	let _repo_guard = repo_lock.lock().await;
 	if !commit_exists(&review.base_head_commit(), &review.clone_dir()) 
 		|| !commit_exists(&review.pr_head_commit(), &review.clone_dir()) {
 		log::info!("Executing git pull on repo {}...", &review.repo_name());
@@ -146,6 +159,8 @@ fn parse_review(message_data: &Vec<u8>) -> (Option<(Review, RepoConfig)>, Option
 		create_and_save_bitbucket_review_object(&deserialized_data)
 	} else if repo_provider == ProviderEnum::Github.to_string().to_lowercase() {
 		create_and_save_github_review_object(&deserialized_data)
+	} else if repo_provider == ProviderEnum::Gitlab.to_string().to_lowercase() {
+		create_and_save_gitlab_review_object(&deserialized_data)
 	} else {
 		(None, None)
 	};
@@ -237,20 +252,74 @@ fn create_and_save_github_review_object(deserialized_data: &Value) -> (Option<Re
 		return (None,old_review_opt);
 	}
 	let (clone_url, clone_dir) = clone_opt.expect("Empty clone_opt");
-	let review = Review::new(
+	let review = github_review(deserialized_data, clone_url, clone_dir);
+	log::debug!("[create_and_save_github_review_object] github review object = {:?}", &review);
+	save_review_to_db(&review);
+	return (Some(review), old_review_opt);
+}
+
+// The author is the PR opener's login, like pr info from the api, so it can
+// be compared with reviewer handles
+pub(crate) fn github_review(deserialized_data: &Value, clone_url: String, clone_dir: String) -> Review {
+	let repo_owner = deserialized_data["eventPayload"]["repository"]["owner"]["login"].to_string().trim_matches('"').to_string();
+	let repo_name = deserialized_data["eventPayload"]["repository"]["name"].to_string().trim_matches('"').to_string();
+	let pr_id = deserialized_data["eventPayload"]["pull_request"]["number"].to_string().trim_matches('"').to_string();
+	return Review::new(
 		deserialized_data["eventPayload"]["pull_request"]["base"]["sha"].to_string().replace("\"", ""),
 		deserialized_data["eventPayload"]["pull_request"]["head"]["sha"].to_string().replace("\"", ""),
 		pr_id.clone(),
 		repo_name.clone(),
 		repo_owner.clone(),
-		repo_provider.clone(),
+		ProviderEnum::Github.to_string().to_lowercase(),
 		format!("github/{}/{}/{}", &repo_owner, &repo_name, &pr_id),
 		clone_dir,
 		clone_url,
-		deserialized_data["eventPayload"]["pull_request"]["user"]["id"].to_string().replace("\"", ""),
+		deserialized_data["eventPayload"]["pull_request"]["user"]["login"].to_string().replace("\"", ""),
 		None,
 	);
-	log::debug!("[create_and_save_github_review_object] github review object = {:?}", &review);
+}
+
+pub(crate) fn create_and_save_gitlab_review_object(deserialized_data: &Value) -> (Option<Review>, Option<Review>) {
+	log::debug!("[create_and_save_gitlab_review_object] deserialised_data {}", deserialized_data);
+	let path_with_namespace = deserialized_data["eventPayload"]["project"]["path_with_namespace"].to_string().trim_matches('"').to_string();
+	let (repo_owner, repo_name) = match path_with_namespace.rsplit_once('/') {
+		Some((owner, name)) => (owner.to_string(), name.to_string()),
+		None => {
+			log::error!("[create_and_save_gitlab_review_object] Invalid project path: {}", &path_with_namespace);
+			return (None, None);
+		}
+	};
+	let repo_provider = ProviderEnum::Gitlab.to_string().to_lowercase();
+	let pr_id = deserialized_data["eventPayload"]["object_attributes"]["iid"].to_string().trim_matches('"').to_string();
+	let old_review_opt = get_review_from_db(&repo_name, &repo_owner, &repo_provider, &pr_id);
+	let clone_opt = get_clone_url_clone_dir(&repo_provider, &repo_owner, &repo_name);
+	if clone_opt.is_none() {
+		log::error!("[create_and_save_gitlab_review_object] Unable to get clone url and directory for gitlab review");
+		return (None, old_review_opt);
+	}
+	let (clone_url, clone_dir) = clone_opt.expect("Empty clone_opt");
+	// merge request hooks carry no base commit, pr info is fetched from the api before review
+	let pr_info_opt = get_saved_pr_info(&repo_provider, &repo_owner, &repo_name, &pr_id);
+	if pr_info_opt.is_none() {
+		log::error!("[create_and_save_gitlab_review_object] No pr info in db for {}", &path_with_namespace);
+		return (None, old_review_opt);
+	}
+	let pr_info = pr_info_opt.expect("Empty pr_info_opt");
+	let review = Review::new(
+		pr_info.base_head_commit.clone(),
+		pr_info.pr_head_commit.clone(),
+		pr_id.clone(),
+		repo_name.clone(),
+		repo_owner.clone(),
+		repo_provider.clone(),
+		format!("gitlab/{}/{}/{}", &repo_owner, &repo_name, &pr_id),
+		clone_dir,
+		clone_url,
+		// the hook only carries author_id, the api's username is what reviewers are matched on
+		pr_info.author.clone().unwrap_or_default(),
+		None,
+	);
+	log::debug!("[create_and_save_gitlab_review_object] gitlab review object = {:?}", &review);
 	save_review_to_db(&review);
 	return (Some(review), old_review_opt);
-}
\ No newline at end of file
+}
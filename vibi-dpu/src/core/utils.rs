@@ -8,6 +8,7 @@ use serde_json::Value;
 use crate::bitbucket;
 use crate::db::aliases::update_handles_in_db;
 use crate::github;
+use crate::gitlab;
 use crate::health::status::send_status_failed;
 use crate::health::status::send_status_success;
 use crate::utils::repo::Repository;
@@ -37,7 +38,9 @@ struct AliasResponse {
 struct AliasResponseHandles {
 	git_alias: String,
 	github: Option<Vec<String>>,
-	bitbucket: Option<Vec<String>>
+	bitbucket: Option<Vec<String>>,
+	#[serde(default)]
+	gitlab: Option<Vec<String>>
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -157,6 +160,13 @@ pub async fn get_handles_from_server(review: &Review) -> Option<HashMap<String,
 				let bb_handles = alias_handle.bitbucket.expect("Empty github handles");
 				update_handles_in_db(&alias_handle.git_alias, &review.provider(), bb_handles.clone());
 				aliases_map.insert(alias_handle.git_alias, bb_handles);
+				continue;
+		}
+		if review.provider().to_owned() == ProviderEnum::Gitlab.to_string()
+			&& alias_handle.gitlab.is_some() {
+				let gl_handles = alias_handle.gitlab.expect("Empty gitlab handles");
+				update_handles_in_db(&alias_handle.git_alias, &review.provider(), gl_handles.clone());
+				aliases_map.insert(alias_handle.git_alias, gl_handles);
 		}
 	}
 	if aliases_map.is_empty() {
@@ -187,8 +197,17 @@ pub async fn get_access_token (review: &Option<Review>, provider: &str) -> Optio
 			return None;
 		}
 		access_token = access_token_opt.expect("Empty access_token");
+	}
+	else if provider == ProviderEnum::Gitlab.to_string().to_lowercase() {
+		let access_token_opt = gitlab::auth::gitlab_access_token();
+		if access_token_opt.is_none() {
+			log::error!("[get_access_token] Unable to get access token, review: {:?}",
+				&review);
+			return None;
+		}
+		access_token = access_token_opt.expect("Empty access_token_opt");
 	} else {
-		log::error!("[git pull] | repo provider is not github or bitbucket");
+		log::error!("[git pull] | repo provider is not github, bitbucket or gitlab");
 		return None;
 	}
 	return Some(access_token);
@@ -1,28 +1,12 @@
-use sled::IVec;
 use std::collections::HashSet;
 use crate::db::config::get_db;
 
 fn save_handles_to_db(alias_key: &str, provider: &str, provider_login_ids: Vec<String>) {
-    let db = get_db();
-    let full_key = format!("{}/aliases/{}", provider, alias_key);
-    log::debug!("[save_handles_to_db] full_key = {}", &full_key);
-    let val_res = serde_json::to_vec(&provider_login_ids);
-    if let Err(e) = val_res {
-        log::error!(
-            "[save_handles_to_db] Failed to serialize provider login ids to json: {:?}",
-            e
-        );
-        return;
-    }
-    let val = val_res.expect("Uncaught error in val_res");
-    let insert_res = db.insert(
-        IVec::from(full_key.as_bytes()),
-        val,
-    );
+    let insert_res = get_db().save_aliases(provider, alias_key, &provider_login_ids);
     if let Err(e) = insert_res {
         log::error!(
-            "[save_handles_to_db] Failed to upsert aliases into sled DB: {:?}",
-            e
+            "[save_handles_to_db] Failed to upsert aliases for {} into db: {:?}",
+            alias_key, e
         );
         return;
     } 
@@ -33,34 +17,19 @@ fn save_handles_to_db(alias_key: &str, provider: &str, provider_login_ids: Vec<S
 }
 
 pub fn get_handles_from_db(alias_key: &str, provider: &str) -> Option<Vec<String>> {
-    let db = get_db();
-    let full_key = format!("{}/aliases/{}", provider, alias_key);
-    log::debug!("[get_handles_from_db] full_key = {}", &full_key);
-    
-    let result = db.get(IVec::from(full_key.as_bytes()));
+    let result = get_db().get_aliases(provider, alias_key);
     if let Err(e) = result {
         log::error!(
-            "[get_handles_from_db] Failed to retrieve aliases from sled DB: {:?}",
+            "[get_handles_from_db] Failed to retrieve aliases from db: {:?}",
             e
         );
         return None;
     }
-    let ivec_opt = result.expect("Uncaught error in result");
-    if ivec_opt.is_none() {
+    let alias_opt = result.expect("Uncaught error in result");
+    if alias_opt.is_none() {
         log::debug!("[get_handles_from_db] No aliases found for provider: {}", provider);
-        return None;
-    }
-    let ivec = ivec_opt.expect("Empty ivec_opt");
-    let alias_res = serde_json::from_slice(&ivec);
-    if let Err(e) = alias_res {
-        log::error!(
-            "[get_handles_from_db] Failed to deserialize aliases from json: {:?}",
-            e
-        );
-        return None;
     }
-    let alias: Vec<String> = alias_res.expect("Uncaught error in alias_res");
-    return Some(alias);
+    return alias_opt;
 }
 
 pub fn update_handles_in_db(alias_key: &str, provider: &str, new_logins: Vec<String>) -> Vec<String>{
@@ -0,0 +1,47 @@
+use crate::db::config::get_db;
+use crate::utils::comment::CommentKind;
+use crate::utils::review::Review;
+
+pub fn save_comment_id_to_db(review: &Review, kind: &CommentKind, comment_id: &str) {
+    let insert_res = get_db().save_comment_id(review, kind, comment_id);
+    if insert_res.is_err() {
+        let e = insert_res.expect_err("No error in insert_res");
+        log::error!("[save_comment_id_to_db] Failed to upsert {} comment id for {}: {}", kind, review.db_key(), e);
+        return;
+    }
+    log::debug!("[save_comment_id_to_db] Saved {} comment id {} for {}", kind, comment_id, review.db_key());
+}
+
+pub fn get_comment_id_from_db(review: &Review, kind: &CommentKind) -> Option<String> {
+    let comment_id_res = get_db().get_comment_id(review, kind);
+    if comment_id_res.is_err() {
+        let e = comment_id_res.expect_err("No error in comment_id_res");
+        log::error!("[get_comment_id_from_db] Failed to get {} comment id for {}: {}", kind, review.db_key(), e);
+        return None;
+    }
+    let comment_id_opt = comment_id_res.expect("Uncaught error in comment_id_res");
+    if comment_id_opt.is_none() {
+        log::debug!("[get_comment_id_from_db] No {} comment posted yet for {}", kind, review.db_key());
+    }
+    return comment_id_opt;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_ids_are_kept_per_kind() {
+        let review = Review::new("base".to_string(), "head".to_string(), "7".to_string(),
+            "repo".to_string(), "owner".to_string(), "github".to_string(),
+            "github/owner/repo/7".to_string(), "/tmp/repo".to_string(),
+            "https://example.com/repo.git".to_string(), "author".to_string(), None);
+        assert_eq!(get_comment_id_from_db(&review, &CommentKind::Relevance), None);
+        save_comment_id_to_db(&review, &CommentKind::Relevance, "101");
+        save_comment_id_to_db(&review, &CommentKind::DiffGraph, "102");
+        save_comment_id_to_db(&review, &CommentKind::Relevance, "103");
+        assert_eq!(get_comment_id_from_db(&review, &CommentKind::Relevance), Some("103".to_string()));
+        assert_eq!(get_comment_id_from_db(&review, &CommentKind::DiffGraph), Some("102".to_string()));
+        assert_eq!(get_comment_id_from_db(&review, &CommentKind::Coverage), None);
+    }
+}
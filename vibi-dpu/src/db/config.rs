@@ -1,14 +1,34 @@
-use std::sync::Mutex;
-
-static mut DB: Option<sled::Db> = None;
-static DB_MUTEX: Mutex<()> = Mutex::new(()); 
-
-pub fn get_db() -> &'static sled::Db {
-  let _lock = DB_MUTEX.lock().unwrap();
-  unsafe {
-    if DB.is_none() {
-      DB = Some(sled::open("/tmp/db").unwrap());
-    }
-    DB.as_ref().unwrap()
+use std::sync::Arc;
+
+use crate::db::store::Store;
+
+#[cfg(not(test))]
+const DEFAULT_DB_PATH: &str = "/tmp/db";
+
+#[cfg(not(test))]
+static STORE: once_cell::sync::OnceCell<Arc<dyn Store>> = once_cell::sync::OnceCell::new();
+
+// Opened on first use at DPU_DB_PATH, mount a volume there to keep data across restarts
+#[cfg(not(test))]
+pub fn get_db() -> Arc<dyn Store> {
+  return STORE.get_or_init(|| {
+    let db_path = std::env::var("DPU_DB_PATH")
+      .ok()
+      .filter(|path| !path.is_empty())
+      .unwrap_or_else(|| DEFAULT_DB_PATH.to_string());
+    log::info!("[get_db] Opening database at {}", &db_path);
+    let store = crate::db::store::SledStore::open(&db_path)
+      .unwrap_or_else(|e| panic!("Unable to open database at {}: {}", &db_path, e));
+    return Arc::new(store);
+  }).clone();
+}
+
+// Every test thread gets its own empty in-memory store
+#[cfg(test)]
+pub fn get_db() -> Arc<dyn Store> {
+  thread_local! {
+    static TEST_STORE: Arc<dyn Store> = Arc::new(crate::db::store::MemoryStore::new());
   }
-}
\ No newline at end of file
+  return TEST_STORE.with(|store| store.clone());
+}
+
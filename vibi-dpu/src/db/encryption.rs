@@ -0,0 +1,217 @@
+use std::env;
+use std::fs;
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const CIPHER_NAME: &str = "chacha20poly1305";
+
+static KEYRING: Lazy<Keyring> = Lazy::new(|| Keyring::from_env()
+    .unwrap_or_else(|e| panic!("Invalid database encryption key: {}", e)));
+
+// Stored in place of the plaintext json. Records written before encryption was
+// enabled don't parse as this and are read as plaintext.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedRecord {
+    cipher: String,
+    key_id: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+struct OpenedRecord {
+    plaintext: Vec<u8>,
+    // Plaintext or sealed with a previous key, should be written back sealed
+    needs_reseal: bool,
+}
+
+struct RecordKey {
+    id: String,
+    cipher: ChaCha20Poly1305,
+}
+
+impl RecordKey {
+    fn from_hex(key_hex: &str) -> Result<Self, String> {
+        let key_res = hex::decode(key_hex.trim());
+        if key_res.is_err() {
+            return Err("encryption key is not valid hex".to_string());
+        }
+        let key_bytes = key_res.expect("Uncaught error in key_res");
+        if key_bytes.len() != 32 {
+            return Err(format!("encryption key must be 32 bytes, got {}", key_bytes.len()));
+        }
+        let id = hex::encode(&Sha256::digest(&key_bytes)[..4]);
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key_bytes));
+        return Ok(Self { id, cipher });
+    }
+}
+
+struct Keyring {
+    current: Option<RecordKey>,
+    previous: Vec<RecordKey>,
+}
+
+impl Keyring {
+    // DPU_DB_ENCRYPTION_KEY (or a file at DPU_DB_ENCRYPTION_KEY_FILE) holds the
+    // hex encoded 32 byte key. Rotated out keys go in the comma separated
+    // DPU_DB_ENCRYPTION_PREVIOUS_KEYS until every record has been re-sealed.
+    // A key that is set but can't be read or parsed is an error rather than
+    // a silent fallback to plaintext.
+    fn from_env() -> Result<Self, String> {
+        let mut current_hex_opt = env::var("DPU_DB_ENCRYPTION_KEY").ok()
+            .filter(|key| !key.is_empty());
+        let key_file_opt = env::var("DPU_DB_ENCRYPTION_KEY_FILE").ok()
+            .filter(|path| !path.is_empty());
+        if current_hex_opt.is_none() && key_file_opt.is_some() {
+            let key_file = key_file_opt.expect("Empty key_file_opt");
+            let key_file_res = fs::read_to_string(&key_file);
+            if let Err(e) = &key_file_res {
+                return Err(format!("unable to read DPU_DB_ENCRYPTION_KEY_FILE {}: {}", &key_file, e));
+            }
+            current_hex_opt = Some(key_file_res.expect("Uncaught error in key_file_res"));
+        }
+        let previous_hex = env::var("DPU_DB_ENCRYPTION_PREVIOUS_KEYS").unwrap_or_default();
+        let previous_hex_vec: Vec<&str> = previous_hex.split(',')
+            .filter(|key| !key.trim().is_empty())
+            .collect();
+        let keyring = Self::new(current_hex_opt.as_deref(), &previous_hex_vec)?;
+        if keyring.current.is_none() {
+            log::warn!("[Keyring/from_env] No DPU_DB_ENCRYPTION_KEY set, provider credentials are stored unencrypted");
+        }
+        return Ok(keyring);
+    }
+
+    fn new(current_hex: Option<&str>, previous_hex: &[&str]) -> Result<Self, String> {
+        let current = match current_hex {
+            Some(key_hex) => Some(RecordKey::from_hex(key_hex)?),
+            None => None,
+        };
+        let previous = previous_hex.iter()
+            .map(|key_hex| RecordKey::from_hex(key_hex)
+                .map_err(|e| format!("in DPU_DB_ENCRYPTION_PREVIOUS_KEYS, {}", e)))
+            .collect::<Result<Vec<RecordKey>, String>>()?;
+        return Ok(Self { current, previous });
+    }
+
+    fn seal(&self, plaintext: &[u8]) -> Option<Vec<u8>> {
+        if self.current.is_none() {
+            return Some(plaintext.to_vec());
+        }
+        let key = self.current.as_ref().expect("Empty current key");
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext_res = key.cipher.encrypt(&nonce, plaintext);
+        if ciphertext_res.is_err() {
+            log::error!("[Keyring/seal] Unable to encrypt record");
+            return None;
+        }
+        let record = EncryptedRecord {
+            cipher: CIPHER_NAME.to_string(),
+            key_id: key.id.to_owned(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext_res.expect("Uncaught error in ciphertext_res")),
+        };
+        return serde_json::to_vec(&record).ok();
+    }
+
+    fn open(&self, stored: &[u8]) -> Option<OpenedRecord> {
+        let record_res = serde_json::from_slice::<EncryptedRecord>(stored);
+        if record_res.is_err() {
+            return Some(OpenedRecord {
+                plaintext: stored.to_vec(),
+                needs_reseal: self.current.is_some(),
+            });
+        }
+        let record = record_res.expect("Uncaught error in record_res");
+        let key_opt = self.current.iter()
+            .chain(self.previous.iter())
+            .find(|key| key.id == record.key_id);
+        if key_opt.is_none() {
+            log::error!("[Keyring/open] No encryption key with id {} configured", &record.key_id);
+            return None;
+        }
+        let key = key_opt.expect("Empty key_opt");
+        let nonce_res = hex::decode(&record.nonce);
+        let ciphertext_res = hex::decode(&record.ciphertext);
+        if nonce_res.is_err() || ciphertext_res.is_err() || record.cipher != CIPHER_NAME {
+            log::error!("[Keyring/open] Malformed encrypted record");
+            return None;
+        }
+        let nonce_bytes = nonce_res.expect("Uncaught error in nonce_res");
+        if nonce_bytes.len() != 12 {
+            log::error!("[Keyring/open] Malformed nonce in encrypted record");
+            return None;
+        }
+        let ciphertext = ciphertext_res.expect("Uncaught error in ciphertext_res");
+        let plaintext_res = key.cipher.decrypt(Nonce::from_slice(&nonce_bytes), ciphertext.as_slice());
+        if plaintext_res.is_err() {
+            log::error!("[Keyring/open] Unable to decrypt record with key {}", &key.id);
+            return None;
+        }
+        let needs_reseal = self.current.as_ref().is_some_and(|current| current.id != key.id);
+        return Some(OpenedRecord {
+            plaintext: plaintext_res.expect("Uncaught error in plaintext_res"),
+            needs_reseal,
+        });
+    }
+}
+
+// Loads the encryption keys, panicking on a key that is set but unusable so
+// the DPU doesn't start writing credentials in plaintext
+pub fn init_encryption() {
+    Lazy::force(&KEYRING);
+}
+
+// Sealed form of a record kept outside the database, such as the credentials file
+pub fn seal_bytes(plaintext: &[u8]) -> Option<Vec<u8>> {
+    return KEYRING.seal(plaintext);
+}
+
+// Plaintext of what seal_bytes returned, or of a file written before
+// encryption was enabled, and whether it should be sealed again
+pub fn open_bytes(stored: &[u8]) -> Option<(Vec<u8>, bool)> {
+    return KEYRING.open(stored).map(|opened| (opened.plaintext, opened.needs_reseal));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const NEW_KEY: &str = "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100";
+
+    #[test]
+    fn records_migrate_and_survive_rotation() {
+        let plaintext = br#"{"token":"secret"}"#;
+        let plain_keyring = Keyring::new(None, &[]).expect("no key");
+        let old_keyring = Keyring::new(Some(OLD_KEY), &[]).expect("old key");
+        let rotated_keyring = Keyring::new(Some(NEW_KEY), &[OLD_KEY]).expect("rotated keys");
+
+        let migrated = old_keyring.open(plaintext).expect("plaintext opens");
+        assert!(migrated.needs_reseal);
+        let sealed = old_keyring.seal(&migrated.plaintext).expect("sealed");
+        assert!(!String::from_utf8_lossy(&sealed).contains("secret"));
+        assert!(plain_keyring.open(&sealed).is_none());
+
+        let opened = old_keyring.open(&sealed).expect("opens with same key");
+        assert_eq!(opened.plaintext, plaintext.to_vec());
+        assert!(!opened.needs_reseal);
+
+        let rotated = rotated_keyring.open(&sealed).expect("opens with previous key");
+        assert_eq!(rotated.plaintext, plaintext.to_vec());
+        assert!(rotated.needs_reseal);
+        let resealed = rotated_keyring.seal(&rotated.plaintext).expect("resealed");
+        assert!(Keyring::new(Some(NEW_KEY), &[]).expect("new key").open(&resealed).is_some());
+        assert!(old_keyring.open(&resealed).is_none());
+    }
+
+    #[test]
+    fn unusable_keys_are_rejected() {
+        assert!(Keyring::new(Some("not hex"), &[]).is_err());
+        assert!(Keyring::new(Some("0011"), &[]).is_err());
+        assert!(Keyring::new(Some(NEW_KEY), &["0011"]).is_err());
+        assert!(Keyring::new(Some(&format!("{}\n", NEW_KEY)), &[]).is_ok());
+    }
+}
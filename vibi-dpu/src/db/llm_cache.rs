@@ -0,0 +1,223 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::db::config::get_db;
+use crate::db::store::Store;
+
+const LLM_CACHE_PREFIX: &str = "llm_cache/";
+// Empty values under llm_cache_idx/{created_at}/{hash} keep entries in age order
+const LLM_CACHE_INDEX_PREFIX: &str = "llm_cache_idx/";
+const LLM_CACHE_COUNT_KEY: &str = "llm_cache_count";
+const DEFAULT_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+const DEFAULT_MAX_ENTRIES: usize = 10000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    response: String,
+    created_at: u64,
+}
+
+// LLM_CACHE_DISABLE=true skips the cache for both reads and writes
+pub fn llm_cache_enabled() -> bool {
+    let disable = env::var("LLM_CACHE_DISABLE").unwrap_or_default();
+    return !(disable.eq_ignore_ascii_case("true") || disable == "1");
+}
+
+pub fn llm_cache_key(model: &str, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(prompt.as_bytes());
+    return format!("{}{}", LLM_CACHE_PREFIX, hex::encode(hasher.finalize()));
+}
+
+pub fn get_cached_llm_response(cache_key: &str) -> Option<String> {
+    let ttl_secs = env_u64("LLM_CACHE_TTL_SECS").unwrap_or(DEFAULT_TTL_SECS);
+    return cached_response(get_db().as_ref(), cache_key, ttl_secs, now_secs());
+}
+
+pub fn save_llm_response_to_cache(cache_key: &str, response: &str) {
+    let max_entries = env_u64("LLM_CACHE_MAX_ENTRIES")
+        .map(|max_entries| max_entries as usize)
+        .unwrap_or(DEFAULT_MAX_ENTRIES);
+    insert_response(get_db().as_ref(), cache_key, response, now_secs(), max_entries);
+}
+
+fn cached_response(store: &dyn Store, cache_key: &str, ttl_secs: u64, now: u64) -> Option<String> {
+    let entry_res = store.get(cache_key.as_bytes());
+    if entry_res.is_err() {
+        let e = entry_res.expect_err("No error in entry_res");
+        log::error!("[cached_response] Unable to read llm cache: {:?}", e);
+        return None;
+    }
+    let entry_opt = entry_res.expect("Uncaught error in entry_res");
+    if entry_opt.is_none() {
+        log::debug!("[cached_response] Cache miss for {}", cache_key);
+        return None;
+    }
+    let entry_value = entry_opt.expect("Empty entry_opt");
+    let parse_res = serde_json::from_slice::<CachedResponse>(&entry_value);
+    if parse_res.is_err() {
+        let e = parse_res.expect_err("No error in parse_res");
+        log::error!("[cached_response] Unable to deserialize cached response, dropping it: {:?}", e);
+        // Its index key is left behind and skipped by evict_oldest
+        remove_entry(store, cache_key, None);
+        return None;
+    }
+    let entry = parse_res.expect("Uncaught error in parse_res");
+    if now.saturating_sub(entry.created_at) > ttl_secs {
+        log::debug!("[cached_response] Cache entry expired for {}", cache_key);
+        remove_entry(store, cache_key, Some(entry.created_at));
+        return None;
+    }
+    log::debug!("[cached_response] Cache hit for {}", cache_key);
+    return Some(entry.response);
+}
+
+fn insert_response(store: &dyn Store, cache_key: &str, response: &str, now: u64, max_entries: usize) {
+    let entry = CachedResponse { response: response.to_string(), created_at: now };
+    let entry_res = serde_json::to_vec(&entry);
+    if entry_res.is_err() {
+        let e = entry_res.expect_err("No error in entry_res");
+        log::error!("[insert_response] Unable to serialize llm response: {:?}", e);
+        return;
+    }
+    let entry_bytes = entry_res.expect("Uncaught error in entry_res");
+    let mut count = entry_count(store);
+    match store.get(cache_key.as_bytes()) {
+        Ok(Some(previous)) => {
+            if let Ok(previous_entry) = serde_json::from_slice::<CachedResponse>(&previous) {
+                let _ = store.remove(index_key(previous_entry.created_at, cache_key).as_bytes());
+            }
+        }
+        _ => count += 1,
+    }
+    let insert_res = store.insert(cache_key.as_bytes(), &entry_bytes);
+    if insert_res.is_err() {
+        let e = insert_res.expect_err("No error in insert_res");
+        log::error!("[insert_response] Failed to insert llm response into cache: {:?}", e);
+        return;
+    }
+    let _ = store.insert(index_key(now, cache_key).as_bytes(), b"");
+    set_entry_count(store, count);
+    if count > max_entries {
+        evict_oldest(store, count, max_entries);
+    }
+}
+
+// Past max_entries the oldest entries are dropped in one batch down to 90%
+// of the limit, so the index is only walked every few inserts
+fn evict_oldest(store: &dyn Store, count: usize, max_entries: usize) {
+    let target = max_entries - max_entries / 10;
+    let excess = count.saturating_sub(target);
+    let index_res = store.scan_prefix(LLM_CACHE_INDEX_PREFIX.as_bytes());
+    if index_res.is_err() {
+        let e = index_res.expect_err("No error in index_res");
+        log::error!("[evict_oldest] Unable to scan llm cache index: {:?}", e);
+        return;
+    }
+    let mut evicted = 0;
+    for (index_key, _) in index_res.expect("Uncaught error in index_res") {
+        if evicted == excess {
+            break;
+        }
+        let _ = store.remove(&index_key);
+        let hash_opt = String::from_utf8_lossy(&index_key).rsplit('/').next().map(str::to_string);
+        let cache_key = format!("{}{}", LLM_CACHE_PREFIX, hash_opt.unwrap_or_default());
+        if let Ok(Some(_)) = store.get(cache_key.as_bytes()) {
+            let _ = store.remove(cache_key.as_bytes());
+            evicted += 1;
+        }
+    }
+    set_entry_count(store, count.saturating_sub(evicted));
+    log::debug!("[evict_oldest] Evicted {} entries from llm cache", evicted);
+}
+
+fn remove_entry(store: &dyn Store, cache_key: &str, created_at_opt: Option<u64>) {
+    let _ = store.remove(cache_key.as_bytes());
+    if let Some(created_at) = created_at_opt {
+        let _ = store.remove(index_key(created_at, cache_key).as_bytes());
+    }
+    set_entry_count(store, entry_count(store).saturating_sub(1));
+}
+
+// Zero-padded so key order matches age order
+fn index_key(created_at: u64, cache_key: &str) -> String {
+    let hash = cache_key.trim_start_matches(LLM_CACHE_PREFIX);
+    return format!("{}{:020}/{}", LLM_CACHE_INDEX_PREFIX, created_at, hash);
+}
+
+fn entry_count(store: &dyn Store) -> usize {
+    return store.get(LLM_CACHE_COUNT_KEY.as_bytes()).ok().flatten()
+        .and_then(|count| String::from_utf8(count).ok())
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(0);
+}
+
+fn set_entry_count(store: &dyn Store, count: usize) {
+    if let Err(e) = store.insert(LLM_CACHE_COUNT_KEY.as_bytes(), count.to_string().as_bytes()) {
+        log::error!("[set_entry_count] Unable to save llm cache count: {:?}", e);
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    return env::var(name).ok().and_then(|value| value.parse::<u64>().ok());
+}
+
+fn now_secs() -> u64 {
+    return SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_honours_ttl_and_size_limit() {
+        let store = get_db();
+        let store = store.as_ref();
+        let first_key = llm_cache_key("model-a", "prompt");
+        assert_ne!(first_key, llm_cache_key("model-b", "prompt"));
+
+        insert_response(store, &first_key, "first", 100, 2);
+        assert_eq!(cached_response(store, &first_key, 50, 120), Some("first".to_string()));
+        assert_eq!(cached_response(store, &first_key, 50, 200), None);
+        assert_eq!(cached_response(store, &first_key, 50, 120), None);
+
+        let keys: Vec<String> = (0..3).map(|i| llm_cache_key("model-a", &i.to_string())).collect();
+        for (i, key) in keys.iter().enumerate() {
+            insert_response(store, key, "response", 100 + i as u64, 2);
+        }
+        assert_eq!(store.scan_prefix(LLM_CACHE_PREFIX.as_bytes()).expect("scan").len(), 2);
+        assert_eq!(cached_response(store, &keys[0], 50, 110), None);
+        assert_eq!(cached_response(store, &keys[2], 50, 110), Some("response".to_string()));
+    }
+
+    #[test]
+    fn eviction_follows_the_index_in_batches() {
+        let store = get_db();
+        let store = store.as_ref();
+        let keys: Vec<String> = (0..11).map(|i| llm_cache_key("model-a", &i.to_string())).collect();
+        for (i, key) in keys.iter().enumerate().take(10) {
+            insert_response(store, key, "response", 1000 - i as u64, 10);
+        }
+        insert_response(store, &keys[9], "updated", 2000, 10);
+        assert_eq!(entry_count(store), 10);
+
+        insert_response(store, &keys[10], "response", 3000, 10);
+        assert_eq!(entry_count(store), 9);
+        assert_eq!(store.scan_prefix(LLM_CACHE_PREFIX.as_bytes()).expect("scan").len(), 9);
+        assert_eq!(store.scan_prefix(LLM_CACHE_INDEX_PREFIX.as_bytes()).expect("scan").len(), 9);
+        // The oldest entries were inserted last, except for the updated one
+        assert_eq!(cached_response(store, &keys[8], 5000, 3000), None);
+        assert_eq!(cached_response(store, &keys[7], 5000, 3000), None);
+        assert_eq!(cached_response(store, &keys[9], 5000, 3000), Some("updated".to_string()));
+        assert_eq!(cached_response(store, &keys[0], 5000, 3000), Some("response".to_string()));
+    }
+}
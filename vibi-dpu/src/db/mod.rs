@@ -1,6 +1,8 @@
 pub mod owner;
 pub mod repo;
 mod config;
+pub mod store;
+pub mod encryption;
 pub mod webhook;
 pub mod user;
 pub mod hunk;
@@ -9,4 +11,6 @@ pub mod repo_config;
 pub mod prs;
 pub mod bitbucket;
 pub mod github;
-pub mod aliases;
\ No newline at end of file
+pub mod aliases;
+pub mod llm_cache;
+pub mod comment;
\ No newline at end of file
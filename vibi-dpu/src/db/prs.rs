@@ -1,25 +1,13 @@
 use serde_json::Value;
-use sled::IVec;
 use crate::core::approval::process_approval;
 use crate::db::config::get_db;
+use crate::gitlab::auth::gitlab_access_token;
+use crate::gitlab::prs::get_pr_info_gitlab;
 use crate::utils::pr_info::PrInfo;
 
 pub async fn update_pr_info_in_db(workspace_slug: &str, repo_slug: &str, pr_info: &PrInfo, pr_number: &str, repo_provider: &str) {
-	let key = format!("pr_info/{}/{}/{}/{}", repo_provider, workspace_slug, repo_slug, pr_number);
-	let db = get_db();
-
-	let pr_info_json_result = serde_json::to_vec(&pr_info);
-
-	if pr_info_json_result.is_err() {
-		let e = pr_info_json_result.expect_err("Empty error in pr_info_bytes");
-		log::error!("[update_pr_info_in_db] Failed to serialize PR info: {:?}", e);
-		return;
-	}
-
-	let pr_info_bytes = pr_info_json_result.expect("empty pr_info_json_result");
-
 	// Update the entry in the database. It will create a new entry if the key does not exist.
-	let update_result = db.insert(IVec::from(key.as_bytes()), pr_info_bytes);
+	let update_result = get_db().save_pr_info(repo_provider, workspace_slug, repo_slug, pr_number, pr_info);
 
 	if update_result.is_err() {
 		let e = update_result.expect_err("No error in updating pr_info");
@@ -27,7 +15,8 @@ pub async fn update_pr_info_in_db(workspace_slug: &str, repo_slug: &str, pr_info
 		return;
 	}
 
-	log::debug!("[update_pr_info_in_db] PR info updated successfully in the database. {:?} {:?}", key, pr_info);
+	log::debug!("[update_pr_info_in_db] PR info updated successfully in the database. {}/{}/{} {:?}",
+		workspace_slug, repo_slug, pr_number, pr_info);
 }
 
 pub async fn bitbucket_process_and_update_pr_if_different(webhook_data: &Value, workspace_slug: &str, repo_slug: &str, pr_number: &str, repo_provider: &str) -> bool {
@@ -78,13 +67,11 @@ fn parse_bitbucket_webhook_data(webhook_data: &Value) -> Option<PrInfo> {
 }
 
 pub async fn get_pr_info_from_db(workspace_slug: &str, repo_slug: &str, pr_number: &str, repo_provider: &str, pr_info_parsed: &PrInfo) -> Option<PrInfo> {
-	let db = get_db();
-	let db_pr_key = format!("pr_info/{}/{}/{}/{}", repo_provider, workspace_slug, repo_slug, pr_number);
-	let pr_info_res = db.get(IVec::from(db_pr_key.as_bytes()));
+	let pr_info_res = get_db().get_pr_info(repo_provider, workspace_slug, repo_slug, pr_number);
 
 	if pr_info_res.is_err() {
 		let e = pr_info_res.expect_err("No error in pr_info_res");
-		log::error!("[get_pr_info_from_db] Unable to get bb pr info from db: {:?}", e);
+		log::error!("[get_pr_info_from_db] Unable to get {} pr info from db: {:?}", repo_provider, e);
 		return None;
 	};
 
@@ -94,20 +81,8 @@ pub async fn get_pr_info_from_db(workspace_slug: &str, repo_slug: &str, pr_numbe
 		update_pr_info_in_db(&workspace_slug, &repo_slug, pr_info_parsed, &pr_number, repo_provider).await;
 		return None; //If no info in db then it will be considered as new commit
 	}
-
-	let pr_info_ivec = pr_info_opt.expect("Empty pr_info_opt");
-	log::debug!("[get_pr_info_from_db] pr_info_ivec = {:?}", &pr_info_ivec);
-
-	let pr_info_parse = serde_json::from_slice(&pr_info_ivec);
-	log::debug!("[get_pr_info_from_db] pr_info_parse = {:?}", &pr_info_parse);
-
-	if pr_info_parse.is_err() {
-		let e = pr_info_parse.expect_err("No error in pr_info_parse");
-		log::error!("[get_pr_info_from_db] Unable to deserialize pr_Info: {:?}", e);
-		return None;
-	}
-	let pr_info: PrInfo = pr_info_parse.expect("Failed to deserialize PR info");
-	return Some(pr_info);
+	log::debug!("[get_pr_info_from_db] pr_info_opt = {:?}", &pr_info_opt);
+	return pr_info_opt;
 }
 
 pub async fn github_process_and_update_pr_if_different(webhook_data: &Value, repo_config: &Value, repo_owner: &str, repo_name: &str, pr_number: &str, repo_provider: &str) -> bool {
@@ -168,3 +143,54 @@ fn parse_github_webhook_data(webhook_data: &Value) -> Option<PrInfo> {
 	log::debug!("[parse_github_webhook_data] pr_info :{:?}", &pr_info);
 	return Some(pr_info)
 }
+
+pub async fn gitlab_process_and_update_pr_if_different(webhook_data: &Value, repo_owner: &str, repo_name: &str, pr_number: &str, repo_provider: &str) -> bool {
+	log::debug!("[gitlab_process_and_update_pr_if_different] {:?}, {:?}, {:?}, {:?}", repo_owner, repo_name, pr_number, repo_provider);
+	let event_action = webhook_data["object_attributes"]["action"].to_string().trim_matches('"').to_string();
+	if event_action != "open" && event_action != "reopen" && event_action != "update" {
+		log::info!("MR event is not recognized, skipping processing");
+		log::debug!("[gitlab_process_and_update_pr_if_different] event action: {}", &event_action);
+		return false;
+	}
+	// merge request hooks do not carry the base commit, so fetch it from the api
+	let access_token_opt = gitlab_access_token();
+	if access_token_opt.is_none() {
+		log::error!("[gitlab_process_and_update_pr_if_different] Unable to get gitlab access token");
+		return false;
+	}
+	let access_token = access_token_opt.expect("Empty access_token_opt");
+	let pr_info_opt = get_pr_info_gitlab(repo_owner, repo_name, &access_token, pr_number).await;
+	if pr_info_opt.is_none() {
+		log::error!("[gitlab_process_and_update_pr_if_different] Unable to get MR info from gitlab");
+		return false;
+	}
+	let pr_info_parsed = pr_info_opt.expect("Empty pr_info_opt");
+	let pr_info_db_opt = get_pr_info_from_db(repo_owner, repo_name, pr_number, repo_provider, &pr_info_parsed).await;
+	if pr_info_db_opt.is_none() {
+		return true; // new pr, stored by get_pr_info_from_db
+	}
+	let pr_info_db = pr_info_db_opt.expect("Empty pr_info_db_opt");
+	if event_action == "update"
+		&& pr_info_db.pr_head_commit().eq_ignore_ascii_case(pr_info_parsed.pr_head_commit()) {
+		log::debug!("[gitlab_process_and_update_pr_if_different] {:?} same commit id as in db, doing nothing",
+			pr_info_db.pr_head_commit());
+		return false;
+	}
+	update_pr_info_in_db(repo_owner, repo_name, &pr_info_parsed, pr_number, repo_provider).await;
+	return true;
+}
+
+pub fn get_saved_pr_info(repo_provider: &str, repo_owner: &str, repo_name: &str, pr_number: &str) -> Option<PrInfo> {
+	let pr_info_res = get_db().get_pr_info(repo_provider, repo_owner, repo_name, pr_number);
+	if pr_info_res.is_err() {
+		let e = pr_info_res.expect_err("No error in pr_info_res");
+		log::error!("[get_saved_pr_info] Unable to get pr info from db: {:?}", e);
+		return None;
+	}
+	let pr_info_opt = pr_info_res.expect("Uncaught error in pr_info_res");
+	if pr_info_opt.is_none() {
+		log::error!("[get_saved_pr_info] No pr info in db for {}/{}/{}/{}",
+			repo_provider, repo_owner, repo_name, pr_number);
+	}
+	return pr_info_opt;
+}
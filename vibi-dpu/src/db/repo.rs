@@ -1,26 +1,12 @@
-use sled::IVec;
 
 use crate::db::config::get_db;
 use crate::utils::repo::Repository;
 
 pub fn save_repo_to_db(repo: &Repository) {
-    let db = get_db();
-    let repo_key = format!("{}/{}/{}", repo.provider(), repo.workspace(), repo.name());
-    log::debug!("[save_repo_to_db] repo_key = {}",  &repo_key);
-  
-    // Serialize repo struct to JSON 
-    let parse_res = serde_json::to_vec(repo);
-	if parse_res.is_err() {
-		let e = parse_res.expect_err("Empty error in parse_res");
-		log::error!("[save_repo_to_db] Unable to serialize repo: {:?}, error: {:?}", repo, e);
-		return;
-	}
-	let repo_json = parse_res.expect("Uncaught error in parse_res repo");
-    // Insert JSON into sled DB
-    let insert_res = db.insert(IVec::from(repo_key.as_bytes()), repo_json);
-    if insert_res.is_err() {
-        let e = insert_res.expect_err("No error in insert_res");
-        log::error!("[save_repo_to_db] Failed to upsert repo into sled DB: {:?}", e);
+    let save_res = get_db().save_repo(repo);
+    if save_res.is_err() {
+        let e = save_res.expect_err("No error in save_res");
+        log::error!("[save_repo_to_db] Failed to upsert repo into db: {:?}, error: {:?}", repo, e);
         return;
     }
     log::debug!("[save_repo_to_db] Repo succesfully upserted: {:?}", repo);
@@ -45,9 +31,7 @@ pub fn get_clone_url_clone_dir(repo_provider: &str, workspace_name: &str, repo_n
 }
 
 pub fn get_repo_from_db(repo_provider: &str, workspace_name: &str, repo_name: &str) -> Option<Repository> {
-	let db = get_db();
-	let key = format!("{}/{}/{}", repo_provider, workspace_name, repo_name);
-	let repo_res = db.get(IVec::from(key.as_bytes()));
+	let repo_res = get_db().get_repo(repo_provider, workspace_name, repo_name);
 	if repo_res.is_err() {
 		let e = repo_res.expect_err("No error in repo_res");
 		log::error!("[get_repo_from_db] Unable to get repo from db: {:?}", e);
@@ -56,15 +40,6 @@ pub fn get_repo_from_db(repo_provider: &str, workspace_name: &str, repo_name: &s
 	let repo_opt = repo_res.expect("Uncaught error in repo_res");
 	if repo_opt.is_none() {
 		log::error!("[get_repo_from_db] Empty repo_opt from db");
-		return None;
 	}
-	let repo_ivec = repo_opt.expect("Empty repo_opt");
-	let parse_res = serde_json::from_slice::<Repository>(&repo_ivec);
-	if parse_res.is_err() {
-		let e = parse_res.expect_err("No error in parse_res repo");
-		log::error!("[get_repo_from_db] error in deserializing repo from db: {:?}", e);
-		return None;
-	}
-	let repo: Repository = parse_res.expect("Uncaught error in parse_res");
-	return Some(repo);
-}
\ No newline at end of file
+	return repo_opt;
+}
@@ -0,0 +1,393 @@
+#[cfg(test)]
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(test)]
+use std::sync::Mutex;
+
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::db::encryption::{open_bytes, seal_bytes};
+use crate::utils::{bitbucket_auth_info::BitbucketAuthInfo, bitbucket_webhook, comment::CommentKind,
+    github_auth_info::GithubAuthInfo, github_webhook, gitlab_webhook, hunk::HunkMap, owner::Workspace,
+    pr_info::PrInfo, repo::Repository, repo_config::RepoConfig, review::Review, user::WorkspaceUser};
+
+pub type StoreEntry = (Vec<u8>, Vec<u8>);
+#[cfg(test)]
+type MemoryEntries = BTreeMap<Vec<u8>, Vec<u8>>;
+
+// Storage behind every db/* module. Backends only implement byte-level
+// get/insert/remove/scan; every record is read and written through the
+// typed methods, which own their key layout and serialization.
+pub trait Store: Send + Sync {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StoreError>;
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<(), StoreError>;
+    fn remove(&self, key: &[u8]) -> Result<(), StoreError>;
+    // Entries whose key starts with prefix, in key order
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<StoreEntry>, StoreError>;
+
+    fn get_repo(&self, provider: &str, workspace: &str, name: &str) -> Result<Option<Repository>, StoreError> {
+        return get_json(self, &format!("{}/{}/{}", provider, workspace, name));
+    }
+
+    fn save_repo(&self, repo: &Repository) -> Result<(), StoreError> {
+        let key = format!("{}/{}/{}", repo.provider(), repo.workspace(), repo.name());
+        return insert_json(self, &key, repo);
+    }
+
+    fn get_review(&self, provider: &str, owner: &str, name: &str, review_id: &str) -> Result<Option<Review>, StoreError> {
+        return get_json(self, &format!("review/{}/{}/{}/{}", provider, owner, name, review_id));
+    }
+
+    fn save_review(&self, review: &Review) -> Result<(), StoreError> {
+        return insert_json(self, &format!("review/{}", review.db_key()), review);
+    }
+
+    // Hunks are kept per base and head commit of the review
+    fn get_hunkmap(&self, review: &Review) -> Result<Option<HunkMap>, StoreError> {
+        return get_json(self, &hunk_key(review));
+    }
+
+    fn save_hunkmap(&self, review: &Review, hunkmap: &HunkMap) -> Result<(), StoreError> {
+        return insert_json(self, &hunk_key(review), hunkmap);
+    }
+
+    fn get_pr_info(&self, provider: &str, owner: &str, name: &str, pr_number: &str) -> Result<Option<PrInfo>, StoreError> {
+        return get_json(self, &format!("pr_info/{}/{}/{}/{}", provider, owner, name, pr_number));
+    }
+
+    fn save_pr_info(&self, provider: &str, owner: &str, name: &str, pr_number: &str, pr_info: &PrInfo) -> Result<(), StoreError> {
+        return insert_json(self, &format!("pr_info/{}/{}/{}/{}", provider, owner, name, pr_number), pr_info);
+    }
+
+    // Provider logins known for a git alias
+    fn get_aliases(&self, provider: &str, alias_key: &str) -> Result<Option<Vec<String>>, StoreError> {
+        return get_json(self, &format!("{}/aliases/{}", provider, alias_key));
+    }
+
+    fn save_aliases(&self, provider: &str, alias_key: &str, logins: &[String]) -> Result<(), StoreError> {
+        return insert_json(self, &format!("{}/aliases/{}", provider, alias_key), &logins);
+    }
+
+    // Workspace users are keyed by their display name
+    fn get_workspace_user(&self, display_name: &str) -> Result<Option<WorkspaceUser>, StoreError> {
+        return get_json(self, display_name);
+    }
+
+    fn save_workspace_user(&self, user: &WorkspaceUser) -> Result<(), StoreError> {
+        return insert_json(self, user.display_name(), user);
+    }
+
+    fn get_github_auth_info(&self) -> Result<Option<GithubAuthInfo>, StoreError> {
+        return get_sealed_json(self, "github_auth_info");
+    }
+
+    fn save_github_auth_info(&self, auth_info: &GithubAuthInfo) -> Result<(), StoreError> {
+        return insert_sealed_json(self, "github_auth_info", auth_info);
+    }
+
+    fn get_bitbucket_auth_info(&self) -> Result<Option<BitbucketAuthInfo>, StoreError> {
+        return get_sealed_json(self, "bitbucket_auth_info");
+    }
+
+    fn save_bitbucket_auth_info(&self, auth_info: &BitbucketAuthInfo) -> Result<(), StoreError> {
+        return insert_sealed_json(self, "bitbucket_auth_info", auth_info);
+    }
+
+    fn save_github_webhook(&self, webhook: &github_webhook::Webhook) -> Result<(), StoreError> {
+        return insert_json(self, &webhook_key(), webhook);
+    }
+
+    fn save_bitbucket_webhook(&self, webhook: &bitbucket_webhook::Webhook) -> Result<(), StoreError> {
+        return insert_json(self, &webhook_key(), webhook);
+    }
+
+    fn save_gitlab_webhook(&self, webhook: &gitlab_webhook::Webhook) -> Result<(), StoreError> {
+        return insert_json(self, &webhook_key(), webhook);
+    }
+
+    fn save_workspace(&self, workspace: &Workspace) -> Result<(), StoreError> {
+        return insert_json(self, &format!("owners:{}", workspace.uuid()), workspace);
+    }
+
+    fn get_repo_config(&self, provider: &str, owner: &str, name: &str) -> Result<Option<RepoConfig>, StoreError> {
+        return get_json(self, &format!("{}/{}/{}/config", provider, owner, name));
+    }
+
+    fn save_repo_config(&self, provider: &str, owner: &str, name: &str, repo_config: &RepoConfig) -> Result<(), StoreError> {
+        return insert_json(self, &format!("{}/{}/{}/config", provider, owner, name), repo_config);
+    }
+
+    // Comment ids are stored as plain strings, one per review and kind
+    fn get_comment_id(&self, review: &Review, kind: &CommentKind) -> Result<Option<String>, StoreError> {
+        let comment_id_opt = self.get(comment_key(review, kind).as_bytes())?;
+        return Ok(comment_id_opt.map(|comment_id| String::from_utf8_lossy(&comment_id).to_string()));
+    }
+
+    fn save_comment_id(&self, review: &Review, kind: &CommentKind, comment_id: &str) -> Result<(), StoreError> {
+        return self.insert(comment_key(review, kind).as_bytes(), comment_id.as_bytes());
+    }
+}
+
+fn hunk_key(review: &Review) -> String {
+    return format!("hunk/{}/{}/{}", review.db_key(), review.base_head_commit(), review.pr_head_commit());
+}
+
+// Webhooks are only ever written, each under a fresh id
+fn webhook_key() -> String {
+    return format!("webhook/{}", Uuid::new_v4());
+}
+
+fn comment_key(review: &Review, kind: &CommentKind) -> String {
+    return format!("comment/{}/{}", review.db_key(), kind);
+}
+
+fn get_json<S: Store + ?Sized, T: DeserializeOwned>(store: &S, key: &str) -> Result<Option<T>, StoreError> {
+    let value_opt = store.get(key.as_bytes())?;
+    if value_opt.is_none() {
+        return Ok(None);
+    }
+    let value = value_opt.expect("Empty value_opt");
+    return Ok(Some(serde_json::from_slice(&value)?));
+}
+
+fn insert_json<S: Store + ?Sized, T: Serialize>(store: &S, key: &str, value: &T) -> Result<(), StoreError> {
+    let json = serde_json::to_vec(value)?;
+    return store.insert(key.as_bytes(), &json);
+}
+
+// Credentials are encrypted with the current key, if one is configured.
+// Plaintext records and records sealed with a previous key are re-sealed
+// with the current key when read.
+fn get_sealed_json<S: Store + ?Sized, T: DeserializeOwned>(store: &S, key: &str) -> Result<Option<T>, StoreError> {
+    let stored_opt = store.get(key.as_bytes())?;
+    if stored_opt.is_none() {
+        return Ok(None);
+    }
+    let stored = stored_opt.expect("Empty stored_opt");
+    let opened_opt = open_bytes(&stored);
+    if opened_opt.is_none() {
+        return Err(StoreError::new(&format!("Unable to decrypt record {}", key)));
+    }
+    let (plaintext, needs_reseal) = opened_opt.expect("Empty opened_opt");
+    if needs_reseal {
+        log::info!("[get_sealed_json] Re-encrypting {} with the current key", key);
+        if let Err(e) = insert_sealed(store, key, &plaintext) {
+            log::error!("[get_sealed_json] Unable to re-encrypt {}: {}", key, e);
+        }
+    }
+    return Ok(Some(serde_json::from_slice(&plaintext)?));
+}
+
+fn insert_sealed_json<S: Store + ?Sized, T: Serialize>(store: &S, key: &str, value: &T) -> Result<(), StoreError> {
+    let json = serde_json::to_vec(value)?;
+    return insert_sealed(store, key, &json);
+}
+
+fn insert_sealed<S: Store + ?Sized>(store: &S, key: &str, plaintext: &[u8]) -> Result<(), StoreError> {
+    let sealed_opt = seal_bytes(plaintext);
+    if sealed_opt.is_none() {
+        return Err(StoreError::new("Unable to encrypt record"));
+    }
+    let sealed = sealed_opt.expect("Empty sealed_opt");
+    return store.insert(key.as_bytes(), &sealed);
+}
+
+#[derive(Debug)]
+pub struct StoreError(String);
+
+impl StoreError {
+    pub fn new(msg: &str) -> Self {
+        StoreError(msg.to_string())
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<sled::Error> for StoreError {
+    fn from(e: sled::Error) -> Self {
+        StoreError(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(e: serde_json::Error) -> Self {
+        StoreError(e.to_string())
+    }
+}
+
+pub struct SledStore {
+    db: sled::Db,
+}
+
+impl SledStore {
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        let db = sled::open(path)?;
+        return Ok(Self { db });
+    }
+}
+
+impl Store for SledStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        let value_opt = self.db.get(key)?;
+        return Ok(value_opt.map(|value| value.to_vec()));
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<(), StoreError> {
+        self.db.insert(key, value)?;
+        return Ok(());
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<(), StoreError> {
+        self.db.remove(key)?;
+        return Ok(());
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<StoreEntry>, StoreError> {
+        let mut entries = Vec::new();
+        for entry_res in self.db.scan_prefix(prefix) {
+            let (key, value) = entry_res?;
+            entries.push((key.to_vec(), value.to_vec()));
+        }
+        return Ok(entries);
+    }
+}
+
+// Backs get_db in tests, one per test thread
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Mutex<MemoryEntries>,
+}
+
+#[cfg(test)]
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entries(&self) -> Result<std::sync::MutexGuard<'_, MemoryEntries>, StoreError> {
+        return self.entries.lock().map_err(|e| StoreError(e.to_string()));
+    }
+}
+
+#[cfg(test)]
+impl Store for MemoryStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        return Ok(self.entries()?.get(key).cloned());
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<(), StoreError> {
+        self.entries()?.insert(key.to_vec(), value.to_vec());
+        return Ok(());
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<(), StoreError> {
+        self.entries()?.remove(key);
+        return Ok(());
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<StoreEntry>, StoreError> {
+        let entries = self.entries()?
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        return Ok(entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(store: &dyn Store) {
+        store.insert(b"review/a", b"1").expect("insert");
+        store.insert(b"review/b", b"2").expect("insert");
+        store.insert(b"hunk/a", b"3").expect("insert");
+        assert_eq!(store.get(b"review/a").expect("get"), Some(b"1".to_vec()));
+        assert_eq!(store.get(b"missing").expect("get"), None);
+        let keys: Vec<Vec<u8>> = store.scan_prefix(b"review/").expect("scan")
+            .into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![b"review/a".to_vec(), b"review/b".to_vec()]);
+        store.remove(b"review/a").expect("remove");
+        assert_eq!(store.get(b"review/a").expect("get"), None);
+    }
+
+    #[test]
+    fn typed_records_round_trip() {
+        let store = MemoryStore::new();
+        let repo = Repository::new("repo".to_string(), "uuid".to_string(), "owner".to_string(), true,
+            "git@example.com:owner/repo.git".to_string(), None, None, "owner".to_string(), None,
+            "github".to_string());
+        store.save_repo(&repo).expect("save repo");
+        let saved_repo = store.get_repo("github", "owner", "repo").expect("get repo").expect("repo");
+        assert_eq!(saved_repo.clone_ssh_url(), repo.clone_ssh_url());
+        assert!(store.get_repo("github", "owner", "other").expect("get repo").is_none());
+
+        let review = Review::new("base".to_string(), "head".to_string(), "7".to_string(),
+            "repo".to_string(), "owner".to_string(), "github".to_string(),
+            "github/owner/repo/7".to_string(), String::new(), String::new(), "alice".to_string(), None);
+        store.save_review(&review).expect("save review");
+        let saved_review = store.get_review("github", "owner", "repo", "7").expect("get review").expect("review");
+        assert_eq!(saved_review.pr_head_commit(), "head");
+
+        let hunkmap = HunkMap::new("github".to_string(), "owner".to_string(), "repo".to_string(),
+            Vec::new(), review.db_key().to_string(), Default::default());
+        store.save_hunkmap(&review, &hunkmap).expect("save hunkmap");
+        let saved_hunkmap = store.get_hunkmap(&review).expect("get hunkmap").expect("hunkmap");
+        assert_eq!(saved_hunkmap.db_key(), review.db_key());
+
+        store.insert(b"review/github/owner/repo/8", b"not json").expect("insert");
+        assert!(store.get_review("github", "owner", "repo", "8").is_err());
+    }
+
+    #[test]
+    fn remaining_records_round_trip() {
+        let store = MemoryStore::new();
+        let pr_info = PrInfo { base_head_commit: "base".to_string(), pr_head_commit: "head".to_string(),
+            state: "open".to_string(), pr_branch: "feature".to_string(), author: Some("alice".to_string()) };
+        store.save_pr_info("gitlab", "owner", "repo", "7", &pr_info).expect("save pr info");
+        let saved_pr_info = store.get_pr_info("gitlab", "owner", "repo", "7").expect("get pr info").expect("pr info");
+        assert_eq!(saved_pr_info.author, Some("alice".to_string()));
+        assert!(store.get_pr_info("github", "owner", "repo", "7").expect("get pr info").is_none());
+
+        store.save_aliases("github", "alice@example.com", &["alice".to_string()]).expect("save aliases");
+        assert_eq!(store.get_aliases("github", "alice@example.com").expect("get aliases"), Some(vec!["alice".to_string()]));
+
+        let repo_config: RepoConfig = serde_json::from_value(serde_json::json!({
+            "comment": true, "auto_assign": false, "diff_graph": true })).expect("repo config");
+        store.save_repo_config("github", "owner", "repo", &repo_config).expect("save repo config");
+        let saved_config = store.get_repo_config("github", "owner", "repo").expect("get repo config").expect("config");
+        assert!(!saved_config.auto_assign());
+
+        let auth_info: GithubAuthInfo = serde_json::from_value(serde_json::json!({
+            "token": "secret", "expires_at": "never", "installation_id": null })).expect("auth info");
+        store.save_github_auth_info(&auth_info).expect("save auth info");
+        let saved_auth_info = store.get_github_auth_info().expect("get auth info").expect("auth info");
+        assert_eq!(saved_auth_info.token(), "secret");
+
+        let review = Review::new("base".to_string(), "head".to_string(), "7".to_string(),
+            "repo".to_string(), "owner".to_string(), "github".to_string(),
+            "github/owner/repo/7".to_string(), String::new(), String::new(), "alice".to_string(), None);
+        store.save_comment_id(&review, &CommentKind::Relevance, "101").expect("save comment id");
+        assert_eq!(store.get_comment_id(&review, &CommentKind::Relevance).expect("get comment id"), Some("101".to_string()));
+        assert_eq!(store.get_comment_id(&review, &CommentKind::DiffGraph).expect("get comment id"), None);
+    }
+
+    #[test]
+    fn sled_and_memory_stores_agree() {
+        exercise(&MemoryStore::new());
+        let dir = std::env::temp_dir().join(format!("vibi-dpu-store-test-{}", std::process::id()));
+        let sled_store = SledStore::open(dir.to_str().expect("utf8 temp dir")).expect("open sled");
+        exercise(&sled_store);
+        drop(sled_store);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
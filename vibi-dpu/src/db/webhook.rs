@@ -1,28 +1,25 @@
-use sled::IVec;
-use uuid::Uuid;
-use serde::Serialize;
 use std::fmt::Debug;
 
 use crate::db::config::get_db;
+use crate::db::store::StoreError;
+use crate::utils::{bitbucket_webhook, github_webhook, gitlab_webhook};
 
-pub fn save_webhook_to_db<T: Serialize>(webhook: &T) where T: Serialize + Debug, {
-    let db = get_db();
-    // Generate unique ID
-    let uuid = Uuid::new_v4();
-    let id = uuid.as_bytes();
-    // Serialize webhook struct to JSON
-    let parse_res = serde_json::to_vec(webhook);
-    if parse_res.is_err() {
-        let e = parse_res.expect_err("No error in parse_res in save_webhook_to_db");
-        log::error!("[save_webhook_to_db] Failed to serialize webhook: {:?}", e);
-        return;
-    }
-    let webhook_json = parse_res.expect("Uncaught error in parse_res webhook");
-    // Insert JSON into sled DB
-    let insert_res = db.insert(IVec::from(id), webhook_json);
+pub fn save_github_webhook_to_db(webhook: &github_webhook::Webhook) {
+    log_webhook_save(get_db().save_github_webhook(webhook), webhook);
+}
+
+pub fn save_bitbucket_webhook_to_db(webhook: &bitbucket_webhook::Webhook) {
+    log_webhook_save(get_db().save_bitbucket_webhook(webhook), webhook);
+}
+
+pub fn save_gitlab_webhook_to_db(webhook: &gitlab_webhook::Webhook) {
+    log_webhook_save(get_db().save_gitlab_webhook(webhook), webhook);
+}
+
+fn log_webhook_save<T: Debug>(insert_res: Result<(), StoreError>, webhook: &T) {
     if insert_res.is_err() {
         let e = insert_res.expect_err("No error in insert_res");
-        log::error!("[save_webhook_to_db] Failed to upsert webhook into sled DB: {e}");
+        log::error!("[save_webhook_to_db] Failed to upsert webhook into db: {e}");
         return;
     }
     log::debug!("[save_webhook_to_db] Webhook succesfully upserted: {:?}", webhook);
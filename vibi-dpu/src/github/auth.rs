@@ -10,6 +10,7 @@ use crate::utils::review::Review;
 use crate::utils::user::ProviderEnum;
 use crate::{utils::reqwest_client::get_client, utils::github_auth_info::GithubAuthInfo, db::github::auth::save_github_auth_info_to_db};
 use crate::utils::gitops::set_git_remote_url;
+use crate::logger::redact::register_secret;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct AccessTokenResponse {
@@ -120,6 +121,7 @@ async fn call_access_token_api(installation_id: &str) -> Option<GithubAuthInfo>{
         return None;
     }
     let mut gh_auth_info = parse_res.expect("Uncaught error in parse_res for AuthInfo");
+    register_secret("github_token", gh_auth_info.token());
     gh_auth_info.set_installation_id(installation_id);
     return Some(gh_auth_info);
 }
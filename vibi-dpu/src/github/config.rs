@@ -1,7 +1,8 @@
-use std::{env, collections::HashMap};
+use std::{env, collections::HashMap, time::Instant};
 use reqwest::{Response, header::{HeaderMap, HeaderValue, AUTHORIZATION, ACCEPT, USER_AGENT}, header};
 use serde_json::Value;
 
+use crate::metrics::registry::record_provider_response;
 use crate::utils::reqwest_client::get_client;
 
 pub fn github_base_url() -> String {
@@ -49,11 +50,13 @@ async fn get_api_response(url: &str, headers_opt: Option<reqwest::header::Header
     }
     let headers = get_headers_opt.expect("Uncaught error in get_headers_opt");
     let client = get_client();
+    let request_start = Instant::now();
     let get_response = client.get(url)
         .headers(headers.clone())
         .query(params)
         .send()
         .await;
+    record_provider_response("github", &get_response, request_start.elapsed());
 
     if get_response.is_err() {
         let e = get_response.expect_err("No error in get_response");
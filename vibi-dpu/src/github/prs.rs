@@ -1,4 +1,5 @@
 use crate::db::prs::update_pr_info_in_db;
+use crate::metrics::registry::record_provider_response;
 use crate::utils::user::ProviderEnum;
 use crate::utils::{pr_info::PrInfo, reqwest_client::get_client};
 use reqwest::header::{HeaderMap, USER_AGENT};
@@ -6,6 +7,7 @@ use reqwest::Response;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::str;
+use std::time::Instant;
 
 use super::config::{github_base_url, prepare_headers};
 
@@ -171,6 +173,43 @@ pub async fn pr_reviewer_handles(repo_owner: &str, repo_name: &str,
     Some(reviewer_handles)
 }
 
+// Open PRs in the owner's repos that are waiting on a review from this login
+pub async fn open_review_request_count(login: &str, repo_owner: &str, access_token: &str) -> Option<usize> {
+    let headers_opt = prepare_headers(access_token);
+    if headers_opt.is_none() {
+        log::error!("[open_review_request_count] Unable to prepare auth headers for: {}", login);
+        return None;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let query = format!("is:pr is:open review-requested:{} user:{}", login, repo_owner);
+    let request_start = Instant::now();
+    let response_res = get_client()
+        .get(format!("{}/search/issues", github_base_url()))
+        .headers(headers)
+        .query(&[("q", query.as_str()), ("per_page", "1")])
+        .send()
+        .await;
+    record_provider_response("github", &response_res, request_start.elapsed());
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[open_review_request_count] Failed to send the request: {:?}", e);
+        return None;
+    }
+    let response = response_res.expect("Uncaught error in response_res");
+    if !response.status().is_success() {
+        log::error!("[open_review_request_count] Request failed with status: {:?}", response.status());
+        return None;
+    }
+    let search_res = response.json::<Value>().await;
+    if search_res.is_err() {
+        let e = search_res.expect_err("No error in search_res");
+        log::error!("[open_review_request_count] Unable to deserialize search result: {:?}", e);
+        return None;
+    }
+    let search = search_res.expect("Uncaught error in search_res");
+    return search["total_count"].as_u64().map(|count| count as usize);
+}
+
 async fn all_pr_reviews(access_token: &str,
         repo_owner: &str, repo_name: &str, pr_number: &str) -> Option<Response> {
     let headers_opt = prepare_headers(access_token);
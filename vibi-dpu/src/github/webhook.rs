@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use reqwest::{Response, Error};
 use serde_json::{json, Value};
 
-use crate::{db::webhook::save_webhook_to_db, utils::github_webhook::Webhook, github::config::{github_base_url, get_api_paginated}};
+use crate::{db::webhook::save_github_webhook_to_db, utils::github_webhook::Webhook, github::config::{github_base_url, get_api_paginated}};
 use crate::utils::reqwest_client::get_client;
 use super::config::prepare_headers;
 
@@ -33,7 +33,7 @@ fn deserialize_webhooks(webhook_val: Vec<Value>) -> Vec<Webhook> {
         let webhook_page_json = webhook_json_opt.expect("Empty repo_json_opt").to_owned();
         for webhook_json in webhook_page_json {
             let webhook = deserialize_webhook_object(&webhook_json);
-            save_webhook_to_db(&webhook);
+            save_github_webhook_to_db(&webhook);
             all_webhooks.push(webhook);
         }
     }
@@ -112,5 +112,5 @@ async fn process_add_webhook_response(response: Result<Response, Error>){
                 config_obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<HashMap<String, Value>>()
             }).expect("Config should be a JSON object")
     );
-    save_webhook_to_db(&webhook); 
+    save_github_webhook_to_db(&webhook); 
 }
\ No newline at end of file
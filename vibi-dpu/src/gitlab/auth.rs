@@ -0,0 +1,27 @@
+use std::env;
+
+use crate::utils::user::ProviderEnum;
+
+pub fn gitlab_access_token() -> Option<String> {
+    let gitlab_pat_res = env::var("GITLAB_PAT");
+    if gitlab_pat_res.is_err() {
+        log::debug!("[gitlab_access_token] GITLAB_PAT env var must be set");
+        return None;
+    }
+    let gitlab_pat = gitlab_pat_res.expect("Empty GITLAB_PAT env var");
+    if gitlab_pat.is_empty() {
+        log::debug!("[gitlab_access_token] GITLAB_PAT 0 length");
+        return None;
+    }
+    let provider_res = env::var("PROVIDER");
+    if provider_res.is_err() {
+        log::error!("[gitlab_access_token] PROVIDER env var must be set");
+        return None;
+    }
+    let provider = provider_res.expect("Empty PROVIDER env var");
+    if !provider.eq_ignore_ascii_case(&ProviderEnum::Gitlab.to_string()) {
+        log::error!("[gitlab_access_token] PROVIDER is not gitlab: {}", provider);
+        return None;
+    }
+    return Some(gitlab_pat);
+}
@@ -0,0 +1,110 @@
+use std::{env, collections::HashMap, time::Instant};
+use reqwest::{Response, header::{HeaderMap, HeaderValue, AUTHORIZATION, ACCEPT, USER_AGENT}, header};
+use serde_json::Value;
+
+use crate::metrics::registry::record_provider_response;
+use crate::utils::reqwest_client::get_client;
+
+pub fn gitlab_base_url() -> String {
+    env::var("GITLAB_BASE_URL").expect("GITLAB_BASE_URL must be set")
+}
+
+// GitLab accepts url-encoded "namespace/path" wherever a numeric project id is expected
+pub fn project_path_id(repo_owner: &str, repo_name: &str) -> String {
+    format!("{}/{}", repo_owner, repo_name).replace('/', "%2F")
+}
+
+pub async fn get_api_paginated(url: &str, access_token: &str, params: Option<HashMap<&str, &str>>) -> Option<Vec<Value>> {
+    let mut is_first_call = true;
+    let mut next_url_mut: Option<String> = None;
+    let mut get_url = url.to_string();
+    let mut values = Vec::<Value>::new();
+    while is_first_call || next_url_mut.is_some() {
+        let mut params_opt = None;
+        if is_first_call {
+            is_first_call = false;
+            params_opt = params.clone();
+        } else {
+            // next link already carries the query params of the first call
+            get_url = next_url_mut.expect("Empty next_url_mut");
+        }
+        let response_opt = get_api_response(&get_url, access_token, &params_opt).await;
+        if response_opt.is_none() {
+            log::error!("[gitlab/get_api_paginated] Unable to call get api and get response for: {:?}, {:?}",
+                &get_url, &params_opt);
+            return None;
+        }
+        let response = response_opt.expect("Empty response_opt");
+        let next_url = extract_next_url(&response);
+        let deserialized_res = response.json::<Value>().await;
+        if deserialized_res.is_err() {
+            let e = deserialized_res.expect_err("Empty error in deserialized_res");
+            log::error!("[gitlab/get_api_paginated] deserialization failed for: {:?}, error: {:?}", &get_url, e);
+            return None;
+        }
+        let deserialized_val = deserialized_res.expect("Uncaught error in deserialized_res");
+        values.push(deserialized_val);
+        next_url_mut = next_url;
+    }
+    return Some(values);
+}
+
+pub async fn get_api_response(url: &str, access_token: &str, params: &Option<HashMap<&str, &str>>) -> Option<Response> {
+    let headers_opt = prepare_headers(access_token);
+    if headers_opt.is_none() {
+        log::error!("[gitlab/get_api_response] Unable to prepare headers for url: {}", url);
+        return None;
+    }
+    let headers = headers_opt.expect("Empty headers_opt");
+    let client = get_client();
+    let request_start = Instant::now();
+    let get_response = client.get(url)
+        .headers(headers)
+        .query(params)
+        .send()
+        .await;
+    record_provider_response("gitlab", &get_response, request_start.elapsed());
+    if get_response.is_err() {
+        let e = get_response.expect_err("No error in get_response");
+        log::error!("[gitlab/get_api_response] Error sending GET request to {}, error: {}", url, e);
+        return None;
+    }
+    let response = get_response.expect("Uncaught error in get_response");
+    if !response.status().is_success() {
+        log::error!("[gitlab/get_api_response] Failed to call Gitlab API {}, status: {}",
+            url, response.status());
+        return None;
+    }
+    return Some(response);
+}
+
+fn extract_next_url(response: &Response) -> Option<String> {
+    let link_header = response.headers().get(header::LINK);
+    return link_header.and_then(|value| {
+        value.to_str().ok().and_then(|header_value| {
+            header_value.split(',')
+                .find(|part| part.contains(r#"rel="next""#))
+                .and_then(|next_link_part| {
+                    next_link_part.split(';')
+                        .next()
+                        .map(|url| url.trim_matches(&[' ', '<', '>', '"'] as &[_]))
+                        .map(str::to_string)
+                })
+        })
+    });
+}
+
+pub fn prepare_headers(access_token: &str) -> Option<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    let auth_header_res = HeaderValue::from_str(&format!("Bearer {}", access_token));
+    if auth_header_res.is_err() {
+        let e = auth_header_res.expect_err("Empty error in auth_header_res");
+        log::error!("[gitlab/prepare_headers] Invalid auth header: {:?}", e);
+        return None;
+    }
+    let header_authval = auth_header_res.expect("Uncaught error in auth_header_res");
+    headers.insert(AUTHORIZATION, header_authval);
+    headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+    headers.insert(USER_AGENT, HeaderValue::from_static("Vibinex code review app"));
+    return Some(headers);
+}
@@ -0,0 +1,6 @@
+pub mod auth;
+pub mod repos;
+pub mod config;
+pub mod webhook;
+pub mod prs;
+pub mod user;
\ No newline at end of file
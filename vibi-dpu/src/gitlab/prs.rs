@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::db::prs::update_pr_info_in_db;
+use crate::utils::pr_info::PrInfo;
+use crate::utils::user::ProviderEnum;
+
+use super::config::{get_api_paginated, get_api_response, gitlab_base_url, project_path_id};
+
+pub async fn list_prs_gitlab(repo_owner: &str, repo_name: &str, access_token: &str, state: &str) -> Option<Vec<String>> {
+    let url = format!("{}/projects/{}/merge_requests", gitlab_base_url(), project_path_id(repo_owner, repo_name));
+    let mut params = HashMap::new();
+    params.insert("state", state);
+    params.insert("per_page", "100");
+    let mrs_opt = get_api_paginated(&url, access_token, Some(params)).await;
+    if mrs_opt.is_none() {
+        log::error!("[list_prs_gitlab] Unable to get merge requests for repository: {}", repo_name);
+        return None;
+    }
+    let mrs_pages = mrs_opt.expect("Empty mrs_opt");
+    let mut pr_list = Vec::<String>::new();
+    for mrs_page in mrs_pages {
+        let mrs_arr_opt = mrs_page.as_array();
+        if mrs_arr_opt.is_none() {
+            log::error!("[list_prs_gitlab] Expected an array of merge requests: {:?}", &mrs_page);
+            continue;
+        }
+        for mr in mrs_arr_opt.expect("Empty mrs_arr_opt") {
+            pr_list.push(mr["iid"].to_string());
+        }
+    }
+    Some(pr_list)
+}
+
+pub async fn get_pr_info_gitlab(repo_owner: &str, repo_name: &str, access_token: &str, pr_number: &str) -> Option<PrInfo> {
+    let url = format!("{}/projects/{}/merge_requests/{}",
+        gitlab_base_url(), project_path_id(repo_owner, repo_name), pr_number);
+    log::debug!("[get_pr_info_gitlab] URL: {:?}", &url);
+    let response_opt = get_api_response(&url, access_token, &None).await;
+    if response_opt.is_none() {
+        log::error!("[get_pr_info_gitlab] Failed to get MR info for {}/{}!{}", repo_owner, repo_name, pr_number);
+        return None;
+    }
+    let response = response_opt.expect("Empty response_opt");
+    let parse_result = response.json::<Value>().await;
+    if parse_result.is_err() {
+        let e = parse_result.expect_err("No error in parsing");
+        log::error!("[get_pr_info_gitlab] Error parsing MR data: {:?}", e);
+        return None;
+    }
+    let mr_data = parse_result.expect("Uncaught error in parsing MR data");
+    let pr_info_opt = parse_mr_info(&mr_data);
+    log::debug!("[get_pr_info_gitlab] PR info: {:?}", &pr_info_opt);
+    return pr_info_opt;
+}
+
+fn parse_mr_info(mr_data: &Value) -> Option<PrInfo> {
+    // diff_refs is not populated until GitLab finishes computing the MR diff
+    let pr_head_commit = mr_data["diff_refs"]["head_sha"].as_str()
+        .or_else(|| mr_data["sha"].as_str())?;
+    Some(PrInfo {
+        base_head_commit: mr_data["diff_refs"]["base_sha"].as_str()?.to_string(),
+        pr_head_commit: pr_head_commit.to_string(),
+        state: mr_data["state"].as_str()?.to_string(),
+        pr_branch: mr_data["source_branch"].as_str()?.to_string(),
+        author: mr_data["author"]["username"].as_str().map(str::to_string),
+    })
+}
+
+pub async fn get_and_store_pr_info(repo_owner: &str, repo_name: &str, access_token: &str, pr_number: &str) -> Option<PrInfo> {
+    if let Some(pr_info) = get_pr_info_gitlab(repo_owner, repo_name, access_token, pr_number).await {
+        update_pr_info_in_db(repo_owner, repo_name, &pr_info, pr_number,
+            &ProviderEnum::Gitlab.to_string().to_lowercase()).await;
+        return Some(pr_info);
+    }
+    log::error!("[get_and_store_pr_info] No MR info available for MR: {:?} repository: {:?} repo_owner: {:?}",
+        pr_number, repo_name, repo_owner);
+    return None;
+}
+
+// Open merge requests anywhere on the instance with this user as a reviewer
+pub async fn open_review_request_count(username: &str, access_token: &str) -> Option<usize> {
+    let url = format!("{}/merge_requests", gitlab_base_url());
+    let mut params = HashMap::new();
+    params.insert("reviewer_username", username);
+    params.insert("state", "opened");
+    params.insert("scope", "all");
+    params.insert("per_page", "1");
+    let response_opt = get_api_response(&url, access_token, &Some(params)).await;
+    if response_opt.is_none() {
+        log::error!("[gitlab/open_review_request_count] Unable to get merge requests for: {}", username);
+        return None;
+    }
+    let response = response_opt.expect("Empty response_opt");
+    let total_opt = response.headers().get("x-total")
+        .and_then(|total| total.to_str().ok())
+        .and_then(|total| total.parse::<usize>().ok());
+    if total_opt.is_some() {
+        return total_opt;
+    }
+    // GitLab leaves out x-total when counting is too expensive
+    let mrs = response.json::<Value>().await.unwrap_or_default();
+    return mrs.as_array().map(|mrs| mrs.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[tokio::test]
+    async fn merge_request_is_parsed_into_pr_info() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind test server");
+        let address = listener.local_addr().expect("test server has no address");
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("failed to accept request");
+            let mut request = [0; 4096];
+            let read = stream.read(&mut request).expect("failed to read request");
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let body = r#"{"iid":7,"state":"opened","source_branch":"feature","sha":"def456",
+                "author":{"username":"alice"},
+                "diff_refs":{"base_sha":"abc123","head_sha":"def456","start_sha":"abc123"}}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body);
+            stream.write_all(response.as_bytes()).expect("failed to write response");
+            request
+        });
+        env::set_var("GITLAB_BASE_URL", format!("http://{address}/api/v4"));
+
+        let pr_info = get_pr_info_gitlab("group/sub", "project", "test-token", "7").await
+            .expect("merge request should be parsed");
+
+        let request = server.join().expect("test server panicked");
+        assert!(request.starts_with("GET /api/v4/projects/group%2Fsub%2Fproject/merge_requests/7 "));
+        assert!(request.to_lowercase().contains("authorization: bearer test-token"));
+        assert_eq!(pr_info.base_head_commit, "abc123");
+        assert_eq!(pr_info.pr_head_commit, "def456");
+        assert_eq!(pr_info.state, "opened");
+        assert_eq!(pr_info.pr_branch, "feature");
+        assert_eq!(pr_info.author, Some("alice".to_string()));
+    }
+}
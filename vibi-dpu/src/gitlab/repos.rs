@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::config::{get_api_paginated, gitlab_base_url};
+use crate::db::repo::save_repo_to_db;
+use crate::utils::repo::Repository;
+use crate::utils::user::ProviderEnum;
+
+pub async fn get_user_gitlab_repos(access_token: &str) -> Option<Vec<Repository>> {
+    let repos_url = format!("{}/projects", gitlab_base_url());
+    let mut params = HashMap::new();
+    params.insert("membership", "true");
+    // developer access is required to add webhooks and notes
+    params.insert("min_access_level", "30");
+    params.insert("per_page", "100");
+    let repos_opt = get_api_paginated(&repos_url, access_token, Some(params)).await;
+    if repos_opt.is_none() {
+        log::error!("[get_user_gitlab_repos] Unable to call get api and get all projects");
+        return None;
+    }
+    let repos_val = repos_opt.expect("Empty repos_opt");
+    let repositories = deserialize_repos(repos_val);
+    log::debug!("[get_user_gitlab_repos] Fetched {:?} repositories from Gitlab", &repositories);
+    return Some(repositories);
+}
+
+fn deserialize_repos(repos_val: Vec<Value>) -> Vec<Repository> {
+    let mut all_repos = Vec::new();
+    for response_json in repos_val {
+        let repo_json_opt = response_json.as_array();
+        if repo_json_opt.is_none() {
+            log::error!("[deserialize_repos] Unable to deserialize repo value: {:?}", &response_json);
+            continue;
+        }
+        let repo_page_json = repo_json_opt.expect("Empty repo_json_opt");
+        for repo_json in repo_page_json {
+            let repo = deserialize_repo_object(repo_json);
+            save_repo_to_db(&repo);
+            all_repos.push(repo);
+        }
+    }
+    return all_repos;
+}
+
+fn deserialize_repo_object(repo_json: &Value) -> Repository {
+    let namespace = repo_json["namespace"]["full_path"].to_string().trim_matches('"').to_string();
+    let is_private = repo_json["visibility"].as_str() != Some("public");
+    let repo = Repository::new(
+        repo_json["path"].to_string().trim_matches('"').to_string(),
+        repo_json["id"].to_string().trim_matches('"').to_string(),
+        namespace.clone(),
+        is_private,
+        repo_json["ssh_url_to_repo"].to_string().trim_matches('"').to_string(),
+        repo_json["namespace"]["name"].as_str().map(str::to_string),
+        repo_json["namespace"]["kind"].as_str().map(str::to_string),
+        namespace,
+        None,
+        ProviderEnum::Gitlab.to_string().to_lowercase(),
+    );
+    return repo;
+}
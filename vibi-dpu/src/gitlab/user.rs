@@ -0,0 +1,25 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::config::{get_api_response, gitlab_base_url};
+
+pub async fn get_user_id(username: &str, access_token: &str) -> Option<u64> {
+    let url = format!("{}/users", gitlab_base_url());
+    let mut params = HashMap::new();
+    params.insert("username", username);
+    let response_opt = get_api_response(&url, access_token, &Some(params)).await;
+    if response_opt.is_none() {
+        log::error!("[gitlab/get_user_id] Unable to get user for username: {}", username);
+        return None;
+    }
+    let response = response_opt.expect("Empty response_opt");
+    let users_res = response.json::<Vec<Value>>().await;
+    if users_res.is_err() {
+        let e = users_res.expect_err("No error in users_res");
+        log::error!("[gitlab/get_user_id] Unable to deserialize users: {:?}", e);
+        return None;
+    }
+    let users = users_res.expect("Uncaught error in users_res");
+    return users.first().and_then(|user| user["id"].as_u64());
+}
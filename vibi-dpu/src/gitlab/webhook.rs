@@ -0,0 +1,120 @@
+use std::env;
+use std::time::Instant;
+
+use reqwest::{Response, Error};
+use serde_json::{json, Value};
+
+use crate::{db::webhook::save_gitlab_webhook_to_db, utils::gitlab_webhook::Webhook, gitlab::config::{gitlab_base_url, get_api_paginated, project_path_id}};
+use crate::metrics::registry::record_provider_response;
+use crate::utils::reqwest_client::get_client;
+use crate::webhook_queue::listener::webhook_secret;
+use super::config::prepare_headers;
+
+pub async fn get_webhooks_in_repo(repo_owner: &str, repo_name: &str, access_token: &str) -> Option<Vec<Webhook>> {
+    let url = format!("{}/projects/{}/hooks", gitlab_base_url(), project_path_id(repo_owner, repo_name));
+    log::debug!("[get_webhooks_in_repo] Getting webhooks from {}", url);
+    let response_opt = get_api_paginated(&url, access_token, None).await;
+    if response_opt.is_none() {
+        log::error!("[get_webhooks_in_repo] Unable to call get api and get all webhooks");
+        return None;
+    }
+    let webhook_val = response_opt.expect("Empty response_opt");
+    let mut all_webhooks = Vec::new();
+    for response_json in webhook_val {
+        let webhook_json_opt = response_json.as_array();
+        if webhook_json_opt.is_none() {
+            log::error!("[get_webhooks_in_repo] Unable to deserialize webhook value: {:?}", &response_json);
+            continue;
+        }
+        for webhook_json in webhook_json_opt.expect("Empty webhook_json_opt") {
+            all_webhooks.push(deserialize_webhook_object(webhook_json));
+        }
+    }
+    return Some(all_webhooks);
+}
+
+fn deserialize_webhook_object(webhook_json: &Value) -> Webhook {
+    return Webhook::new(
+        webhook_json["id"].to_string(),
+        webhook_json["project_id"].to_string(),
+        webhook_json["created_at"].to_string().replace('"', ""),
+        webhook_json["merge_requests_events"].as_bool().unwrap_or(false),
+        webhook_json["url"].to_string().replace('"', ""),
+    );
+}
+
+pub async fn add_webhook(repo_owner: &str, repo_name: &str, access_token: &str) {
+    let url = format!("{}/projects/{}/hooks", gitlab_base_url(), project_path_id(repo_owner, repo_name));
+    let headers_map_opt = prepare_headers(access_token);
+    if headers_map_opt.is_none() {
+        return;
+    }
+    let headers_map = headers_map_opt.expect("Empty headers_map_opt");
+    let callback_url = format!("{}/api/gitlab/callbacks/webhook",
+        env::var("SERVER_URL").expect("SERVER_URL must be set"));
+    let payload = webhook_payload(&callback_url, webhook_secret().as_deref());
+    let request_start = Instant::now();
+    let response = get_client()
+        .post(&url)
+        .headers(headers_map)
+        .json(&payload)
+        .send()
+        .await;
+    record_provider_response("gitlab", &response, request_start.elapsed());
+    process_add_webhook_response(response).await;
+}
+
+// GitLab echoes token back in X-Gitlab-Token, which the webhook listener checks
+fn webhook_payload(callback_url: &str, secret_opt: Option<&str>) -> Value {
+    let mut payload = json!({
+        "url": callback_url,
+        "merge_requests_events": true,
+        "push_events": false,
+        "enable_ssl_verification": true,
+    });
+    if let Some(secret) = secret_opt {
+        payload["token"] = json!(secret);
+    }
+    return payload;
+}
+
+async fn process_add_webhook_response(response: Result<Response, Error>) {
+    if response.is_err() {
+        let err = response.expect_err("No error in response");
+        log::error!("[process_add_webhook_response] Error in api call: {:?}", err);
+        return;
+    }
+    let res = response.expect("Uncaught error in response");
+    if !res.status().is_success() {
+        log::error!("[process_add_webhook_response] Failed to add webhook. Status code: {}, Text: {:?}",
+            res.status(), res.text().await);
+        return;
+    }
+    let webhook_json_res = res.json::<Value>().await;
+    if webhook_json_res.is_err() {
+        let e = webhook_json_res.expect_err("No error in webhook_json_res");
+        log::error!("[process_add_webhook_response] Unable to deserialize webhook response: {:?}", e);
+        return;
+    }
+    let webhook_json = webhook_json_res.expect("Uncaught error in webhook_json_res");
+    log::info!("Webhook added: {:?}", &webhook_json["url"]);
+    let webhook = deserialize_webhook_object(&webhook_json);
+    save_gitlab_webhook_to_db(&webhook);
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::HeaderMap;
+
+    use super::*;
+    use crate::webhook_queue::listener::verify_delivery;
+
+    #[test]
+    fn registered_token_passes_verification() {
+        let payload = webhook_payload("https://dpu.example.com/api/gitlab/callbacks/webhook", Some("s3cret"));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-gitlab-token", payload["token"].as_str().expect("token is set").parse().expect("valid header"));
+        assert!(verify_delivery("gitlab", &headers, b"{}", "s3cret"));
+        assert!(webhook_payload("https://dpu.example.com", None).get("token").is_none());
+    }
+}
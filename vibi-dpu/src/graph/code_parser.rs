@@ -0,0 +1,484 @@
+use std::path::Path;
+
+use tree_sitter::{Language, Node, Parser, Tree};
+
+use crate::utils::repo_config::RepoConfig;
+
+use super::{file_imports::ImportPathOutput, function_call::FunctionCall, function_line_range::FuncDefInfo, function_name::FunctionDefinition};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphBackend {
+    Parser,
+    Llm,
+}
+
+impl GraphBackend {
+    pub fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        if let Some(backend) = repo_config.diff_graph_backend() {
+            if backend.eq_ignore_ascii_case("llm") {
+                return GraphBackend::Llm;
+            }
+        }
+        return GraphBackend::Parser;
+    }
+
+    // Languages without a grammar always go to the llm
+    pub fn uses_parser(&self, lang: &str) -> bool {
+        return *self == GraphBackend::Parser && language_spec(lang).is_some();
+    }
+}
+
+struct LanguageSpec {
+    language: Language,
+    function_kinds: &'static [&'static str],
+    container_kinds: &'static [&'static str],
+    call_kinds: &'static [&'static str],
+    import_kinds: &'static [&'static str],
+}
+
+const JS_FUNCTIONS: &[&str] = &["function_declaration", "generator_function_declaration", "method_definition"];
+const JS_CONTAINERS: &[&str] = &["class_declaration", "class"];
+const TS_FUNCTIONS: &[&str] = &["function_declaration", "generator_function_declaration", "method_definition",
+    "function_signature", "method_signature", "abstract_method_signature"];
+const TS_CONTAINERS: &[&str] = &["class_declaration", "class", "abstract_class_declaration", "interface_declaration"];
+const JS_CALLS: &[&str] = &["call_expression", "new_expression"];
+const JS_IMPORTS: &[&str] = &["import_statement"];
+
+// Keyed on the language names returned by detect_language
+fn language_spec(lang: &str) -> Option<LanguageSpec> {
+    let spec = match lang {
+        "Rust" => LanguageSpec {
+            language: tree_sitter_rust::LANGUAGE.into(),
+            function_kinds: &["function_item", "function_signature_item"],
+            container_kinds: &["impl_item", "trait_item", "mod_item"],
+            call_kinds: &["call_expression"],
+            import_kinds: &["use_declaration"],
+        },
+        "Python" => LanguageSpec {
+            language: tree_sitter_python::LANGUAGE.into(),
+            function_kinds: &["function_definition"],
+            container_kinds: &["class_definition"],
+            call_kinds: &["call"],
+            import_kinds: &["import_from_statement", "import_statement"],
+        },
+        "JavaScript" | "React JSX" => LanguageSpec {
+            language: tree_sitter_javascript::LANGUAGE.into(),
+            function_kinds: JS_FUNCTIONS,
+            container_kinds: JS_CONTAINERS,
+            call_kinds: JS_CALLS,
+            import_kinds: JS_IMPORTS,
+        },
+        "TypeScript" => LanguageSpec {
+            language: tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            function_kinds: TS_FUNCTIONS,
+            container_kinds: TS_CONTAINERS,
+            call_kinds: JS_CALLS,
+            import_kinds: JS_IMPORTS,
+        },
+        "React TypeScript TSX" => LanguageSpec {
+            language: tree_sitter_typescript::LANGUAGE_TSX.into(),
+            function_kinds: TS_FUNCTIONS,
+            container_kinds: TS_CONTAINERS,
+            call_kinds: JS_CALLS,
+            import_kinds: JS_IMPORTS,
+        },
+        "Go" => LanguageSpec {
+            language: tree_sitter_go::LANGUAGE.into(),
+            function_kinds: &["function_declaration", "method_declaration"],
+            container_kinds: &[],
+            call_kinds: &["call_expression"],
+            import_kinds: &[],
+        },
+        "Java" => LanguageSpec {
+            language: tree_sitter_java::LANGUAGE.into(),
+            function_kinds: &["method_declaration", "constructor_declaration"],
+            container_kinds: &["class_declaration", "interface_declaration", "enum_declaration", "record_declaration"],
+            call_kinds: &["method_invocation", "object_creation_expression"],
+            import_kinds: &["import_declaration"],
+        },
+        "C" => LanguageSpec {
+            language: tree_sitter_c::LANGUAGE.into(),
+            function_kinds: &["function_definition"],
+            container_kinds: &[],
+            call_kinds: &["call_expression"],
+            import_kinds: &[],
+        },
+        "C++" => LanguageSpec {
+            language: tree_sitter_cpp::LANGUAGE.into(),
+            function_kinds: &["function_definition"],
+            container_kinds: &["class_specifier", "struct_specifier", "namespace_definition"],
+            call_kinds: &["call_expression", "new_expression"],
+            import_kinds: &[],
+        },
+        "Ruby" => LanguageSpec {
+            language: tree_sitter_ruby::LANGUAGE.into(),
+            function_kinds: &["method", "singleton_method"],
+            container_kinds: &["class", "module"],
+            call_kinds: &["call"],
+            import_kinds: &[],
+        },
+        _ => return None,
+    };
+    return Some(spec);
+}
+
+// Line numbers are zero based rows, the same numbering numbered_content shows the llm,
+// so parser and llm results can be used interchangeably.
+pub struct ParsedFile {
+    source: String,
+    tree: Tree,
+    spec: LanguageSpec,
+}
+
+impl ParsedFile {
+    pub fn from_file(filepath: &Path, lang: &str) -> Option<Self> {
+        let source_res = std::fs::read_to_string(filepath);
+        if source_res.is_err() {
+            let e = source_res.expect_err("Empty error in source_res");
+            log::error!("[ParsedFile/from_file] Unable to read file: {:?}, error: {:?}", filepath, e);
+            return None;
+        }
+        let source = source_res.expect("Uncaught error in source_res");
+        return Self::from_source(source, lang);
+    }
+
+    pub fn from_source(source: String, lang: &str) -> Option<Self> {
+        let spec_opt = language_spec(lang);
+        if spec_opt.is_none() {
+            log::debug!("[ParsedFile/from_source] No grammar for language: {}", lang);
+            return None;
+        }
+        let spec = spec_opt.expect("Empty spec_opt");
+        let mut parser = Parser::new();
+        if let Err(e) = parser.set_language(&spec.language) {
+            log::error!("[ParsedFile/from_source] Unable to load grammar for {}: {:?}", lang, e);
+            return None;
+        }
+        let tree_opt = parser.parse(&source, None);
+        if tree_opt.is_none() {
+            log::error!("[ParsedFile/from_source] Unable to parse {} source", lang);
+            return None;
+        }
+        let tree = tree_opt.expect("Empty tree_opt");
+        return Some(Self { source, tree, spec });
+    }
+
+    pub fn function_defs(&self) -> Vec<FuncDefInfo> {
+        let mut func_defs = Vec::<FuncDefInfo>::new();
+        for node in descendants(self.tree.root_node()) {
+            if let Some(func_def) = self.func_def_info(node) {
+                func_defs.push(func_def);
+            }
+        }
+        return func_defs;
+    }
+
+    pub fn definitions(&self) -> Vec<FunctionDefinition> {
+        return self.function_defs().into_iter()
+            .map(|func_def| FunctionDefinition {
+                line_number: func_def.line_start,
+                structure_name: func_def.name,
+            })
+            .collect();
+    }
+
+    // Innermost function whose definition covers the line, decorators and annotations included,
+    // since hunk headers can point at any of those lines
+    pub fn function_name_at_line(&self, line_number: usize) -> Option<String> {
+        return descendants(self.tree.root_node()).into_iter()
+            .filter_map(|node| {
+                let func_def = self.func_def_info(node)?;
+                let line_start = node.parent()
+                    .filter(|parent| parent.kind() == "decorated_definition")
+                    .map_or(func_def.line_start, |parent| parent.start_position().row);
+                Some((line_start, func_def))
+            })
+            .filter(|(line_start, func_def)| *line_start <= line_number && line_number <= func_def.line_end)
+            .min_by_key(|(line_start, func_def)| func_def.line_end - line_start)
+            .map(|(_, func_def)| func_def.name);
+    }
+
+    // Innermost function containing the line
+    pub fn enclosing_function(&self, line_number: usize) -> Option<FuncDefInfo> {
+        return self.function_defs().into_iter()
+            .filter(|func_def| func_def.line_start <= line_number && line_number <= func_def.line_end)
+            .min_by_key(|func_def| func_def.line_end - func_def.line_start);
+    }
+
+    pub fn function_calls(&self) -> Vec<FunctionCall> {
+        let mut func_calls = Vec::<FunctionCall>::new();
+        for node in descendants(self.tree.root_node()) {
+            if !self.spec.call_kinds.contains(&node.kind()) {
+                continue;
+            }
+            let callee_opt = ["function", "method", "name", "constructor", "type"].iter()
+                .find_map(|field| node.child_by_field_name(field));
+            if let Some(callee) = callee_opt {
+                if let Some(func_name) = self.trailing_name(callee) {
+                    func_calls.push(FunctionCall::new(node.start_position().row as u32, func_name));
+                }
+            }
+        }
+        return func_calls;
+    }
+
+    pub fn function_calls_in_lines(&self, start_line: usize, end_line: usize) -> Vec<FunctionCall> {
+        return self.function_calls().into_iter()
+            .filter(|func_call| {
+                let line_number = func_call.line_number().to_owned() as usize;
+                start_line <= line_number && line_number <= end_line
+            })
+            .collect();
+    }
+
+    pub fn import_for(&self, func_name: &str) -> Option<ImportPathOutput> {
+        for node in descendants(self.tree.root_node()) {
+            if !self.spec.import_kinds.contains(&node.kind()) {
+                continue;
+            }
+            let imported_opt = descendants(node).into_iter()
+                .find(|child| child.named_child_count() == 0 && self.text(*child) == func_name);
+            if imported_opt.is_none() {
+                continue;
+            }
+            let imported = imported_opt.expect("Empty imported_opt");
+            if let Some(import_path) = self.import_path(node, imported) {
+                if import_path.is_empty() {
+                    continue;
+                }
+                return Some(ImportPathOutput::new(
+                    node.start_position().row as u32,
+                    self.text(node).to_string(),
+                    import_path));
+            }
+        }
+        return None;
+    }
+
+    fn func_def_info(&self, node: Node) -> Option<FuncDefInfo> {
+        let name;
+        if self.spec.function_kinds.contains(&node.kind()) {
+            name = self.definition_name(node)?;
+        } else if node.kind() == "variable_declarator" {
+            // const handler = () => {...} and friends
+            let value = node.child_by_field_name("value")?;
+            if !["arrow_function", "function_expression", "function"].contains(&value.kind()) {
+                return None;
+            }
+            name = self.trailing_name(node.child_by_field_name("name")?)?;
+        } else {
+            return None;
+        }
+        return Some(FuncDefInfo {
+            name,
+            line_start: node.start_position().row,
+            line_end: node.end_position().row,
+            parent: self.parent_name(node).unwrap_or_default(),
+        });
+    }
+
+    fn definition_name(&self, node: Node) -> Option<String> {
+        if let Some(name_node) = node.child_by_field_name("name") {
+            return self.trailing_name(name_node);
+        }
+        // C and C++ nest the name in a chain of declarators
+        let mut declarator = node.child_by_field_name("declarator")?;
+        while let Some(inner) = declarator.child_by_field_name("declarator") {
+            declarator = inner;
+        }
+        return self.trailing_name(declarator);
+    }
+
+    fn parent_name(&self, node: Node) -> Option<String> {
+        let mut current = node.parent();
+        while let Some(parent) = current {
+            if self.spec.container_kinds.contains(&parent.kind()) {
+                let name_node = parent.child_by_field_name("name")
+                    .or_else(|| parent.child_by_field_name("type"))?;
+                return self.trailing_name(name_node);
+            }
+            current = parent.parent();
+        }
+        return None;
+    }
+
+    // Last identifier of a possibly qualified name, `self.client.send` -> `send`
+    fn trailing_name(&self, node: Node) -> Option<String> {
+        if node.named_child_count() == 0 {
+            let text = self.text(node);
+            if text.is_empty() {
+                return None;
+            }
+            return Some(text.to_string());
+        }
+        for field in ["name", "field", "property", "attribute", "function", "type"] {
+            if let Some(child) = node.child_by_field_name(field) {
+                return self.trailing_name(child);
+            }
+        }
+        if node.kind() == "generic_type" {
+            return self.trailing_name(node.named_child(0)?);
+        }
+        let last_idx = node.named_child_count() - 1;
+        return self.trailing_name(node.named_child(last_idx)?);
+    }
+
+    fn import_path(&self, import_node: Node, imported: Node) -> Option<String> {
+        match import_node.kind() {
+            "use_declaration" => {
+                // Collect the `path` of every use list and scoped name wrapping the imported name
+                let mut segments = Vec::<&str>::new();
+                let mut child = imported;
+                let mut current = imported.parent();
+                while let Some(parent) = current {
+                    if parent.id() == import_node.id() {
+                        break;
+                    }
+                    if let Some(path) = parent.child_by_field_name("path") {
+                        if path.id() != child.id() {
+                            segments.insert(0, self.text(path));
+                        }
+                    }
+                    child = parent;
+                    current = parent.parent();
+                }
+                let module_path = segments.join("::").split("::")
+                    .filter(|segment| !["crate", "self", "super", ""].contains(segment))
+                    .collect::<Vec<&str>>()
+                    .join("/");
+                return Some(module_path);
+            }
+            "import_from_statement" => {
+                let module = self.text(import_node.child_by_field_name("module_name")?);
+                return Some(module.trim_start_matches('.').replace('.', "/"));
+            }
+            "import_statement" if import_node.child_by_field_name("source").is_some() => {
+                let source = self.text(import_node.child_by_field_name("source")?)
+                    .trim_matches(|c| c == '"' || c == '\'' || c == '`');
+                let mut module_path = source;
+                while let Some(stripped) = module_path.strip_prefix("./")
+                    .or_else(|| module_path.strip_prefix("../"))
+                    .or_else(|| module_path.strip_prefix("@/")) {
+                    module_path = stripped;
+                }
+                let module_path = Path::new(module_path).with_extension("");
+                return Some(module_path.to_string_lossy().to_string());
+            }
+            "import_statement" => {
+                // python `import a.b`, the imported name has to be the module itself
+                return Some(self.text(imported.parent()?).replace('.', "/"));
+            }
+            "import_declaration" => {
+                let import_text = self.text(import_node)
+                    .trim_start_matches("import")
+                    .trim_end_matches(';')
+                    .trim()
+                    .trim_start_matches("static ");
+                return Some(import_text.replace('.', "/"));
+            }
+            _ => return None,
+        }
+    }
+
+    fn text(&self, node: Node) -> &str {
+        return node.utf8_text(self.source.as_bytes()).unwrap_or("");
+    }
+}
+
+fn descendants(node: Node) -> Vec<Node> {
+    let mut nodes = Vec::<Node>::new();
+    let mut cursor = node.walk();
+    loop {
+        nodes.push(cursor.node());
+        if cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.node().id() == node.id() {
+                return nodes;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return nodes;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_defs_calls_and_imports_are_extracted() {
+        let source = r#"use crate::utils::gitops::{commit_exists, StatItem};
+use super::utils::call_llm_api;
+
+struct Graph;
+
+impl Graph {
+    fn render(&self) -> String {
+        let ok = commit_exists("abc", "/tmp");
+        call_llm_api(format!("{}", ok))
+    }
+}
+
+fn main() {
+    Graph.render();
+}
+"#;
+        let parsed = ParsedFile::from_source(source.to_string(), "Rust").expect("rust grammar loads");
+
+        let defs = parsed.function_defs();
+        assert_eq!(defs.iter().map(|def| (def.name().as_str(), *def.line_start(), *def.line_end(), def.parent().as_str())).collect::<Vec<_>>(),
+            vec![("render", 6, 9, "Graph"), ("main", 12, 14, "")]);
+        assert_eq!(parsed.function_name_at_line(12), Some("main".to_string()));
+        assert_eq!(parsed.enclosing_function(8).map(|def| def.name().to_owned()), Some("render".to_string()));
+
+        let calls = parsed.function_calls_in_lines(6, 9);
+        assert_eq!(calls.iter().map(|call| (call.function_name().as_str(), *call.line_number())).collect::<Vec<_>>(),
+            vec![("commit_exists", 7), ("call_llm_api", 8)]);
+
+        let import = parsed.import_for("commit_exists").expect("import is found");
+        assert_eq!(import.get_matching_import().possible_file_path(), "utils/gitops");
+        let import = parsed.import_for("call_llm_api").expect("import is found");
+        assert_eq!(import.get_matching_import().possible_file_path(), "utils");
+        assert!(parsed.import_for("render").is_none());
+    }
+
+    #[test]
+    fn python_and_typescript_outputs_match_the_llm_shapes() {
+        let python = "from .graph.utils import read_file\n\nclass Loader:\n    def load(self, path):\n        return read_file(path)\n";
+        let parsed = ParsedFile::from_source(python.to_string(), "Python").expect("python grammar loads");
+        assert_eq!(parsed.definitions(), vec![FunctionDefinition { line_number: 3, structure_name: "load".to_string() }]);
+        assert_eq!(parsed.function_calls()[0].function_name(), "read_file");
+        assert_eq!(parsed.import_for("read_file").expect("import is found").get_matching_import().possible_file_path(), "graph/utils");
+
+        let typescript = "import { fetchUser } from '../api/users.ts';\n\nexport const loadUser = async (id: string) => {\n  return await fetchUser(id);\n};\n";
+        let parsed = ParsedFile::from_source(typescript.to_string(), "TypeScript").expect("typescript grammar loads");
+        assert_eq!(parsed.function_name_at_line(2), Some("loadUser".to_string()));
+        assert_eq!(parsed.function_calls()[0].line_number(), &3);
+        assert_eq!(parsed.import_for("fetchUser").expect("import is found").get_matching_import().possible_file_path(), "api/users");
+
+        assert!(GraphBackend::Parser.uses_parser("Go"));
+        assert!(!GraphBackend::Parser.uses_parser("Haskell"));
+        assert!(!GraphBackend::Llm.uses_parser("Rust"));
+    }
+
+    #[test]
+    fn function_name_at_line_covers_decorators_and_annotations() {
+        let python = "class Api:\n    @staticmethod\n    @cached\n    def fetch(url):\n        return url\n";
+        let parsed = ParsedFile::from_source(python.to_string(), "Python").expect("python grammar loads");
+        assert_eq!(parsed.function_name_at_line(1), Some("fetch".to_string()));
+        assert_eq!(parsed.function_name_at_line(3), Some("fetch".to_string()));
+        assert_eq!(parsed.function_name_at_line(0), None);
+
+        let java = "class Users {\n    @Override\n    public String load(String id) {\n        return id;\n    }\n}\n";
+        let parsed = ParsedFile::from_source(java.to_string(), "Java").expect("java grammar loads");
+        assert_eq!(parsed.function_name_at_line(1), Some("load".to_string()));
+        assert_eq!(parsed.function_name_at_line(2), Some("load".to_string()));
+        assert_eq!(parsed.function_name_at_line(5), None);
+    }
+}
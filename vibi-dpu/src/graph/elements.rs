@@ -440,6 +440,46 @@ impl MermaidGraphElements {
         all_elements_str
     }
 
+    // Markdown adjacency list of the call edges, for providers without mermaid
+    pub fn render_table(&self) -> String {
+        let rows = self.edge_rows();
+        if rows.is_empty() {
+            return "No function calls affected by this PR.\n".to_string();
+        }
+        let mut table = "| Caller | Called function | Line | Change |\n".to_string();
+        table += "| ------ | --------------- | ---- | ------ |\n";
+        for (src, dest, line, change) in rows {
+            table += &format!("| `{}` | `{}` | {} | {} |\n", src, dest, line, change);
+        }
+        return table;
+    }
+
+    // Plain text version of the call edges, one edge per line inside a fence
+    pub fn render_text(&self) -> String {
+        let rows = self.edge_rows();
+        if rows.is_empty() {
+            return "No function calls affected by this PR.\n".to_string();
+        }
+        let lines: Vec<String> = rows.iter()
+            .map(|(src, dest, line, change)| format!("{} --(line {}, {})--> {}", src, line, change, dest))
+            .collect();
+        return format!("```text\n{}\n```\n", lines.join("\n"));
+    }
+
+    // (caller, callee, calling line, change) sorted so comments are stable across runs
+    fn edge_rows(&self) -> Vec<(String, String, usize, &'static str)> {
+        let mut rows: Vec<(String, String, usize, &'static str)> = self.edges.values()
+            .map(|edge| (
+                format!("{}::{}", edge.src_subgraph_key(), edge.src_func_key()),
+                format!("{}::{}", edge.dest_subgraph_key(), edge.dest_func_key()),
+                edge.line(),
+                edge_change_label(edge.color()),
+            ))
+            .collect();
+        rows.sort();
+        return rows;
+    }
+
     fn render_edges(&self, review: &Review) -> String {
         let mut edge_defs = Vec::<String>::new();
         let mut default_edge_styles = Vec::<String>::new();
@@ -513,3 +553,34 @@ impl MermaidGraphElements {
         return "".to_string();
     }
 }
+
+fn edge_change_label(color: &str) -> &'static str {
+    match color {
+        "green" => "added",
+        "red" => "deleted",
+        "yellow" => "modified",
+        _ => "unchanged",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_and_text_list_edges_in_order() {
+        let mut elems = MermaidGraphElements::new();
+        elems.add_edge("green", 12, "main", "parse", "src/main.rs", "src/parse.rs", "", "green", &1, &3);
+        elems.add_edge("", 4, "parse", "lex", "src/parse.rs", "src/lex.rs", "", "", &3, &1);
+        let table = elems.render_table();
+        let rows: Vec<&str> = table.lines().skip(2).collect();
+        assert_eq!(rows, vec![
+            "| `src/main.rs::main` | `src/parse.rs::parse` | 12 | added |",
+            "| `src/parse.rs::parse` | `src/lex.rs::lex` | 4 | unchanged |",
+        ]);
+        let text = elems.render_text();
+        assert!(text.starts_with("```text\n"));
+        assert!(text.contains("src/main.rs::main --(line 12, added)--> src/parse.rs::parse"));
+        assert!(MermaidGraphElements::new().render_table().starts_with("No function calls"));
+    }
+}
@@ -2,9 +2,10 @@ use std::{collections::{HashMap, HashSet}, io::BufReader, path::{Path, PathBuf},
 
 use serde::{Deserialize, Serialize};
 use std::io::BufRead;
+use walkdir::WalkDir;
 use crate::utils::review::Review;
 
-use super::{file_imports::ImportDefOutput, function_name::FunctionDefinition, gitops::HunkDiffLines, utils::{call_llm_api, detect_language, numbered_content, read_file, strip_json_prefix}};
+use super::{code_parser::{GraphBackend, ParsedFile}, function_name::FunctionDefinition, gitops::HunkDiffLines, utils::{call_llm_api, detect_language, numbered_content, read_file, strip_json_prefix, LazyPrompt}};
 
 #[derive(Debug, Serialize, Default, Deserialize, Clone)]
 pub struct FunctionCallChunk {
@@ -157,6 +158,10 @@ pub struct FunctionCall {
 }
 
 impl FunctionCall {
+    pub fn new(line_number: u32, function_name: String) -> Self {
+        Self { line_number, function_name }
+    }
+
     pub fn function_name(&self) -> &String {
         &self.function_name
     }
@@ -174,6 +179,10 @@ pub struct FunctionCallsOutput {
 }
 
 impl FunctionCallsOutput {
+    pub fn new(function_calls: Vec<FunctionCall>) -> Self {
+        Self { function_calls, notes: None }
+    }
+
     pub fn function_calls(&self) -> &Vec<FunctionCall> {
         return &self.function_calls
     }
@@ -274,29 +283,26 @@ impl JsonStructure {
 }
 
 pub struct FunctionCallIdentifier {
-    prompt: JsonStructure,
-    chunk_size: usize
+    prompt: LazyPrompt<JsonStructure>,
+    chunk_size: usize,
+    backend: GraphBackend
 }
 
 impl FunctionCallIdentifier {
-    pub fn new() -> Option<Self> {
-        let system_prompt_opt = read_file("/app/prompts/prompt_function_calls");
-        if system_prompt_opt.is_none() {
-            log::error!("[function_calls_in_chunk] Unable to read prompt_function_calls");
-            return None;
-        }
-        let system_prompt_lines = system_prompt_opt.expect("Empty system_prompt");
-        let prompt_json_res = serde_json::from_str(&system_prompt_lines);
-        if prompt_json_res.is_err() {
-            log::error!("[FunctionCallIdentifier/new] Unable to deserialize prompt_json: {:?}",
-                prompt_json_res.expect("Empty prompt_json_res"));
-            return None;
-        }
-        let prompt_json: JsonStructure = prompt_json_res.expect("Empty error in prompt_json_res");
-        return Some(Self { prompt: prompt_json, chunk_size: 30});
+    pub fn new(backend: GraphBackend) -> Self {
+        Self { prompt: LazyPrompt::new("prompt_function_calls"), chunk_size: 30, backend }
     }
 
     pub async fn functions_in_file(&mut self, filepath: &PathBuf, lang: &str) -> Option<FunctionCallsOutput> {
+        if self.backend.uses_parser(lang) {
+            let parsed_file = ParsedFile::from_file(filepath, lang)?;
+            let mut func_calls = FunctionCallsOutput::new(parsed_file.function_calls());
+            func_calls.trim_empty_function_calls();
+            if func_calls.function_calls().is_empty() {
+                return None;
+            }
+            return Some(func_calls);
+        }
         // concatenate functioncallsoutput for all chunks
         let mut all_func_calls: FunctionCallsOutput = FunctionCallsOutput{ function_calls: vec![], notes: None };
         let file_contents_res = std::fs::read_to_string(filepath.clone());
@@ -327,8 +333,9 @@ impl FunctionCallIdentifier {
     async fn functions_in_chunk(&mut self, chunk: &str, filepath: &PathBuf, lang: &str) -> Option<FunctionCallsOutput> {
         let input = InputSchema{ code_chunk: chunk.to_string(), language: lang.to_string(),
             file_path: filepath.to_str().expect("Empty filepath").to_string() };
-        self.prompt.input = Some(input);
-        let prompt_str_res = serde_json::to_string(&self.prompt);
+        let prompt = self.prompt.get()?;
+        prompt.input = Some(input);
+        let prompt_str_res = serde_json::to_string(prompt);
         if prompt_str_res.is_err() {
             log::error!(
                 "[FunctionCallIdentifier/functions_in_chunk] Unable to serialize prompt: {:?}",
@@ -361,6 +368,9 @@ impl FunctionCallIdentifier {
     }
 
     pub async fn function_calls_in_hunks(&mut self, filepath: &PathBuf, lang: &str, diff_hunks: &Vec<HunkDiffLines>) -> Option<Vec<(HunkDiffLines, FunctionCallsOutput)>> {
+        if self.backend.uses_parser(lang) {
+            return function_calls_in_hunks_parsed(filepath, lang, diff_hunks);
+        }
         let file_contents_res = std::fs::read_to_string(filepath.clone());
         if file_contents_res.is_err() {
             log::error!(
@@ -400,7 +410,27 @@ impl FunctionCallIdentifier {
     
 }
 
-pub fn function_calls_search(review: &Review, function_name: &str, lang: &str) -> Option<HashMap<String, Vec<(usize, String)>>>{
+fn function_calls_in_hunks_parsed(filepath: &PathBuf, lang: &str, diff_hunks: &Vec<HunkDiffLines>) -> Option<Vec<(HunkDiffLines, FunctionCallsOutput)>> {
+    let parsed_file = ParsedFile::from_file(filepath, lang)?;
+    let mut hunk_func_pairs: Vec<(HunkDiffLines, FunctionCallsOutput)> = Vec::new();
+    for diff_hunk in diff_hunks {
+        let mut hunk_calls = FunctionCallsOutput::new(
+            parsed_file.function_calls_in_lines(diff_hunk.start_line().to_owned(), diff_hunk.end_line().to_owned()));
+        hunk_calls.trim_empty_function_calls();
+        if !hunk_calls.function_calls().is_empty() {
+            hunk_func_pairs.push((diff_hunk.clone(), hunk_calls));
+        }
+    }
+    if hunk_func_pairs.is_empty() {
+        return None;
+    }
+    return Some(hunk_func_pairs);
+}
+
+pub fn function_calls_search(review: &Review, function_name: &str, lang: &str, backend: &GraphBackend) -> Option<HashMap<String, Vec<(usize, String)>>>{
+    if backend.uses_parser(lang) {
+        return Some(parsed_function_calls_search(review.clone_dir(), function_name, lang));
+    }
     let pattern = format!(r"{}\([^\)]*\)", function_name); // Regex pattern for the specific function call
     let directory = review.clone_dir();                    // The directory to search in
 
@@ -456,6 +486,46 @@ pub fn function_calls_search(review: &Review, function_name: &str, lang: &str) -
     Some(results)
 }
 
+// Same shape as the rg search above: file path -> (1-based line number, line content)
+fn parsed_function_calls_search(directory: &str, function_name: &str, lang: &str) -> HashMap<String, Vec<(usize, String)>> {
+    let mut results: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+    let entries = WalkDir::new(directory).into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_map(|entry| entry.ok());
+    for entry in entries {
+        let file = entry.path().to_string_lossy().to_string();
+        if detect_language(&file).as_deref() != Some(lang) {
+            continue;
+        }
+        let file_contents_res = std::fs::read_to_string(entry.path());
+        if file_contents_res.is_err() {
+            log::debug!("[parsed_function_calls_search] Unable to read file: {}, error: {:?}",
+                &file, file_contents_res.expect_err("Empty error in file_contents_res"));
+            continue;
+        }
+        let file_contents = file_contents_res.expect("Uncaught error in file_contents_res");
+        // skip parsing files that cannot contain the call
+        if !file_contents.contains(function_name) {
+            continue;
+        }
+        let file_lines: Vec<String> = file_contents.lines().map(|line| line.to_string()).collect();
+        let parsed_file_opt = ParsedFile::from_source(file_contents, lang);
+        if parsed_file_opt.is_none() {
+            continue;
+        }
+        let parsed_file = parsed_file_opt.expect("Empty parsed_file_opt");
+        for func_call in parsed_file.function_calls() {
+            if func_call.function_name() != function_name {
+                continue;
+            }
+            let row = func_call.line_number().to_owned() as usize;
+            let line_content = file_lines.get(row).cloned().unwrap_or_default();
+            results.entry(file.clone()).or_insert_with(Vec::new).push((row + 1, line_content));
+        }
+    }
+    return results;
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FunctionCallValidatorInput {
     code_line: String,               // A line of code that potentially contains the function call or object usage
@@ -508,31 +578,28 @@ impl FunctionCallValidatorPrompt {
 
 #[derive(Debug)]
 pub struct FunctionCallValidator {
-    prompt: FunctionCallValidatorPrompt
+    prompt: LazyPrompt<FunctionCallValidatorPrompt>,
+    backend: GraphBackend
 }
 
 impl FunctionCallValidator {
-    pub fn new() -> Option<Self> {
-        let system_prompt_opt = read_file("/app/prompts/prompt_function_call_validator");
-        if system_prompt_opt.is_none() {
-            log::debug!("[FunctionCallValidator/new] Unable to read prompt_function_call_validator");
-            return None;
-        }
-        let system_prompt_str = system_prompt_opt.expect("Empty system_prompt_opt");
-        let sys_prompt_struct_res = serde_json::from_str(&system_prompt_str);
-        if sys_prompt_struct_res.is_err() {
-            log::debug!("[FunctionCallValidator/new] Unable to deserialize sys prompt: {:?}",
-                sys_prompt_struct_res.expect_err("Empty error"));
-            return None;
-        }
-        let sys_prompt_struct: FunctionCallValidatorPrompt = sys_prompt_struct_res.expect("Uncaught error in sys_prompt_struct_res");
-        return Some(Self {
-            prompt: sys_prompt_struct
-        });
+    pub fn new(backend: GraphBackend) -> Self {
+        Self { prompt: LazyPrompt::new("prompt_function_call_validator"), backend }
     }
 
     pub async fn valid_func_calls_in_file(&mut self, file_path_buf: &PathBuf, lang: &str, 
-        func_name: &str, func_call_line: &str) -> bool {
+        func_name: &str, line_num: usize, func_call_line: &str) -> bool {
+        if self.backend.uses_parser(lang) {
+            let parsed_file_opt = ParsedFile::from_file(file_path_buf, lang);
+            if parsed_file_opt.is_none() {
+                return false;
+            }
+            let parsed_file = parsed_file_opt.expect("Empty parsed_file_opt");
+            // line_num is 1-based, parser rows are 0-based
+            let row = line_num.saturating_sub(1) as u32;
+            return parsed_file.function_calls().iter().any(|func_call|
+                func_call.line_number() == &row && func_call.function_name() == func_name);
+        }
         let file_contents_res = std::fs::read_to_string(file_path_buf);
         if file_contents_res.is_err() {
             let e = file_contents_res.expect_err("Empty error in file_content_res");
@@ -554,8 +621,13 @@ impl FunctionCallValidator {
             file_path: file_path.to_owned(),
             language: lang.to_owned(),
         };
-        self.prompt.set_input(func_call_validator_input);
-        let func_call_validator_prompt_res = serde_json::to_string(&self.prompt);
+        let prompt_opt = self.prompt.get();
+        if prompt_opt.is_none() {
+            return false;
+        }
+        let prompt = prompt_opt.expect("Empty prompt_opt");
+        prompt.set_input(func_call_validator_input);
+        let func_call_validator_prompt_res = serde_json::to_string(prompt);
         if func_call_validator_prompt_res.is_err() {
             log::debug!(
                 "[FunctionCallValidator/valid_func_calls] Unable to deserialize prompt struct: {:?}",
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::graph::utils::numbered_content;
 
-use super::{function_call::FunctionCall, gitops::HunkDiffLines, utils::{call_llm_api, read_file, strip_json_prefix}};
+use super::{code_parser::{GraphBackend, ParsedFile}, function_call::FunctionCall, gitops::HunkDiffLines, utils::{call_llm_api, detect_language, read_file}};
 
 #[derive(Debug, Serialize, Default, Deserialize, Clone)]
 pub struct FuncDefInfo {
@@ -50,152 +50,6 @@ impl HunkFuncDef {
     }
 }
 
-// Struct to represent function definition
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
-struct FunctionDefinition {
-    line_number: i32,
-}
-
-// Struct to represent the output schema
-#[derive(Serialize, Deserialize, Debug)]
-pub struct FunctionDefOutput {
-    function_definition: Option<FunctionDefinition>,
-    notes: Option<String>,
-}
-
-impl FunctionDefOutput {
-    pub fn get_function_line_number(&self) -> Option<usize> {
-        if let Some(func_def) = &self.function_definition {
-            return Some(func_def.line_number as usize)
-        }
-        return None;
-    }
-}
-
-// Struct to represent the input schema
-#[derive(Serialize, Deserialize, Debug)]
-struct InputSchema {
-    code_chunk: String,
-    language: String,
-    function_name: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct InputSchemaDescription {
-    code_chunk: String,
-    language: String,
-    function_name: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct OutputSchemaDescription {
-    function_definition: FunctionDefinitionDescription,
-    notes: String,
-}
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
-struct FunctionDefinitionDescription {
-    line_number: String,
-}
-
-// Struct for instructions that hold input/output schemas
-#[derive(Serialize, Deserialize, Debug)]
-struct Instructions {
-    input_schema: InputSchemaDescription,
-    output_schema: OutputSchemaDescription,
-    task_description: String,
-}
-// Struct for the entire JSON prompt
-#[derive(Serialize, Deserialize, Debug)]
-struct FunctionDefPrompt {
-    instructions: Instructions,
-    sample_input: InputSchema,
-    expected_output: FunctionDefOutput,
-    input: Option<InputSchema>,
-}
-
-impl FunctionDefPrompt {
-    fn set_input(&mut self, input: InputSchema) {
-        self.input = Some(input);
-    }
-}
-
-pub struct FunctionDefIdentifier {
-    prompt: FunctionDefPrompt
-}
-
-impl FunctionDefIdentifier {
-    pub fn new() -> Option<Self> {
-        let system_prompt_opt = read_file("/app/prompts/prompt_function_def");
-        if system_prompt_opt.is_none() {
-            log::error!("[function_calls_in_chunk] Unable to read prompt_function_def");
-            return None;
-        }
-        let system_prompt_lines = system_prompt_opt.expect("Empty system_prompt");
-        let prompt_json_res = serde_json::from_str(&system_prompt_lines);
-        if prompt_json_res.is_err() {
-            log::error!("[FunctionCallIdentifier/new] Unable to deserialize prompt_json: {:?}",
-                prompt_json_res.expect("Empty prompt_json_res"));
-            return None;
-        }
-        let prompt_json: FunctionDefPrompt = prompt_json_res.expect("Empty error in prompt_json_res");
-        return Some(Self { prompt: prompt_json});
-    }
-
-    pub async fn function_defs_in_file(&mut self, filepath: &PathBuf, lang: &str, function_name: &str) -> Option<FunctionDefOutput> {
-        // concatenate functioncallsoutput for all chunks
-        let file_contents_res = std::fs::read_to_string(filepath.clone());
-        if file_contents_res.is_err() {
-            log::error!(
-                "[FunctionCallIdentifier/functions_in_file] Unable to read file: {:?}, error: {:?}",
-                &filepath, file_contents_res.expect_err("Empty error in file_contents_res")
-            );
-            return None;
-        }
-        let file_contents = file_contents_res.expect("Uncaught error in file_contents_res");
-        let numbered_content = numbered_content(file_contents);
-        let chunks = numbered_content.chunks(50);
-        for chunk in chunks {
-            let chunk_str = chunk.join("\n");
-            if let Some(func_defs) = self.function_defs_in_chunk(&chunk_str, filepath, lang, function_name).await {
-                return Some(func_defs);
-            }
-        }
-        return None;
-    }
-
-    async fn function_defs_in_chunk(&mut self, chunk: &str, filepath: &PathBuf, lang: &str, function_name: &str) -> Option<FunctionDefOutput> {
-        let input = InputSchema{ code_chunk: chunk.to_string(), language: lang.to_string(),
-            function_name: function_name.to_string() };
-        self.prompt.input = Some(input);
-        let prompt_str_res = serde_json::to_string(&self.prompt);
-        if prompt_str_res.is_err() {
-            log::error!(
-                "[FunctionCallIdentifier/functions_in_chunk] Unable to serialize prompt: {:?}",
-                prompt_str_res.expect_err("Empty error in prompt_str_res"));
-                return None;
-        }
-        let prompt_str = prompt_str_res.expect("Uncaught error in prompt_str_res");
-        let final_prompt = format!("{}\nOutput - ", &prompt_str);
-        let prompt_response_opt =  call_llm_api(final_prompt).await;
-        if prompt_response_opt.is_none() {
-            log::error!("[FunctionCallIdentifier/functions_in_chunk] Unable to call llm for chunk: {:?}", chunk);
-            return None;
-        }
-        let mut prompt_response = prompt_response_opt.expect("Empty prompt_response_opt");
-        if let Some(stripped_json) = strip_json_prefix(&prompt_response) {
-            prompt_response = stripped_json.to_string();
-        }
-        let deserialized_response = serde_json::from_str(&prompt_response);
-        if deserialized_response.is_err() {
-            let e = deserialized_response.expect_err("Empty error in deserialized_response");
-            log::error!("[FunctionCallIdentifier/functions_in_chunk] Error in deserializing response: {:?}", e);
-            return None;
-        }
-        let func_calls: FunctionDefOutput = deserialized_response.expect("Empty error in deserialized_response");
-        return Some(func_calls);
-    }
-}
-
 #[derive(Debug, Serialize, Default, Deserialize, Clone)]
 pub struct FunctionFileMap {
     pub(crate) file_name: String,
@@ -300,6 +154,10 @@ pub struct LlmFuncDef {
 }
 
 impl LlmFuncDef {
+    pub fn new(name: String, line_start: usize, parent: String) -> Self {
+        Self { name, line_start, parent }
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
@@ -400,7 +258,15 @@ pub async fn generate_function_map(file_paths: &Vec<PathBuf>) -> Option<AllFileF
     return Some(all_file_functions);
 }
 
-pub async fn get_function_def_for_func_call(filepath: &PathBuf, func_call_line_num: usize) -> Option<LlmFuncDef> {
+pub async fn get_function_def_for_func_call(filepath: &PathBuf, func_call_line_num: usize, backend: &GraphBackend) -> Option<LlmFuncDef> {
+    if let Some(lang) = detect_language(&filepath.to_string_lossy()) {
+        if backend.uses_parser(&lang) {
+            let parsed_file = ParsedFile::from_file(filepath, &lang)?;
+            // func_call_line_num comes from rg and is 1-based
+            let func_def = parsed_file.enclosing_function(func_call_line_num.saturating_sub(1))?;
+            return Some(LlmFuncDef::new(func_def.name, func_def.line_start, func_def.parent));
+        }
+    }
     let system_prompt_opt = read_file("/app/prompts/prompt_function_lines");
     if system_prompt_opt.is_none() {
         log::error!("[get_function_def_for_func_call] Unable to read prompt_function_lines");
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
 use serde::{Deserialize, Serialize};
-use super::{function_line_range::FunctionDefIdentifier, utils::{call_llm_api, numbered_content, read_file, strip_json_prefix}};
+use super::{code_parser::{GraphBackend, ParsedFile}, utils::{call_llm_api, numbered_content, strip_json_prefix, LazyPrompt}};
 
 // Struct to represent the output schema
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -13,6 +13,10 @@ pub struct FunctionNameOutput {
 }
 
 impl FunctionNameOutput {
+    pub fn new(name: String, entity_type: String) -> Self {
+        Self { name, entity_type, status: "valid".to_string(), notes: None }
+    }
+
     pub fn get_name(&self) -> &String {
         &self.name
     }
@@ -101,41 +105,35 @@ impl ValidationPrompt {
 }
 
 pub struct FunctionNameIdentifier {
-    prompt: FunctionNamePrompt,
-    validation_prompt: ValidationPrompt,
-    cached_output: HashMap<String, FunctionNameOutput>
+    prompt: LazyPrompt<FunctionNamePrompt>,
+    validation_prompt: LazyPrompt<ValidationPrompt>,
+    cached_output: HashMap<String, FunctionNameOutput>,
+    backend: GraphBackend
 }
 
 impl FunctionNameIdentifier {
-    pub fn new() -> Option<Self> {
-        let system_prompt_opt = read_file("/app/prompts/prompt_function_name");
-        if system_prompt_opt.is_none() {
-            log::error!("[FunctionNameIdentifier/new] Unable to read prompt_function_name");
-            return None;
-        }
-        let system_prompt_lines = system_prompt_opt.expect("Empty system_prompt");
-        let prompt_json_res = serde_json::from_str(&system_prompt_lines);
-        if prompt_json_res.is_err() {
-            log::error!("[FunctionNameIdentifier/new] Unable to deserialize prompt_json: {:?}",
-                prompt_json_res.expect("Empty prompt_json_res"));
-            return None;
+    pub fn new(backend: GraphBackend) -> Self {
+        Self {
+            prompt: LazyPrompt::new("prompt_function_name"),
+            validation_prompt: LazyPrompt::new("prompt_valid_function_def"),
+            cached_output: HashMap::new(),
+            backend
         }
-        let prompt_json: FunctionNamePrompt = prompt_json_res.expect("Empty error in prompt_json_res");
+    }
 
-        let system_prompt_validation_opt = read_file("/app/prompts/prompt_valid_function_def");
-        if system_prompt_validation_opt.is_none() {
-            log::error!("[FunctionNameIdentifier/new] Unable to read prompt_valid_function_def");
-            return None;
+    // line_number is 1-based, the line the hunk header function context was found at
+    pub async fn function_name_at_line(&mut self, filepath: &str, line_number: usize, code_line: &str, lang: &str) -> Option<FunctionNameOutput> {
+        if !self.backend.uses_parser(lang) {
+            return self.function_name_in_line(code_line, lang).await;
         }
-        let system_prompt_validation_lines = system_prompt_validation_opt.expect("Empty system_prompt");
-        let prompt_validation_json_res = serde_json::from_str(&system_prompt_validation_lines);
-        if prompt_validation_json_res.is_err() {
-            log::error!("[FunctionNameIdentifier/new] Unable to deserialize prompt_json: {:?}",
-                prompt_validation_json_res.expect("Empty prompt_json_res"));
+        let parsed_file = ParsedFile::from_file(Path::new(filepath), lang)?;
+        let func_name_opt = parsed_file.function_name_at_line(line_number - 1);
+        if func_name_opt.is_none() {
+            log::debug!("[FunctionNameIdentifier/function_name_at_line] No function defined at line {} in {}", line_number, filepath);
             return None;
         }
-        let prompt_validation_json: ValidationPrompt = prompt_validation_json_res.expect("Empty error in prompt_json_res");
-        return Some(Self { prompt: prompt_json, validation_prompt: prompt_validation_json, cached_output: HashMap::new()});
+        let func_name = func_name_opt.expect("Empty func_name_opt");
+        return Some(FunctionNameOutput::new(func_name, "function".to_string()));
     }
 
     pub async fn function_name_in_line(&mut self, code_line: &str, lang: &str) -> Option<FunctionNameOutput> {
@@ -143,8 +141,9 @@ impl FunctionNameIdentifier {
             return Some(cached_func_name.to_owned());
         }
         let validation_input = ValidationPromptInput { code_line: code_line.to_string(), language: lang.to_string() };
-        self.validation_prompt.set_input(validation_input);
-        let validation_prompt_str_res = serde_json::to_string(&self.validation_prompt);
+        let validation_prompt = self.validation_prompt.get()?;
+        validation_prompt.set_input(validation_input);
+        let validation_prompt_str_res = serde_json::to_string(validation_prompt);
         if validation_prompt_str_res.is_err() {
             log::error!(
                 "[FunctionNameIdentifier/function_name_in_line] Unable to serialize prompt: {:?}",
@@ -176,8 +175,9 @@ impl FunctionNameIdentifier {
             return None;
         }
         let input = InputSchema { code_line: code_line.to_string(), language: lang.to_string() };
-        self.prompt.set_input(input);
-        let prompt_str_res = serde_json::to_string(&self.prompt);
+        let prompt = self.prompt.get()?;
+        prompt.set_input(input);
+        let prompt_str_res = serde_json::to_string(prompt);
         if prompt_str_res.is_err() {
             log::error!(
                 "[FunctionNameIdentifier/function_name_in_line] Unable to serialize prompt: {:?}",
@@ -272,44 +272,28 @@ impl DefintionPrompt {
 
 
 pub struct DefinitionIdentifier {
-    prompt: DefintionPrompt,
-    validation_prompt: ValidationPrompt
+    prompt: LazyPrompt<DefintionPrompt>,
+    validation_prompt: LazyPrompt<ValidationPrompt>,
+    backend: GraphBackend
 }
 
 impl DefinitionIdentifier {
-    pub fn new() -> Option<Self> {
-        let def_system_prompt_opt = read_file("/app/prompts/prompt_definition");
-        if def_system_prompt_opt.is_none() {
-            log::error!("[DefintionIdentifier/new] Unable to read prompt_definition");
-            return None;
-        }
-        let def_system_prompt_lines = def_system_prompt_opt.expect("Empty def_system_prompt");
-        let def_prompt_json_res = serde_json::from_str(&def_system_prompt_lines);
-        if def_prompt_json_res.is_err() {
-            log::error!("[DefintionIdentifier/new] Unable to deserialize def_prompt_json: {:?}",
-                def_prompt_json_res.expect("Empty def_prompt_json_res"));
-            return None;
+    pub fn new(backend: GraphBackend) -> Self {
+        Self {
+            prompt: LazyPrompt::new("prompt_definition"),
+            validation_prompt: LazyPrompt::new("prompt_valid_function_def"),
+            backend
         }
-        let def_prompt_json: DefintionPrompt = def_prompt_json_res.expect("Empty error in def_prompt_json_res");
-
-        let system_prompt_validation_opt = read_file("/app/prompts/prompt_valid_function_def");
-        if system_prompt_validation_opt.is_none() {
-            log::error!("[DefintionIdentifier/new] Unable to read prompt_valid_function_def");
-            return None;
-        }
-        let system_prompt_validation_lines = system_prompt_validation_opt.expect("Empty system_prompt");
-        let prompt_validation_json_res = serde_json::from_str(&system_prompt_validation_lines);
-        if prompt_validation_json_res.is_err() {
-            log::error!("[DefintionIdentifier/new] Unable to deserialize prompt_json: {:?}",
-                prompt_validation_json_res.expect("Empty prompt_json_res"));
-            return None;
-        }
-        let prompt_validation_json: ValidationPrompt = prompt_validation_json_res.expect("Empty error in prompt_json_res");
-        return Some(Self { prompt: def_prompt_json, validation_prompt: prompt_validation_json});
     }
 
     pub async fn identify_defs_in_file(&mut self, filepath: &str, lang: &str) -> Vec<FunctionDefinition>  {
         let mut func_def_vals = Vec::<FunctionDefinition>::new();
+        if self.backend.uses_parser(lang) {
+            if let Some(parsed_file) = ParsedFile::from_file(Path::new(filepath), lang) {
+                func_def_vals = parsed_file.definitions();
+            }
+            return func_def_vals;
+        }
         // batch up file
         let file_contents_res = std::fs::read_to_string(filepath.clone());
         if file_contents_res.is_err() {
@@ -321,6 +305,11 @@ impl DefinitionIdentifier {
             // return None;
         }
         let file_contents = file_contents_res.expect("Uncaught error in file_contents_res");
+        let prompt_opt = self.prompt.get();
+        if prompt_opt.is_none() {
+            return func_def_vals;
+        }
+        let prompt = prompt_opt.expect("Empty prompt_opt");
         let numbered_content = numbered_content(file_contents);
         let chunk_size = 20;
         let chunks = numbered_content.chunks(chunk_size);
@@ -331,8 +320,8 @@ impl DefinitionIdentifier {
                 code_chunk: chunk_str,
                 language: lang.to_string(),
             };
-            self.prompt.set_input(def_input);
-            let def_prompt_str_res = serde_json::to_string(&self.prompt);
+            prompt.set_input(def_input);
+            let def_prompt_str_res = serde_json::to_string(prompt);
             if def_prompt_str_res.is_err() {
                 log::error!(
                     "[DefintionIdentifier/identify_defs_in_file] Unable to serialize prompt: {:?}",
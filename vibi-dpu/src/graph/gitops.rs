@@ -1,6 +1,6 @@
-use std::{collections::HashMap, path::{Path, PathBuf}, process::Command, str::{self}};
+use std::{collections::HashMap, path::{Path, PathBuf}};
 
-use crate::utils::review::Review;
+use crate::utils::{git_repo::{FileStatus, GitRepo}, review::Review};
 
 use super::{function_name::FunctionDefinition, graph_info::DiffGraph, utils::detect_language};
 
@@ -161,11 +161,16 @@ pub fn get_hunks_all_files(review: &Review) -> Option<HunkDiffMap> {
     let prev_commit = review.base_head_commit();
     let curr_commit = review.pr_head_commit();
     let clone_dir = review.clone_dir();
-    let commit_range = format!("{}...{}", prev_commit, curr_commit);
+    let git_repo_opt = GitRepo::open(clone_dir);
+    if git_repo_opt.is_none() {
+        log::error!("[get_hunks_all_files] Unable to open repo at {}", clone_dir);
+        return None;
+    }
+    let git_repo = git_repo_opt.expect("Empty git_repo_opt");
     if let Some((mut added_files, mut deleted_files, modified_files))
-        = get_separated_files(clone_dir, &commit_range) 
+        = get_separated_files(&git_repo, prev_commit, curr_commit)
     {
-        let mut hunk_diff_map = get_modified_hunk_lines(&modified_files, clone_dir, &commit_range);
+        let mut hunk_diff_map = get_modified_hunk_lines(&modified_files, &git_repo, clone_dir, prev_commit, curr_commit);
         hunk_diff_map.add_added_files(&mut added_files);
         hunk_diff_map.add_deleted_files(&mut deleted_files);
         return Some(hunk_diff_map);
@@ -173,179 +178,75 @@ pub fn get_hunks_all_files(review: &Review) -> Option<HunkDiffMap> {
     return None;
 }
 
-fn get_separated_files(clone_dir: &str, commit_range: &str) -> Option<(Vec<String>, Vec<String>, Vec<String>)> {
-    let output = Command::new("git")
-        .arg("diff")
-        .arg("--name-status")
-        .arg(commit_range)
-        .current_dir(clone_dir)
-        .output()
-        .expect("Failed to execute git diff");
-    if !output.status.success() {
-        eprintln!(
-            "Git diff name status command failed with error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+fn get_separated_files(git_repo: &GitRepo, prev_commit: &str, curr_commit: &str) -> Option<(Vec<String>, Vec<String>, Vec<String>)> {
+    let file_diffs_opt = git_repo.diff(prev_commit, curr_commit, false, false, &[]);
+    if file_diffs_opt.is_none() {
+        log::error!("[get_separated_files] Unable to diff {}...{}", prev_commit, curr_commit);
         return None;
     }
-    let stdout = String::from_utf8_lossy(&output.stdout);
     let mut added_files = Vec::new();
     let mut deleted_files = Vec::new();
     let mut modified_files = Vec::new();
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() == 2 {
-            let (status, file) = (parts[0], parts[1]);
-            if let Some(lang) = detect_language(file) {
-                log::debug!("[get_separated_files] lang = {:}, file = {:}", &lang, file);
-                match status {
-                    "A" => added_files.push(file.to_string()),
-                    "M" => modified_files.push(file.to_string()),
-                    "D" => deleted_files.push(file.to_string()),
-                    _ => log::error!("[get_separated_files] status not identified = {:?}", status),
-                }
+    for file_diff in file_diffs_opt.expect("Empty file_diffs_opt") {
+        if let Some(lang) = detect_language(&file_diff.path) {
+            log::debug!("[get_separated_files] lang = {:}, file = {:}", &lang, &file_diff.path);
+            match file_diff.status {
+                FileStatus::Added => added_files.push(file_diff.path),
+                FileStatus::Modified => modified_files.push(file_diff.path),
+                FileStatus::Deleted => deleted_files.push(file_diff.path),
+                FileStatus::Renamed | FileStatus::Other => log::error!("[get_separated_files] status not identified for {}", &file_diff.path),
             }
         }
     }
     return Some((added_files, deleted_files, modified_files));
 }
 
-fn get_modified_hunk_lines(modified_files: &Vec<String>, clone_dir: &str, commit_range: &str) -> HunkDiffMap {
+fn get_modified_hunk_lines(modified_files: &Vec<String>, git_repo: &GitRepo, clone_dir: &str,
+        prev_commit: &str, curr_commit: &str) -> HunkDiffMap {
     let mut file_hunk_map = HunkDiffMap {
         file_line_map: HashMap::new(),
         added_files_map: HashMap::new(),
         deleted_files_map: HashMap::new()};
-    for filepath in modified_files {
-        log::debug!("[get_changed_hunk_lines] | clone_dir = {:?}, filepath = {:?}", clone_dir, filepath);
-        let output_res = Command::new("git")
-            .arg("diff")
-            .arg("--unified=0")
-            .arg("--ignore-space-change")
-            .arg(commit_range)
-            .arg(&filepath)
-            .current_dir(clone_dir)
-            .output();
-
-        if output_res.is_err() {
-            let commanderr = output_res.expect_err("No error in output_res");
-            log::error!("[get_changed_hunk_lines] git diff command failed to start : {:?}", commanderr);
-            continue;
-        }
-
-        let result = output_res.expect("Uncaught error in output_res");
-        let diff = result.stdout;
-        let diffstr_res = std::str::from_utf8(&diff);
-
-        if diffstr_res.is_err() {
-            let e = diffstr_res.expect_err("No error in diffstr_res");
-            log::error!("[get_changed_hunk_lines] Unable to deserialize diff: {:?}", e);
-            continue;
-        }
-
-        let diffstr = diffstr_res.expect("Uncaught error in diffstr_res");
-        log::debug!("[get_changed_hunk_lines] diffstr = {}", &diffstr);
-
-        let mut current_add_start = 0;
-        let mut current_del_start = 0;
-        let mut current_add_end = 0;
-        let mut current_del_end = 0;
-        let mut in_add_hunk = false;
-        let mut in_del_hunk = false;
+    if modified_files.is_empty() {
+        return file_hunk_map;
+    }
+    let paths: Vec<&str> = modified_files.iter().map(|filepath| filepath.as_str()).collect();
+    let file_diffs_opt = git_repo.diff(prev_commit, curr_commit, true, false, &paths);
+    if file_diffs_opt.is_none() {
+        log::error!("[get_modified_hunk_lines] Unable to diff files: {:?}", &paths);
+        return file_hunk_map;
+    }
+    for file_diff in file_diffs_opt.expect("Empty file_diffs_opt") {
+        log::debug!("[get_modified_hunk_lines] | clone_dir = {:?}, filepath = {:?}", clone_dir, &file_diff.path);
         let mut file_hunks = FileHunks {
             deleted_hunks: Vec::new(),
             added_hunks: Vec::new(),
         };
-
-        // Variable to store the function line
-        let mut function_line: Option<String> = None;
-
-        for line in diffstr.lines() {
-            if line.starts_with("@@") {
-                // Save previous hunks if any
-                if in_add_hunk {
-                    file_hunks.added_hunks.push(HunkDiffLines {
-                        start_line: current_add_start,
-                        end_line: current_add_end,
-                        function_line: function_line.clone(), // Use the function line stored
-                        line_number: None,
-                        function_name: None
-                    });
-                }
-                if in_del_hunk {
-                    file_hunks.deleted_hunks.push(HunkDiffLines {
-                        start_line: current_del_start,
-                        end_line: current_del_end,
-                        function_line: function_line.clone(), // Use the function line stored
-                        line_number: None,
-                        function_name: None
-                    });
-                }
-
-                // Reset states for next hunk
-                in_add_hunk = false;
-                in_del_hunk = false;
-
-                // Extract the function name or any string after the last @@
-                if let Some(pos) = line.rfind("@@") {
-                    function_line = None;
-                    if pos+2 < line.len() {
-                        let fline = line[(pos+2)..].trim().to_string();
-                        if fline.len() > 2 {
-                            function_line = Some(fline);
-                        }
-                    }
-                } else {
-                    function_line = None; // Reset if no valid function line found
-                }
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                // Determine the start and end lines for the hunks
-                let del_hunk = parts.get(1);
-                let add_hunk = parts.get(2);
-
-                if let Some(del_hunk) = del_hunk {
-                    if del_hunk.starts_with('-') {
-                        if let Some((start, len)) = parse_hunk_range(del_hunk) {
-                            current_del_start = start;
-                            current_del_end = start + len - 1;
-                            in_del_hunk = true;
-                        }
-                    }
-                }
-
-                if let Some(add_hunk) = add_hunk {
-                    if add_hunk.starts_with('+') {
-                        if let Some((start, len)) = parse_hunk_range(add_hunk) {
-                            current_add_start = start;
-                            current_add_end = start + len - 1;
-                            in_add_hunk = true;
-                        }
-                    }
-                }
+        for hunk in &file_diff.hunks {
+            // Short trailers like `{` don't name a function
+            let function_line = hunk.function_context.clone()
+                .filter(|fline| fline.len() > 2);
+            if let Some((start_line, end_line)) = hunk.added_range() {
+                file_hunks.added_hunks.push(HunkDiffLines {
+                    start_line,
+                    end_line,
+                    function_line: function_line.clone(),
+                    line_number: None,
+                    function_name: None
+                });
+            }
+            if let Some((start_line, end_line)) = hunk.deleted_range() {
+                file_hunks.deleted_hunks.push(HunkDiffLines {
+                    start_line,
+                    end_line,
+                    function_line,
+                    line_number: None,
+                    function_name: None
+                });
             }
         }
-
-        // Push the last hunks if still in any hunk
-        if in_add_hunk {
-            file_hunks.added_hunks.push(HunkDiffLines {
-                start_line: current_add_start,
-                end_line: current_add_end,
-                function_line: function_line.clone(), // Use the function line stored
-                line_number: None,
-                function_name: None
-            });
-        }
-        if in_del_hunk {
-            file_hunks.deleted_hunks.push(HunkDiffLines {
-                start_line: current_del_start,
-                end_line: current_del_end,
-                function_line: function_line.clone(), // Use the function line stored
-                line_number: None,
-                function_name: None
-            });
-        }
-
         let abs_filepath = Path::new(clone_dir);
-        let abs_file_pathbuf = abs_filepath.join(Path::new(filepath));
+        let abs_file_pathbuf = abs_filepath.join(Path::new(&file_diff.path));
         file_hunk_map.file_line_map.insert(
             abs_file_pathbuf.to_str().expect("Unable to deserialize pathbuf").to_string(),
             file_hunks,
@@ -354,22 +255,3 @@ fn get_modified_hunk_lines(modified_files: &Vec<String>, clone_dir: &str, commit
 
     return file_hunk_map;
 }
-
-
-fn parse_hunk_range(hunk: &str) -> Option<(usize, usize)> {
-    let hunk = hunk.trim_start_matches(&['-', '+'][..]);
-    let parts: Vec<&str> = hunk.split(',').collect();
-    if parts.len() == 1 {
-        if let Ok(start) = parts[0].parse::<usize>() {
-            return Some((start, 1));
-        }
-    } else if parts.len() == 2 {
-        if let (Ok(start), Ok(len)) = (parts[0].parse::<usize>(), parts[1].parse::<usize>()) {
-            if len == 0 {
-                return None;
-            }
-            return Some((start, len));
-        }
-    }
-    None
-}
@@ -1,61 +1,27 @@
 use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}};
 use crate::{graph::{function_call::associate_function_calls, function_name::FunctionDefinition}, utils::{gitops::git_checkout_commit, review::Review}};
 
-use super::{elements::MermaidGraphElements, file_imports::{ImportDefIdentifier, ImportIdentifier, ImportLinesIdentifier}, function_call::{function_calls_search, FunctionCall, FunctionCallIdentifier, FunctionCallValidator}, function_line_range::{get_function_def_for_func_call, FunctionDefIdentifier}, graph_info::DiffGraph, utils::{absolute_to_relative_path, detect_language}};
+use super::{code_parser::GraphBackend, elements::MermaidGraphElements, file_imports::ImportIdentifier, function_call::{function_calls_search, FunctionCall, FunctionCallIdentifier, FunctionCallValidator}, function_line_range::get_function_def_for_func_call, graph_info::DiffGraph, utils::{absolute_to_relative_path, detect_language}};
 
-pub async fn graph_edges(base_filepaths: &Vec<PathBuf>, review: &Review, diff_graph: &DiffGraph, graph_elems: &mut MermaidGraphElements) {
-    let func_call_identifier_opt = FunctionCallIdentifier::new();
-    if func_call_identifier_opt.is_none() {
-        log::error!("[graph_edges] Unable to create new FunctionCallIdentifier");
-        return;
-    }
-    let mut func_call_identifier = func_call_identifier_opt.expect("Empty func_call_identifier_opt");
-    let import_identifier_opt = ImportIdentifier::new();
-    if import_identifier_opt.is_none() {
-        log::debug!("[graph_edges] Unable to create import identifier");
-        return;
-    }
-    let mut import_identifier = import_identifier_opt.expect("Empty import_identifier_opt");
-    let func_def_identifier_opt = FunctionDefIdentifier::new();
-    if func_def_identifier_opt.is_none() {
-        log::debug!("[graph_edges] Unable to create func def identifier");
-        return;
-    }
-    let mut funcdef_identifier = func_def_identifier_opt.expect("Empty func_def_identifier_opt");
+pub async fn graph_edges(base_filepaths: &Vec<PathBuf>, review: &Review, diff_graph: &DiffGraph, graph_elems: &mut MermaidGraphElements, backend: &GraphBackend) {
+    let mut func_call_identifier = FunctionCallIdentifier::new(*backend);
+    let mut import_identifier = ImportIdentifier::new(*backend);
     log::debug!("[graph_edges] review obj = {:#?}", review);
-    outgoing_edges(base_filepaths, diff_graph, graph_elems, review, &mut func_call_identifier, &mut import_identifier, &mut funcdef_identifier).await;
-    incoming_edges(review, diff_graph, graph_elems).await;
+    outgoing_edges(base_filepaths, diff_graph, graph_elems, review, &mut func_call_identifier, &mut import_identifier).await;
+    incoming_edges(review, diff_graph, graph_elems, backend).await;
 }
 
 async fn incoming_edges(review: &Review, diff_graph: &DiffGraph,
-    graph_elems: &mut MermaidGraphElements)
+    graph_elems: &mut MermaidGraphElements, backend: &GraphBackend)
 {
-    let import_lines_identifier_opt = ImportLinesIdentifier::new();
-    if import_lines_identifier_opt.is_none() {
-        log::error!("[incoming_edges] Unable to initiate ImportLinesIdentifier");
-        return;
-    }
-    let mut import_lines_identifier = import_lines_identifier_opt.expect("Empty import_lines_identifier_opt");
-    let import_def_identifier_opt = ImportDefIdentifier::new();
-    if import_def_identifier_opt.is_none() {
-        log::error!("[incoming_edges] Unable to initiate ImportDefIdentifier");
-        return;
-    }
-    let mut import_def_identifier = import_def_identifier_opt.expect("Empty import_def_identifier_opt");
-    let func_call_validator_opt = FunctionCallValidator::new();
-    if func_call_validator_opt.is_none() {
-        log::error!("[incoming_edges] Unable to initiate FunctionCallValidator");
-        return;
-    }
-    let mut func_call_validator = func_call_validator_opt.expect("Empty func_call_validator_opt");
+    let mut func_call_validator = FunctionCallValidator::new(*backend);
     git_checkout_commit(review, review.pr_head_commit());
     process_func_defs(
         review,
         diff_graph,
         graph_elems,
-        &mut import_lines_identifier,
-        &mut import_def_identifier,
         &mut func_call_validator,
+        backend,
         "green"
     ).await;
     git_checkout_commit(review, review.base_head_commit());
@@ -63,9 +29,8 @@ async fn incoming_edges(review: &Review, diff_graph: &DiffGraph,
         review,
         diff_graph,
         graph_elems,
-        &mut import_lines_identifier,
-        &mut import_def_identifier,
         &mut func_call_validator,
+        backend,
         "red"
     ).await;
     log::debug!("[incoming_edges] Incoming edges processed");
@@ -74,15 +39,13 @@ async fn incoming_edges(review: &Review, diff_graph: &DiffGraph,
 async fn outgoing_edges(base_filepaths: &Vec<PathBuf>, diff_graph: &DiffGraph,
     graph_elems: &mut MermaidGraphElements, review: &Review,
     func_call_identifier: &mut FunctionCallIdentifier,
-    import_identifier: &mut ImportIdentifier,
-    funcdef_identifier: &mut FunctionDefIdentifier) 
+    import_identifier: &mut ImportIdentifier)
 {
     log::debug!("[outgoing_edges] review obj = {:#?}", review);
     git_checkout_commit(review, review.pr_head_commit());
     process_func_calls(
         import_identifier,
         func_call_identifier,
-        funcdef_identifier,
         review,
         diff_graph,
         base_filepaths,
@@ -91,7 +54,6 @@ async fn outgoing_edges(base_filepaths: &Vec<PathBuf>, diff_graph: &DiffGraph,
     git_checkout_commit(review, review.base_head_commit());
     process_func_calls(import_identifier,
         func_call_identifier,
-        funcdef_identifier,
         review,
         diff_graph,
         base_filepaths,
@@ -101,7 +63,6 @@ async fn outgoing_edges(base_filepaths: &Vec<PathBuf>, diff_graph: &DiffGraph,
 }
 
 async fn process_func_calls(import_identifier: &mut ImportIdentifier, func_call_identifier: &mut FunctionCallIdentifier,
-    funcdef_identifier: &mut FunctionDefIdentifier,
     review: &Review, diff_graph: &DiffGraph, base_filepaths: &Vec<PathBuf>,
     graph_elems: &mut MermaidGraphElements, edge_color: &str)
 {
@@ -145,7 +106,7 @@ async fn process_func_calls(import_identifier: &mut ImportIdentifier, func_call_
             for (func_def, func_calls_vec) in func_def_call_map {
                 search_func_call(&func_calls_vec, source_filepath, import_identifier, &lang, diff_graph, 
                     review, edge_color, graph_elems, &source_file_name, base_filepaths,
-                    funcdef_identifier, &func_def.structure_name, &func_def.line_number).await;
+                    &func_def.structure_name, &func_def.line_number).await;
             }
         }
     }
@@ -178,7 +139,7 @@ async fn process_func_calls(import_identifier: &mut ImportIdentifier, func_call_
                     if let Some(src_func_line_number) = hunk_lines.line_number() {
                         search_func_call(func_call_output.function_calls(), source_filepath, import_identifier, 
                             &lang, diff_graph, review, edge_color, graph_elems, &source_file_name,
-                            base_filepaths, funcdef_identifier, src_func_name, src_func_line_number).await;
+                            base_filepaths, src_func_name, src_func_line_number).await;
                     }
                 }
             }
@@ -189,7 +150,7 @@ async fn process_func_calls(import_identifier: &mut ImportIdentifier, func_call_
 async fn search_func_call(func_calls: &Vec<FunctionCall>, 
         source_filepath: &str, import_identifier: &mut ImportIdentifier, lang: &str, diff_graph: &DiffGraph,
         review: &Review, edge_color: &str, graph_elems: &mut MermaidGraphElements, source_file_name: &str,
-        base_filepaths: &Vec<PathBuf>, funcdef_identifier: &mut FunctionDefIdentifier, src_func_name: &str,
+        base_filepaths: &Vec<PathBuf>, src_func_name: &str,
         src_func_line_number: &usize
 ) {
     log::debug!("[search_func_call] funcalls = {:#?}, filename - {}", &func_calls ,source_filepath);
@@ -252,8 +213,7 @@ async fn search_func_call(func_calls: &Vec<FunctionCall>,
 
 async fn process_func_defs(review: &Review,
     diff_graph: &DiffGraph, graph_elems: &mut MermaidGraphElements,
-    import_lines_identifier: &mut ImportLinesIdentifier, import_def_identifier: &mut ImportDefIdentifier,
-    func_call_validator: &mut FunctionCallValidator, edge_color: &str)
+    func_call_validator: &mut FunctionCallValidator, backend: &GraphBackend, edge_color: &str)
 {
     let files_def_map;
     if edge_color == "green" {
@@ -272,12 +232,11 @@ async fn process_func_defs(review: &Review,
             let dest_func_name = &func_def.structure_name;
             let dest_funcdef_line = &func_def.line_number;
             if let Some(possible_filepaths) = 
-                    function_calls_search(review, dest_func_name, &dest_lang)
+                    function_calls_search(review, dest_func_name, &dest_lang, backend)
             {
                 search_func_defs(&possible_filepaths, dest_filename, &dest_lang,
                     graph_elems, review,
-                    import_lines_identifier, import_def_identifier,
-                    func_call_validator, dest_func_name, dest_funcdef_line
+                    func_call_validator, dest_func_name, dest_funcdef_line, backend
                 ).await;
             }
         }
@@ -306,7 +265,7 @@ async fn process_func_defs(review: &Review,
                 }
                 if let Some(dest_funcdef_line) = dest_func.line_number() {
                     if let Some(possible_filepaths) = 
-                    function_calls_search(review, dest_func_name, &dest_lang)
+                    function_calls_search(review, dest_func_name, &dest_lang, backend)
                 {
                     if possible_filepaths.is_empty() {
                         log::debug!("[process_func_defs] No files detected having function call");
@@ -315,8 +274,7 @@ async fn process_func_defs(review: &Review,
                     // TODO FIXME - get one file name only once
                     search_func_defs(&possible_filepaths, dest_filename, &dest_lang,
                         graph_elems, review,
-                        import_lines_identifier, import_def_identifier,
-                        func_call_validator, dest_func_name, dest_funcdef_line
+                        func_call_validator, dest_func_name, dest_funcdef_line, backend
                     ).await;
                 }
             }
@@ -327,8 +285,8 @@ async fn process_func_defs(review: &Review,
 
 async fn search_func_defs(possible_filepaths: &HashMap<String, Vec<(usize, String)>>, dest_filename: &str,dest_lang: &str,
     graph_elems: &mut MermaidGraphElements, review: &Review,
-    import_lines_identifier: &mut ImportLinesIdentifier, import_def_identifier: &mut ImportDefIdentifier,
-    func_call_validator: &mut FunctionCallValidator, dest_func_name: &str, dest_funcdef_line: &usize
+    func_call_validator: &mut FunctionCallValidator, dest_func_name: &str, dest_funcdef_line: &usize,
+    backend: &GraphBackend
 ) {
     for (possible_filepath, lines_info) in possible_filepaths {
         if possible_filepath == dest_filename {
@@ -351,10 +309,10 @@ async fn search_func_defs(possible_filepaths: &HashMap<String, Vec<(usize, Strin
             // if let Some(import_hunks) = import_lines_identifier.import_lines_range_in_file(&possible_pathbuf, &lang).await {
                 // if let Some(import_def) = import_def_identifier.identify_import_def(&possible_pathbuf, &dest_func_name, &lang, &import_hunks).await {
                     // log::debug!("[search_func_defs] import_def = {:#?}, possible file names - {}, filename - {}", &import_def , &possible_filepath, dest_filename);
-                    if func_call_validator.valid_func_calls_in_file(&possible_pathbuf, &lang, &dest_func_name, &line_content).await {
+                    if func_call_validator.valid_func_calls_in_file(&possible_pathbuf, &lang, &dest_func_name, *line_num, &line_content).await {
                         if let Some(source_filename) = absolute_to_relative_path(&possible_filepath, review) {
                             if let Some(src_func_def) = get_function_def_for_func_call(
-                                &possible_pathbuf, line_num.to_owned()
+                                &possible_pathbuf, line_num.to_owned(), backend
                             ).await {
                                 // add edge
                                 log::debug!("[search_func_defs] src_func_def = {:#?}, filename = {}", &src_func_def, dest_filename);
@@ -437,4 +395,31 @@ async fn search_func_defs(possible_filepaths: &HashMap<String, Vec<(usize, Strin
             // }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::graph_info::generate_diff_graph;
+    use crate::utils::git_repo::fixture::FixtureRepo;
+
+    #[tokio::test]
+    async fn parser_backend_builds_edges_without_llm() {
+        let fixture = FixtureRepo::new("graph-edges");
+        let caller = "from helpers import helper\n\ndef run():\n    return helper()\n";
+        let base = fixture.commit(&[
+            ("helpers.py", "def helper():\n    return 1\n"),
+            ("main.py", caller),
+        ], "alice@example.com", 1_000);
+        let head = fixture.commit(&[("helpers.py", "def helper():\n    return 2\n")],
+            "bob@example.com", 2_000);
+        let review = Review::new(base.to_string(), head.to_string(), "7".to_string(), "repo".to_string(),
+            "owner".to_string(), "github".to_string(), "github/owner/repo/7".to_string(),
+            fixture.path().to_string(), String::new(), String::new(), None);
+        let diff_graph = generate_diff_graph(&review, &GraphBackend::Parser).await
+            .expect("No diff graph");
+        let mut graph_elems = MermaidGraphElements::new();
+        graph_edges(&Vec::new(), &review, &diff_graph, &mut graph_elems, &GraphBackend::Parser).await;
+        let table = graph_elems.render_table();
+        assert!(table.contains("| `main.py::run` | `helpers.py::helper` | 4 |"), "{}", table);
+    }
+}
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, path::Path};
 
 use crate::{graph::gitops::get_hunks_all_files, utils::{gitops::{git_checkout_commit, StatItem}, review::Review}};
-use super::{function_call::{FunctionCallChunk, FunctionCallsOutput}, function_line_range::HunkFuncDef, function_name::{DefinitionIdentifier, FunctionDefinition, FunctionNameIdentifier}, gitops::HunkDiffMap, utils::{detect_language, read_file}};
+use super::{code_parser::GraphBackend, function_call::{FunctionCallChunk, FunctionCallsOutput}, function_line_range::HunkFuncDef, function_name::{DefinitionIdentifier, FunctionDefinition, FunctionNameIdentifier}, gitops::HunkDiffMap, utils::{detect_language, read_file}};
 
 #[derive(Debug, Default, Clone)]
 pub struct DiffFuncDefs {
@@ -65,30 +65,20 @@ impl DiffGraph {
     }
 }
 
-pub async fn generate_diff_graph(review: &Review) -> Option<DiffGraph> {
+pub async fn generate_diff_graph(review: &Review, backend: &GraphBackend) -> Option<DiffGraph> {
     if let Some(mut hunk_diff_map) = get_hunks_all_files(review) {
         // get func defs for base commit for files in diff
         log::debug!("[generate_diff_graph] hunk diff map =======~~~~~~~~ {:#?}", &hunk_diff_map);
-        let diff_graph_opt = process_hunk_diff(&mut hunk_diff_map, review).await;
+        let diff_graph_opt = process_hunk_diff(&mut hunk_diff_map, review, backend).await;
         return diff_graph_opt;
     }
     return None;
 }
 
-async fn process_hunk_diff(hunk_diff_map: &mut HunkDiffMap, review: &Review) -> Option<DiffGraph> {
+async fn process_hunk_diff(hunk_diff_map: &mut HunkDiffMap, review: &Review, backend: &GraphBackend) -> Option<DiffGraph> {
     // full graph func def and import info for diff selected files is required.
-    let func_name_identifier_opt = FunctionNameIdentifier::new();
-    if func_name_identifier_opt.is_none() {
-        log::error!("[process_hunk_diff] Unable to initialize function name identifier");
-        return None;
-    }
-    let mut func_name_identifier = func_name_identifier_opt.expect("Empty func_name_identifier_opt");
-    let def_identifier_opt = DefinitionIdentifier::new();
-    if def_identifier_opt.is_none() {
-        log::error!("[process_hunk_diff] Unable to initialize definition identifier");
-        return None;
-    }
-    let mut def_identifier = def_identifier_opt.expect("Empty def_identifier_opt");
+    let mut func_name_identifier = FunctionNameIdentifier::new(*backend);
+    let mut def_identifier = DefinitionIdentifier::new(*backend);
     git_checkout_commit(review, review.pr_head_commit());
     set_func_def_info(hunk_diff_map, &mut func_name_identifier, true).await;
     let added_files_defs = set_func_def_whole_file(review, hunk_diff_map.added_files(), &mut def_identifier).await;
@@ -140,7 +130,7 @@ async fn set_func_def_info(hunk_diff_map: &mut HunkDiffMap, func_name_identifier
                     {
                         file_hunk.set_line_number(line_number);
                         if let Some(lang) = detect_language(filepath) {
-                            if let Some(func_name) = func_name_identifier.function_name_in_line(&func_line_raw, &lang).await {
+                            if let Some(func_name) = func_name_identifier.function_name_at_line(filepath, line_number, &func_line_raw, &lang).await {
                                 file_hunk.set_function_name(func_name.get_name().to_string());
                             } else { log:: debug!("[set_func_def_info] No func name for {}", &func_line_raw); }
                         } else { log::debug!("[set_func_def_info] language not detected for: {}", filepath); }
@@ -1,16 +1,21 @@
 
-use crate::{graph::{elements::MermaidGraphElements, graph_edges::graph_edges, graph_info::generate_diff_graph}, utils::{gitops::{get_file_modification_status, git_checkout_commit, StatItem}, review::Review}};
+use crate::{graph::{elements::MermaidGraphElements, graph_edges::graph_edges, graph_info::generate_diff_graph}, utils::{gitops::{get_file_modification_status, StatItem}, review::Review, worktree::ReviewWorktree}};
 
-use super::utils::all_code_files;
+use super::{code_parser::GraphBackend, utils::all_code_files};
 
 
-pub async fn generate_mermaid_flowchart(diff_files: &Vec<StatItem>, review: &Review) -> Option<String> {
-    let flowchart_content_res = generate_flowchart_elements(diff_files, review).await;
-    if flowchart_content_res.is_none() {
+pub async fn generate_mermaid_flowchart(diff_files: &Vec<StatItem>, review: &Review, backend: &GraphBackend) -> Option<String> {
+    let graph_elems_opt = generate_graph_elements(diff_files, review, backend).await;
+    if graph_elems_opt.is_none() {
         log::error!("[generate_mermaid_flowchart] Unable to generate flowchart content, review: {}", review.id());
         return None;
     }
-    let flowchart_content = flowchart_content_res.expect("Empty flowchart_content_res");
+    let graph_elems = graph_elems_opt.expect("Empty graph_elems_opt");
+    return Some(mermaid_flowchart(&graph_elems, review));
+}
+
+pub fn mermaid_flowchart(graph_elems: &MermaidGraphElements, review: &Review) -> String {
+    let flowchart_content = graph_elems.render_elements(review);
     let flowchart_str = format!(
         "%%{{init: {{ \
             'theme': 'neutral', \
@@ -25,39 +30,50 @@ pub async fn generate_mermaid_flowchart(diff_files: &Vec<StatItem>, review: &Rev
         \tflowchart LR\n{}",
         &flowchart_content
     );
-    return Some(flowchart_str);
+    return flowchart_str;
+}
+
+pub async fn generate_graph_elements(diff_files: &Vec<StatItem>, review: &Review, backend: &GraphBackend) -> Option<MermaidGraphElements> {
+    // The graph switches between base and head, so it gets its own checkout
+    let worktree_opt = ReviewWorktree::create(review, review.base_head_commit()).await;
+    if worktree_opt.is_none() {
+        log::error!("[generate_graph_elements] Unable to create worktree for review: {}", review.id());
+        return None;
+    }
+    let worktree = worktree_opt.expect("Empty worktree_opt");
+    let graph_elems_opt = worktree_graph_elements(diff_files, worktree.review(), backend).await;
+    worktree.remove().await;
+    return graph_elems_opt;
 }
 
-async fn generate_flowchart_elements(diff_files: &Vec<StatItem>, review: &Review) -> Option<String> {
-    git_checkout_commit(review, review.base_head_commit());
-    log::debug!("[generate_flowchart_elements] before review obj = {:#?}", review);
+async fn worktree_graph_elements(diff_files: &Vec<StatItem>, review: &Review, backend: &GraphBackend) -> Option<MermaidGraphElements> {
+    log::debug!("[generate_graph_elements] before review obj = {:#?}", review);
     let base_filepaths_opt = all_code_files(review.clone_dir(), diff_files);
     if base_filepaths_opt.is_none() {
         log::error!(
-            "[generate_flowchart_elements] Unable to get file paths: {}", review.clone_dir());
+            "[generate_graph_elements] Unable to get file paths: {}", review.clone_dir());
         return None;
     }
     let base_filepaths = base_filepaths_opt.expect("Empty base_filepaths_opt");
-    let diff_graph_opt = generate_diff_graph(review).await;
-    log::debug!("[generate_flowchart_elements] diff_graph_opt = {:#?}", &diff_graph_opt);
+    let diff_graph_opt = generate_diff_graph(review, backend).await;
+    log::debug!("[generate_graph_elements] diff_graph_opt = {:#?}", &diff_graph_opt);
     if diff_graph_opt.is_none() {
         log::error!(
-            "[generate_flowchart_elements] Unable to generate diff graph for review: {}",
+            "[generate_graph_elements] Unable to generate diff graph for review: {}",
             review.id());
         return None;
     }
     let diff_graph = diff_graph_opt.expect("Empty diff_graph_opt");
     let mut graph_elems = MermaidGraphElements::new();
-    log::debug!("[generate_flowchart_elements] review obj = {:#?}", review);
+    log::debug!("[generate_graph_elements] review obj = {:#?}", review);
     graph_nodes(review, &mut graph_elems);
-    graph_edges(&base_filepaths, review, &diff_graph, &mut graph_elems).await;
-    let elems_str = graph_elems.render_elements(review);
-    return Some(elems_str);
+    graph_edges(&base_filepaths, review, &diff_graph, &mut graph_elems, backend).await;
+    return Some(graph_elems);
 }
 
 fn graph_nodes(review: &Review, graph_elems: &mut MermaidGraphElements) {
-    if let Some(mod_map) = get_file_modification_status(review.clone_dir(), 
-        &format!("{}...{}", review.base_head_commit(), review.pr_head_commit())) {
+    if let Some(mod_map) = get_file_modification_status(review.clone_dir(),
+        review.base_head_commit(), review.pr_head_commit()) {
             log::debug!("[graph_nodes] mod map = {:#?}", &mod_map);
             if !mod_map.is_empty() {
                 for (color_key, file_list) in mod_map {
@@ -7,4 +7,5 @@ pub mod file_imports;
 pub mod graph_info;
 pub mod graph_edges;
 pub mod function_call;
-pub mod function_name;
\ No newline at end of file
+pub mod function_name;
+pub mod code_parser;
\ No newline at end of file
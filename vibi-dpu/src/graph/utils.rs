@@ -1,32 +1,35 @@
 use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}};
 
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde::de::DeserializeOwned;
 use walkdir::WalkDir;
 use std::fs;
 use rand::Rng;
 use once_cell::sync::Lazy;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
-use crate::utils::{gitops::StatItem, reqwest_client::get_client, review::Review};
+use crate::{db::llm_cache::{get_cached_llm_response, llm_cache_enabled, llm_cache_key, save_llm_response_to_cache}, llm::client::{llm_client_from_env, LlmClient}, metrics::registry::record_llm_call, utils::{gitops::StatItem, review::Review}};
 
-#[derive(Debug, Serialize, Default, Deserialize, Clone)]
-struct LlmResponse {
-    model: String,
-    created_at: String,
-    response: String,
-    done: bool
-}
-
-static TOKEN: Lazy<String> = Lazy::new(|| {
-    fs::read_to_string("/app/prompts/hf_token").expect("Failed to read hf_token file")
-});
+static LLM_CLIENT: Lazy<Option<Box<dyn LlmClient>>> = Lazy::new(llm_client_from_env);
 
 pub async fn call_llm_api(prompt: String) -> Option<String> {
-    let client = get_client();
-    let url = "https://diff-grapher.openai.azure.com/openai/deployments/diff-grapher/chat/completions?api-version=2025-01-01-preview";
-    let token = &*TOKEN;
+    let client_opt = &*LLM_CLIENT;
+    if client_opt.is_none() {
+        log::error!("[call_llm_api] No LLM client configured");
+        return None;
+    }
+    let client = client_opt.as_ref().expect("Empty client_opt");
+    let cache_enabled = llm_cache_enabled();
+    let cache_key = llm_cache_key(client.model(), &prompt);
+    if cache_enabled {
+        if let Some(cached_response) = get_cached_llm_response(&cache_key) {
+            log::info!("[call_llm_api] LLM cache hit for {}", &cache_key);
+            record_llm_call("cached", None);
+            return Some(cached_response);
+        }
+        log::info!("[call_llm_api] LLM cache miss for {}", &cache_key);
+    }
+    log::debug!("[call_llm_api] Calling model {}", client.model());
 
     // Initial wait of 1 seconds before making the first API call
     let initial_timeoff = 1;
@@ -35,52 +38,15 @@ pub async fn call_llm_api(prompt: String) -> Option<String> {
     let mut wait_time = 5; // Initial backoff time in seconds
 
     for attempt in 0..5 {
-
-        let response_res = client
-            .post(url)
-            .header("api-key", format!("{}", token))
-            .header("Content-Type", "application/json")
-            .json(&json!({
-                "messages": [
-                    { "role": "user", "content": prompt }
-                ],
-                "max_tokens": 4000,
-            }))
-            .send()
-            .await;
-
-        if let Err(err) = response_res {
-            log::error!("[call_llm_api] Error in calling API: {:?}", err);
-            continue;
-        }
-
-        let response = response_res.expect("Uncaught error in response_res");
-
-        // Ensure we can read the response stream
-        let resp_text_res = response.text().await;
-        if let Err(err) = resp_text_res {
-            log::error!("[call_llm_api] Error reading response text: {:?}", err);
-            continue;
-        }
-
-        let resp_text = resp_text_res.expect("Uncaught error in resp_text_res");
-        // Parse the response text as JSON
-        let resp_json_res = serde_json::from_str(&resp_text);
-        if let Err(e) = resp_json_res {
-            log::error!("[call_llm_api] Unable to parse response json {:?}", e);
-            continue;
-        }
-        let resp_json: Value = resp_json_res.expect("Failed to parse JSON");
-        // Extract and concatenate all `message.content` values from `choices`
-        let final_response: String = resp_json["choices"]
-            .as_array()
-            .unwrap_or(&vec![]) // Handle case where `choices` might be missing
-            .iter()
-            .filter_map(|choice| choice["message"]["content"].as_str()) // Extract content
-            .collect::<Vec<&str>>() // Collect into a Vec of strings
-            .join("\n"); // Join with newlines
-        log::info!("[call_llm_api] Final aggregated response: {:#?}", final_response);
-        if !final_response.is_empty() {
+        let attempt_start = Instant::now();
+        let response_opt = client.complete(&prompt).await;
+        let outcome = if response_opt.is_some() { "success" } else { "failure" };
+        record_llm_call(outcome, Some(attempt_start.elapsed()));
+        if let Some(final_response) = response_opt {
+            log::info!("[call_llm_api] Final aggregated response: {:#?}", final_response);
+            if cache_enabled {
+                save_llm_response_to_cache(&cache_key, &final_response);
+            }
             return Some(final_response);
         }
 
@@ -112,6 +78,38 @@ pub fn read_file(file: &str) -> Option<String> {
     Some(content)
 }
 
+/// A prompt under /app/prompts that is only read and parsed when first used,
+/// so the tree-sitter backend does not depend on the prompt files.
+#[derive(Debug)]
+pub struct LazyPrompt<T> {
+    name: &'static str,
+    prompt_opt: Option<T>
+}
+
+impl<T: DeserializeOwned> LazyPrompt<T> {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, prompt_opt: None }
+    }
+
+    pub fn get(&mut self) -> Option<&mut T> {
+        if self.prompt_opt.is_none() {
+            let prompt_str_opt = read_file(&format!("/app/prompts/{}", self.name));
+            if prompt_str_opt.is_none() {
+                log::error!("[LazyPrompt/get] Unable to read {}", self.name);
+                return None;
+            }
+            let prompt_str = prompt_str_opt.expect("Empty prompt_str_opt");
+            let prompt_res = serde_json::from_str(&prompt_str);
+            if let Err(e) = &prompt_res {
+                log::error!("[LazyPrompt/get] Unable to deserialize {}: {:?}", self.name, e);
+                return None;
+            }
+            self.prompt_opt = Some(prompt_res.expect("Uncaught error in prompt_res"));
+        }
+        return self.prompt_opt.as_mut();
+    }
+}
+
 pub fn generate_random_string(length: usize) -> String {
     const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
     let mut rng = rand::thread_rng();
@@ -1,3 +1,4 @@
+use crate::metrics::registry::record_message_outcome;
 use crate::pubsub::listener::process_message;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -151,10 +152,12 @@ async fn process_job(
 		}
 	}
 	if all_ok {
+		record_message_outcome("http", "ack");
 		if let Err(err) = ack_job(client, server_url, installation_id, auth_token, &job.id).await {
 			log::error!("[http_queue] Failed to ack job {}: {:?}", job.id, err);
 		}
 	} else {
+		record_message_outcome("http", "nack");
 		if let Err(err) = fail_job(client, server_url, installation_id, auth_token, &job.id, "background task panicked", true).await {
 			log::error!("[http_queue] Failed to fail job {}: {:?}", job.id, err);
 		}
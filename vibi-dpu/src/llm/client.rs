@@ -0,0 +1,318 @@
+use std::env;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::utils::reqwest_client::get_client;
+
+const DEFAULT_AZURE_BASE_URL: &str = "https://diff-grapher.openai.azure.com";
+const DEFAULT_AZURE_DEPLOYMENT: &str = "diff-grapher";
+const DEFAULT_AZURE_API_VERSION: &str = "2025-01-01-preview";
+const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+const DEFAULT_MAX_TOKENS: u32 = 4000;
+// Older deployments ship the azure key next to the prompts
+const AZURE_KEY_FILE: &str = "/app/prompts/hf_token";
+
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    // Model or deployment name, also used to tell responses of different models apart
+    fn model(&self) -> &str;
+
+    // Single attempt, retries are left to the caller
+    async fn complete(&self, prompt: &str) -> Option<String>;
+}
+
+pub struct OpenAiClient {
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    max_tokens: u32,
+}
+
+impl OpenAiClient {
+    pub fn new(base_url: &str, api_key: Option<String>, model: &str, max_tokens: u32) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.to_string(),
+            max_tokens,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, prompt: &str) -> Option<String> {
+        let url = format!("{}/chat/completions", &self.base_url);
+        let mut request = get_client().post(&url).json(&json!({
+            "model": &self.model,
+            "messages": [
+                { "role": "user", "content": prompt }
+            ],
+            "max_tokens": self.max_tokens,
+        }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let resp_json = send_for_json(request, "OpenAiClient").await?;
+        return chat_completion_content(&resp_json);
+    }
+}
+
+pub struct AzureClient {
+    base_url: String,
+    deployment: String,
+    api_version: String,
+    api_key: String,
+    max_tokens: u32,
+}
+
+impl AzureClient {
+    pub fn new(base_url: &str, deployment: &str, api_version: &str, api_key: &str, max_tokens: u32) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            deployment: deployment.to_string(),
+            api_version: api_version.to_string(),
+            api_key: api_key.to_string(),
+            max_tokens,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for AzureClient {
+    fn model(&self) -> &str {
+        &self.deployment
+    }
+
+    async fn complete(&self, prompt: &str) -> Option<String> {
+        let url = format!("{}/openai/deployments/{}/chat/completions?api-version={}",
+            &self.base_url, &self.deployment, &self.api_version);
+        let request = get_client()
+            .post(&url)
+            .header("api-key", &self.api_key)
+            .json(&json!({
+                "messages": [
+                    { "role": "user", "content": prompt }
+                ],
+                "max_tokens": self.max_tokens,
+            }));
+        let resp_json = send_for_json(request, "AzureClient").await?;
+        return chat_completion_content(&resp_json);
+    }
+}
+
+#[derive(Debug, Serialize, Default, Deserialize, Clone)]
+struct OllamaResponse {
+    model: String,
+    created_at: String,
+    response: String,
+    done: bool
+}
+
+pub struct OllamaClient {
+    base_url: String,
+    model: String,
+}
+
+impl OllamaClient {
+    pub fn new(base_url: &str, model: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, prompt: &str) -> Option<String> {
+        let url = format!("{}/api/generate", &self.base_url);
+        let request = get_client().post(&url).json(&json!({
+            "model": &self.model,
+            "prompt": prompt,
+            "stream": false,
+        }));
+        let resp_json = send_for_json(request, "OllamaClient").await?;
+        let resp_res = serde_json::from_value::<OllamaResponse>(resp_json);
+        if resp_res.is_err() {
+            let e = resp_res.expect_err("No error in resp_res");
+            log::error!("[OllamaClient/complete] Unexpected response from ollama: {:?}", e);
+            return None;
+        }
+        let resp = resp_res.expect("Uncaught error in resp_res");
+        if resp.response.is_empty() {
+            return None;
+        }
+        return Some(resp.response);
+    }
+}
+
+async fn send_for_json(request: reqwest::RequestBuilder, client_name: &str) -> Option<Value> {
+    let response_res = request.send().await;
+    if response_res.is_err() {
+        let e = response_res.expect_err("No error in response_res");
+        log::error!("[{}/complete] Error in calling API: {:?}", client_name, e);
+        return None;
+    }
+    let response = response_res.expect("Uncaught error in response_res");
+    if !response.status().is_success() {
+        log::error!("[{}/complete] API returned status {}: {:?}", client_name,
+            response.status(), response.text().await);
+        return None;
+    }
+    let resp_json_res = response.json::<Value>().await;
+    if resp_json_res.is_err() {
+        let e = resp_json_res.expect_err("No error in resp_json_res");
+        log::error!("[{}/complete] Unable to parse response json {:?}", client_name, e);
+        return None;
+    }
+    return Some(resp_json_res.expect("Uncaught error in resp_json_res"));
+}
+
+// Joins the `message.content` of all choices in a chat completion
+fn chat_completion_content(resp_json: &Value) -> Option<String> {
+    let final_response: String = resp_json["choices"]
+        .as_array()
+        .map(|choices| choices.iter()
+            .filter_map(|choice| choice["message"]["content"].as_str())
+            .collect::<Vec<&str>>()
+            .join("\n"))
+        .unwrap_or_default();
+    if final_response.is_empty() {
+        return None;
+    }
+    return Some(final_response);
+}
+
+// LLM_PROVIDER picks the backend: "azure" (default), "openai" for any
+// OpenAI-compatible endpoint, or "ollama" for a local model.
+pub fn llm_client_from_env() -> Option<Box<dyn LlmClient>> {
+    let provider = non_empty_env("LLM_PROVIDER").unwrap_or_else(|| "azure".to_string());
+    let base_url_opt = non_empty_env("LLM_BASE_URL");
+    let api_key_opt = non_empty_env("LLM_API_KEY");
+    let max_tokens = non_empty_env("LLM_MAX_TOKENS")
+        .and_then(|max_tokens| max_tokens.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_MAX_TOKENS);
+    if provider.eq_ignore_ascii_case("openai") || provider.eq_ignore_ascii_case("ollama") {
+        let model_opt = non_empty_env("LLM_MODEL");
+        if model_opt.is_none() {
+            log::error!("[llm_client_from_env] LLM_MODEL must be set for the {} provider", provider);
+            return None;
+        }
+        let model = model_opt.expect("Empty model_opt");
+        if provider.eq_ignore_ascii_case("ollama") {
+            let base_url = base_url_opt.unwrap_or_else(|| DEFAULT_OLLAMA_BASE_URL.to_string());
+            return Some(Box::new(OllamaClient::new(&base_url, &model)));
+        }
+        let base_url = base_url_opt.unwrap_or_else(|| DEFAULT_OPENAI_BASE_URL.to_string());
+        return Some(Box::new(OpenAiClient::new(&base_url, api_key_opt, &model, max_tokens)));
+    }
+    if !provider.eq_ignore_ascii_case("azure") {
+        log::error!("[llm_client_from_env] Unknown LLM_PROVIDER: {}", provider);
+        return None;
+    }
+    let api_key_opt = api_key_opt.or_else(|| {
+        std::fs::read_to_string(AZURE_KEY_FILE).ok().map(|key| key.trim().to_string())
+    });
+    if api_key_opt.is_none() {
+        log::error!("[llm_client_from_env] LLM_API_KEY or {} must be set for the azure provider", AZURE_KEY_FILE);
+        return None;
+    }
+    let api_key = api_key_opt.expect("Empty api_key_opt");
+    let base_url = base_url_opt.unwrap_or_else(|| DEFAULT_AZURE_BASE_URL.to_string());
+    let deployment = non_empty_env("LLM_AZURE_DEPLOYMENT").unwrap_or_else(|| DEFAULT_AZURE_DEPLOYMENT.to_string());
+    let api_version = non_empty_env("LLM_AZURE_API_VERSION").unwrap_or_else(|| DEFAULT_AZURE_API_VERSION.to_string());
+    return Some(Box::new(AzureClient::new(&base_url, &deployment, &api_version, &api_key, max_tokens)));
+}
+
+// The Dockerfile sets every ARG as ENV, so unset build args show up as empty strings
+fn non_empty_env(name: &str) -> Option<String> {
+    return env::var(name).ok().filter(|value| !value.is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // Answers one request with `body` and hands back the raw request
+    fn stub_server(body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind test server");
+        let address = listener.local_addr().expect("test server has no address");
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("failed to accept request");
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let read = stream.read(&mut buf).expect("failed to read request");
+                request.extend_from_slice(&buf[..read]);
+                let request_str = String::from_utf8_lossy(&request).to_string();
+                if let Some((headers, received_body)) = request_str.split_once("\r\n\r\n") {
+                    let content_length = headers.lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|len| len.trim().to_string()))
+                        .and_then(|len| len.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if received_body.len() >= content_length {
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body);
+            stream.write_all(response.as_bytes()).expect("failed to write response");
+            String::from_utf8_lossy(&request).to_string()
+        });
+        return (format!("http://{address}"), server);
+    }
+
+    #[tokio::test]
+    async fn each_client_speaks_its_api() {
+        let (url, server) = stub_server(r#"{"choices":[{"message":{"content":"from openai"}}]}"#);
+        let client = OpenAiClient::new(&format!("{url}/v1/"), Some("sk-test".to_string()), "qwen2.5-coder", 100);
+        assert_eq!(client.complete("hello").await, Some("from openai".to_string()));
+        let request = server.join().expect("test server panicked");
+        assert!(request.starts_with("POST /v1/chat/completions "));
+        assert!(request.to_lowercase().contains("authorization: bearer sk-test"));
+        assert!(request.contains(r#""model":"qwen2.5-coder""#));
+
+        let (url, server) = stub_server(r#"{"choices":[{"message":{"content":"from azure"}}]}"#);
+        let client = AzureClient::new(&url, "grapher", "2024-10-21", "azure-key", 100);
+        assert_eq!(client.complete("hello").await, Some("from azure".to_string()));
+        let request = server.join().expect("test server panicked");
+        assert!(request.starts_with("POST /openai/deployments/grapher/chat/completions?api-version=2024-10-21 "));
+        assert!(request.to_lowercase().contains("api-key: azure-key"));
+
+        let (url, server) = stub_server(r#"{"model":"llama3","created_at":"2024-01-01T00:00:00Z","response":"from ollama","done":true}"#);
+        let client = OllamaClient::new(&url, "llama3");
+        assert_eq!(client.complete("hello").await, Some("from ollama".to_string()));
+        let request = server.join().expect("test server panicked");
+        assert!(request.starts_with("POST /api/generate "));
+        assert!(request.contains(r#""stream":false"#));
+        assert!(request.contains(r#""prompt":"hello""#));
+    }
+
+    #[tokio::test]
+    async fn empty_completion_is_none() {
+        let (url, server) = stub_server(r#"{"choices":[]}"#);
+        let client = OpenAiClient::new(&url, None, "m", 100);
+        assert_eq!(client.complete("hello").await, None);
+        server.join().expect("test server panicked");
+    }
+}
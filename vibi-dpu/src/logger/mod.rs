@@ -1,2 +1,3 @@
 pub mod init;
-mod cleanup;
\ No newline at end of file
+mod cleanup;
+pub mod redact;
\ No newline at end of file
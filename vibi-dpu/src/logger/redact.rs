@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+const MASK: &str = "[REDACTED]";
+// Env vars whose values must never show up in logs
+const SECRET_ENV_VARS: [&str; 9] = [
+	"GITHUB_PAT",
+	"GITLAB_PAT",
+	"GITHUB_APP_CLIENT_SECRET",
+	"BITBUCKET_CLIENT_SECRET",
+	"DPU_AUTH_TOKEN",
+	"DPU_WEBHOOK_SECRET",
+	"LLM_API_KEY",
+	"DPU_DB_ENCRYPTION_KEY",
+	"DPU_DB_ENCRYPTION_PREVIOUS_KEYS",
+];
+// Userinfo prefixes used in authenticated clone urls, the credential runs up to '@'
+const URL_CREDENTIAL_MARKERS: [&str; 3] = ["x-access-token:", "x-token-auth:", "oauth2:"];
+const BEARER_MARKERS: [&str; 2] = ["Bearer ", "bearer "];
+// Shorter values are too likely to match unrelated text
+const MIN_SECRET_LEN: usize = 6;
+
+// Secret values by what they are, the env var name for env secrets
+static SECRETS: Lazy<RwLock<HashMap<String, Vec<String>>>> = Lazy::new(|| {
+	let mut secrets = HashMap::new();
+	for env_var in SECRET_ENV_VARS {
+		let value = env::var(env_var).unwrap_or_default();
+		secrets.insert(env_var.to_string(), secret_values(value.split(',')));
+	}
+	return RwLock::new(secrets);
+});
+
+// Tokens fetched at runtime, like installation or oauth access tokens, are
+// registered here so they get masked along with the env secrets. A token
+// registered again under the same key replaces the one it was refreshed from.
+pub fn register_secret(key: &str, secret: &str) {
+	if let Ok(mut secrets) = SECRETS.write() {
+		secrets.insert(key.to_string(), secret_values([secret].into_iter()));
+	}
+}
+
+pub fn redact(message: &str) -> String {
+	let mut redacted = message.to_string();
+	if let Ok(secrets) = SECRETS.read() {
+		redacted = mask_secrets(&redacted, &secrets);
+	}
+	for marker in URL_CREDENTIAL_MARKERS {
+		redacted = mask_after(&redacted, marker, |c| c == '@' || c.is_whitespace());
+	}
+	for marker in BEARER_MARKERS {
+		redacted = mask_after(&redacted, marker, |c| c.is_whitespace() || c == '"' || c == '\'' || c == ',');
+	}
+	return redacted;
+}
+
+fn secret_values<'a>(values: impl Iterator<Item = &'a str>) -> Vec<String> {
+	return values
+		.map(|secret| secret.trim())
+		.filter(|secret| secret.len() >= MIN_SECRET_LEN)
+		.map(|secret| secret.to_string())
+		.collect();
+}
+
+fn mask_secrets(message: &str, secrets: &HashMap<String, Vec<String>>) -> String {
+	let mut masked = message.to_string();
+	// Longest first so a secret containing another one is masked whole
+	let mut secrets_vec: Vec<&String> = secrets.values().flatten().collect();
+	secrets_vec.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+	for secret in secrets_vec {
+		if masked.contains(secret.as_str()) {
+			masked = masked.replace(secret.as_str(), MASK);
+		}
+	}
+	return masked;
+}
+
+// Replaces everything after each occurrence of marker up to the first char matching is_end
+fn mask_after(message: &str, marker: &str, is_end: impl Fn(char) -> bool) -> String {
+	let mut masked = String::with_capacity(message.len());
+	let mut rest = message;
+	while let Some(start) = rest.find(marker) {
+		let value_start = start + marker.len();
+		masked.push_str(&rest[..value_start]);
+		let value = &rest[value_start..];
+		let value_end = value.find(|c: char| is_end(c)).unwrap_or(value.len());
+		if value_end > 0 {
+			masked.push_str(MASK);
+		}
+		rest = &value[value_end..];
+	}
+	masked.push_str(rest);
+	return masked;
+}
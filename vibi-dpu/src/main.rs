@@ -4,23 +4,35 @@ mod db;
 mod core;
 mod bitbucket;
 mod github;
+mod gitlab;
 mod utils;
 mod logger;
 mod health;
 mod graph;
 mod http_queue;
+mod webhook_queue;
+mod cli;
+mod llm;
+mod metrics;
 use github::auth::app_access_token;
 use health::status::send_status_start;
 use crate::{core::github::setup::process_repos, utils::user::ProviderEnum};
 
 #[tokio::main]
 async fn main() {
+	let args: Vec<String> = env::args().collect();
+	if args.get(1).is_some_and(|command| command == "review") {
+		let exit_code = cli::review::run_review_command(&args[2..]).await;
+		std::process::exit(exit_code);
+	}
 	let queue_transport = env::var("DPU_QUEUE_TRANSPORT").unwrap_or_else(|_| "pubsub".to_owned());
 	let installation_id = env::var("INSTALL_ID").expect("INSTALL_ID must be set");
 	let logs_init_status = logger::init::init_logger();
 	if !logs_init_status {
 		log::warn!("[main] Unable to create file logger");
 	}
+	db::encryption::init_encryption();
+	metrics::server::spawn_metrics_server();
 	send_status_start().await;
 	log::info!("Setting up your Vibinex Data Processing Unit, sit back and relax...");
 	let github_pat_res = env::var("GITHUB_PAT");
@@ -30,7 +42,7 @@ async fn main() {
 		log::debug!("[main] GITHUB PAT env var must be set");
 	} else {
 		let github_pat = github_pat_res.expect("Empty GITHUB_PAT env var");
-		log::debug!("[main] GITHUB PAT: [{}]", &github_pat);
+		log::debug!("[main] GITHUB PAT is set");
 
 		if provider_res.is_err() {
 			log::debug!("[main] PROVIDER env var must be set");
@@ -44,6 +56,10 @@ async fn main() {
 			}
 		}
 	}
+	if let Some(gitlab_pat) = gitlab::auth::gitlab_access_token() {
+		is_pat = true;
+		core::gitlab::setup::setup_self_host_user_repos_gitlab(&gitlab_pat).await;
+	}
 	if !is_pat {
 		load_auth_from_previous_installation().await;
 	}
@@ -58,8 +74,11 @@ async fn main() {
 			&gcp_credentials,
 			&installation_id,
 		).await;
+	} else if queue_transport.eq_ignore_ascii_case("webhook") {
+		let webhook_secret = webhook_queue::listener::webhook_secret().expect("DPU_WEBHOOK_SECRET must be set for webhook DPU queue transport");
+		webhook_queue::listener::serve_webhooks(&webhook_secret).await;
 	} else {
-		panic!("Unknown DPU_QUEUE_TRANSPORT value: '{}'. Expected 'http' or 'pubsub'.", queue_transport);
+		panic!("Unknown DPU_QUEUE_TRANSPORT value: '{}'. Expected 'http', 'pubsub' or 'webhook'.", queue_transport);
 	}
 }
 
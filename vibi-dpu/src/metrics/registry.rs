@@ -0,0 +1,171 @@
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use prometheus::{
+	register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+	Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, TextEncoder,
+};
+
+// Reviews and clones can take minutes, git and api calls usually well under a second
+const LONG_BUCKETS: [f64; 10] = [0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+const SHORT_BUCKETS: [f64; 10] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+static MESSAGES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| register_int_counter_vec!(
+	"dpu_messages_total", "Queue messages received, by msgtype", &["msgtype"]
+).expect("dpu_messages_total is registered once"));
+
+static MESSAGE_OUTCOMES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| register_int_counter_vec!(
+	"dpu_message_outcomes_total", "Queue messages acked or nacked, by transport", &["transport", "outcome"]
+).expect("dpu_message_outcomes_total is registered once"));
+
+static REVIEW_DURATION_SECONDS: Lazy<Histogram> = Lazy::new(|| register_histogram!(
+	"dpu_review_duration_seconds", "Time taken to process a review", LONG_BUCKETS.to_vec()
+).expect("dpu_review_duration_seconds is registered once"));
+
+static GIT_COMMAND_DURATION_SECONDS: Lazy<HistogramVec> = Lazy::new(|| register_histogram_vec!(
+	"dpu_git_command_duration_seconds", "Time taken by git commands, by subcommand", &["command"], LONG_BUCKETS.to_vec()
+).expect("dpu_git_command_duration_seconds is registered once"));
+
+static LLM_CALLS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| register_int_counter_vec!(
+	"dpu_llm_calls_total", "LLM completion attempts, by outcome", &["outcome"]
+).expect("dpu_llm_calls_total is registered once"));
+
+static LLM_LATENCY_SECONDS: Lazy<Histogram> = Lazy::new(|| register_histogram!(
+	"dpu_llm_latency_seconds", "Latency of LLM completion attempts", LONG_BUCKETS.to_vec()
+).expect("dpu_llm_latency_seconds is registered once"));
+
+static PROVIDER_API_CALLS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| register_int_counter_vec!(
+	"dpu_provider_api_calls_total", "Calls to provider APIs, by provider and status code", &["provider", "status"]
+).expect("dpu_provider_api_calls_total is registered once"));
+
+static PROVIDER_API_LATENCY_SECONDS: Lazy<HistogramVec> = Lazy::new(|| register_histogram_vec!(
+	"dpu_provider_api_latency_seconds", "Latency of provider API calls, by provider", &["provider"], SHORT_BUCKETS.to_vec()
+).expect("dpu_provider_api_latency_seconds is registered once"));
+
+static BLAME_LINES_TOTAL: Lazy<IntCounter> = Lazy::new(|| register_int_counter!(
+	"dpu_blame_lines_total", "Lines of git blame output processed"
+).expect("dpu_blame_lines_total is registered once"));
+
+static COMMENTS_POSTED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| register_int_counter_vec!(
+	"dpu_comments_posted_total", "Review comments created or edited in place, by provider", &["provider", "action"]
+).expect("dpu_comments_posted_total is registered once"));
+
+static REVIEWERS_ASSIGNED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| register_int_counter_vec!(
+	"dpu_reviewers_assigned_total", "Reviewers assigned to pull requests, by provider", &["provider"]
+).expect("dpu_reviewers_assigned_total is registered once"));
+
+pub fn record_message(msgtype: &str) {
+	MESSAGES_TOTAL.with_label_values(&[msgtype]).inc();
+}
+
+// outcome is "ack" or "nack"
+pub fn record_message_outcome(transport: &str, outcome: &str) {
+	MESSAGE_OUTCOMES_TOTAL.with_label_values(&[transport, outcome]).inc();
+}
+
+pub fn observe_review_duration(elapsed: Duration) {
+	REVIEW_DURATION_SECONDS.observe(elapsed.as_secs_f64());
+}
+
+// Observes the review duration when dropped, so reviews that return early count too
+pub struct ReviewTimer {
+	start: Instant,
+}
+
+impl ReviewTimer {
+	pub fn start() -> Self {
+		Self { start: Instant::now() }
+	}
+}
+
+impl Drop for ReviewTimer {
+	fn drop(&mut self) {
+		observe_review_duration(self.start.elapsed());
+	}
+}
+
+pub fn observe_git_command(command: &str, elapsed: Duration) {
+	GIT_COMMAND_DURATION_SECONDS.with_label_values(&[command]).observe(elapsed.as_secs_f64());
+}
+
+// outcome is "success", "failure" or "cached"
+pub fn record_llm_call(outcome: &str, elapsed: Option<Duration>) {
+	LLM_CALLS_TOTAL.with_label_values(&[outcome]).inc();
+	if let Some(elapsed) = elapsed {
+		LLM_LATENCY_SECONDS.observe(elapsed.as_secs_f64());
+	}
+}
+
+// status is the http status code, or "error" when no response was received
+pub fn record_provider_api_call(provider: &str, status: &str, elapsed: Duration) {
+	PROVIDER_API_CALLS_TOTAL.with_label_values(&[provider, status]).inc();
+	PROVIDER_API_LATENCY_SECONDS.with_label_values(&[provider]).observe(elapsed.as_secs_f64());
+}
+
+// Records the outcome of a provider request, for call sites holding a reqwest result
+pub fn record_provider_response<T>(provider: &str, response_res: &Result<reqwest::Response, T>, elapsed: Duration) {
+	let status = match response_res {
+		Ok(response) => response.status().as_u16().to_string(),
+		Err(_) => "error".to_string(),
+	};
+	record_provider_api_call(provider, &status, elapsed);
+}
+
+pub fn record_blame_lines(count: usize) {
+	BLAME_LINES_TOTAL.inc_by(count as u64);
+}
+
+pub fn record_comment_posted(provider: &str) {
+	COMMENTS_POSTED_TOTAL.with_label_values(&[provider, "created"]).inc();
+}
+
+// An existing review comment updated instead of posting a new one
+pub fn record_comment_edited(provider: &str) {
+	COMMENTS_POSTED_TOTAL.with_label_values(&[provider, "edited"]).inc();
+}
+
+pub fn record_reviewers_assigned(provider: &str, count: usize) {
+	REVIEWERS_ASSIGNED_TOTAL.with_label_values(&[provider]).inc_by(count as u64);
+}
+
+// Prometheus text exposition of every registered metric
+pub fn gather_metrics() -> String {
+	let mut buffer = Vec::new();
+	let encode_res = TextEncoder::new().encode(&prometheus::gather(), &mut buffer);
+	if encode_res.is_err() {
+		let e = encode_res.expect_err("No error in encode_res");
+		log::error!("[gather_metrics] Unable to encode metrics: {:?}", e);
+		return String::new();
+	}
+	return String::from_utf8_lossy(&buffer).to_string();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recorded_metrics_are_exported() {
+		record_message("webhook_callback");
+		record_message_outcome("pubsub", "ack");
+		observe_git_command("blame", Duration::from_millis(20));
+		record_llm_call("failure", Some(Duration::from_secs(1)));
+		record_provider_api_call("github", "404", Duration::from_millis(100));
+		record_blame_lines(7);
+		record_reviewers_assigned("gitlab", 2);
+		record_comment_posted("github");
+		record_comment_edited("github");
+		drop(ReviewTimer::start());
+		let exported = gather_metrics();
+		assert!(exported.contains(r#"dpu_messages_total{msgtype="webhook_callback"}"#));
+		assert!(exported.contains(r#"dpu_message_outcomes_total{outcome="ack",transport="pubsub"}"#));
+		assert!(exported.contains(r#"dpu_git_command_duration_seconds_count{command="blame"}"#));
+		assert!(exported.contains(r#"dpu_llm_calls_total{outcome="failure"}"#));
+		assert!(exported.contains(r#"dpu_provider_api_calls_total{provider="github",status="404"}"#));
+		assert!(exported.contains("dpu_blame_lines_total"));
+		assert!(exported.contains("dpu_review_duration_seconds_count 1"));
+		assert!(exported.contains(r#"dpu_reviewers_assigned_total{provider="gitlab"}"#));
+		assert!(exported.contains(r#"dpu_comments_posted_total{action="created",provider="github"} 1"#));
+		assert!(exported.contains(r#"dpu_comments_posted_total{action="edited",provider="github"} 1"#));
+	}
+}
@@ -0,0 +1,67 @@
+use std::{convert::Infallible, env, net::SocketAddr};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+
+use crate::metrics::registry::gather_metrics;
+
+// Only started when DPU_METRICS_PORT is set
+pub fn spawn_metrics_server() {
+	let port_opt = env::var("DPU_METRICS_PORT")
+		.ok()
+		.filter(|value| !value.is_empty());
+	if port_opt.is_none() {
+		return;
+	}
+	let port_str = port_opt.expect("Empty port_opt");
+	let port_res = port_str.parse::<u16>();
+	if port_res.is_err() {
+		log::error!("[spawn_metrics_server] Invalid DPU_METRICS_PORT: {}", &port_str);
+		return;
+	}
+	let addr = SocketAddr::from(([0, 0, 0, 0], port_res.expect("Uncaught error in port_res")));
+	tokio::spawn(serve_metrics(addr));
+}
+
+// A taken port only loses the metrics, the DPU keeps running
+async fn serve_metrics(addr: SocketAddr) {
+	let builder_res = Server::try_bind(&addr);
+	if let Err(e) = &builder_res {
+		log::error!("[serve_metrics] Unable to bind metrics server to {}: {:?}", addr, e);
+		return;
+	}
+	let make_svc = make_service_fn(|_conn| async {
+		Ok::<_, Infallible>(service_fn(|req| async move { Ok::<_, Infallible>(handle_request(req)) }))
+	});
+	log::info!("[serve_metrics] Serving metrics on {}/metrics", addr);
+	let builder = builder_res.expect("Uncaught error in builder_res");
+	if let Err(err) = builder.serve(make_svc).await {
+		log::error!("[serve_metrics] Metrics server stopped: {:?}", err);
+	}
+}
+
+fn handle_request(req: Request<Body>) -> Response<Body> {
+	if req.method() != Method::GET || req.uri().path() != "/metrics" {
+		let mut response = Response::new(Body::from("not found"));
+		*response.status_mut() = StatusCode::NOT_FOUND;
+		return response;
+	}
+	let mut response = Response::new(Body::from(gather_metrics()));
+	response.headers_mut().insert(
+		header::CONTENT_TYPE,
+		header::HeaderValue::from_static("text/plain; version=0.0.4"),
+	);
+	return response;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn taken_port_does_not_panic() {
+		let listener = std::net::TcpListener::bind("0.0.0.0:0").expect("bind test listener");
+		let addr = listener.local_addr().expect("listener address");
+		serve_metrics(addr).await;
+	}
+}
@@ -1,8 +1,11 @@
+use crate::core::approval::process_approval;
 use crate::core::trigger::process_trigger;
 use crate::{core::bitbucket::setup::handle_install_bitbucket, utils::user::ProviderEnum};
 use crate::core::github::setup::{handle_install_github, process_pat_repos};
+use crate::core::gitlab;
 use crate::core::review::process_review;
-use crate::db::prs::{bitbucket_process_and_update_pr_if_different, github_process_and_update_pr_if_different};
+use crate::metrics::registry::{record_message, record_message_outcome};
+use crate::db::prs::{bitbucket_process_and_update_pr_if_different, github_process_and_update_pr_if_different, gitlab_process_and_update_pr_if_different};
 use futures_util::StreamExt;
 use google_cloud_auth::credentials::CredentialsFile;
 use google_cloud_default::WithAuthExt;
@@ -39,6 +42,7 @@ pub async fn process_message(
 		return handles;
 	}
 	let msgtype = msgtype_opt.expect("Empty msgtype");
+	record_message(msgtype);
 	match msgtype.as_str() {
 		"install_callback" => {
 			handles.extend(process_install_callback(&data_bytes).await);
@@ -73,7 +77,12 @@ pub async fn process_message(
 		}
 		"PATSetup" => {
 			log::info!("Setting up repositories...");
-			process_pat_repos(&data_bytes).await;
+			let provider = std::env::var("PROVIDER").unwrap_or_default();
+			if provider.eq_ignore_ascii_case(&ProviderEnum::Gitlab.to_string()) {
+				gitlab::setup::process_pat_repos(&data_bytes).await;
+			} else {
+				process_pat_repos(&data_bytes).await;
+			}
 			log::info!("Processed repos successfully");
 		}
 		_ => {
@@ -191,11 +200,14 @@ pub async fn listen_messages(keypath: &str, topicname: &str) {
 			// NACK on panic so Pub/Sub redelivers the message.
 			if all_ok {
 				let _ = message.ack().await;
+				record_message_outcome("pubsub", "ack");
 			} else {
 				let _ = message.nack().await;
+				record_message_outcome("pubsub", "nack");
 			}
 		} else {
 			let _ = message.ack().await;
+			record_message_outcome("pubsub", "ack");
 		}
 	}
 }
@@ -245,13 +257,26 @@ async fn process_and_update_pr_if_different(deserialised_msg_data: &Value) -> bo
 		.await;
 
 		if event_type == "pullrequest:approved" {
+			log::info!("Processing Bitbucket approved PR event...");
+			process_approval(&deserialised_msg_data["eventPayload"], &deserialised_msg_data["repoConfig"], &workspace_slug, &repo_slug, &pr_number, &repo_provider).await;
 			is_reviewable = false;
-			todo!("Process approved event");
 		};
 		if if_process_pr && (event_type == "pullrequest:created" || event_type == "pullrequest:updated") {
 			is_reviewable = true;
 		};
 	};
+	if repo_provider == ProviderEnum::Gitlab.to_string().to_lowercase() {
+		let path_with_namespace = deserialised_msg_data["eventPayload"]["project"]["path_with_namespace"].to_string().trim_matches('"').to_string();
+		let pr_number = deserialised_msg_data["eventPayload"]["object_attributes"]["iid"].to_string().trim_matches('"').to_string();
+		let object_kind = deserialised_msg_data["eventPayload"]["object_kind"].to_string().trim_matches('"').to_string();
+		if let Some((repo_owner, repo_name)) = path_with_namespace.rsplit_once('/') {
+			log::debug!("[process_webhook_callback] {}, {}, {}, {}", object_kind, repo_owner, repo_name, pr_number);
+			if object_kind == "merge_request" {
+				log::info!("Parsed Gitlab MR event...");
+				is_reviewable = gitlab_process_and_update_pr_if_different(&deserialised_msg_data["eventPayload"], repo_owner, repo_name, &pr_number, &repo_provider).await;
+			}
+		} else { log::error!("[process_webhook_callback] Invalid gitlab project path: {}", &path_with_namespace); }
+	}
 	return is_reviewable;
 
 }
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use super::gitops::file_at_commit;
+use super::repo_config::RepoConfig;
+use super::review::Review;
+
+const IGNORE_REVS_FILE: &str = ".git-blame-ignore-revs";
+
+// Which lines of the PR's base are blamed and how blame attributes them,
+// beyond the whitespace and mailmap handling that always applies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlameSettings {
+    ignore_revs: Vec<String>,
+    detect_renames: bool,
+    detect_moves: bool,
+    detect_copies: bool,
+    #[serde(default)]
+    context_lines: usize,
+    #[serde(default)]
+    enclosing_function: bool,
+}
+
+impl BlameSettings {
+    pub fn new(ignore_revs: &[String], detect_renames: Option<bool>, detect_moves: Option<bool>,
+            detect_copies: Option<bool>) -> Self {
+        return Self {
+            ignore_revs: ignore_revs.to_vec(),
+            detect_renames: detect_renames.unwrap_or(true),
+            detect_moves: detect_moves.unwrap_or(false),
+            detect_copies: detect_copies.unwrap_or(false),
+            context_lines: 0,
+            enclosing_function: false,
+        };
+    }
+
+    // Also blames context_lines lines on each side of a pure insertion, and
+    // the rest of the function around each change
+    pub fn with_surrounding_lines(mut self, context_lines: Option<usize>, enclosing_function: Option<bool>) -> Self {
+        self.context_lines = context_lines.unwrap_or(0);
+        self.enclosing_function = enclosing_function.unwrap_or(false);
+        return self;
+    }
+
+    pub fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        return Self::new(repo_config.blame_ignore_revs().as_deref().unwrap_or_default(),
+            repo_config.detect_renames(),
+            repo_config.blame_detect_moves(),
+            repo_config.blame_detect_copies())
+            .with_surrounding_lines(repo_config.relevance_context_lines(),
+                repo_config.relevance_enclosing_function());
+    }
+
+    // Adds the commits listed in `.git-blame-ignore-revs` at the PR base, the
+    // file `git config blame.ignoreRevsFile` usually points at
+    pub fn with_ignore_revs_file(mut self, review: &Review) -> Self {
+        let contents_opt = file_at_commit(review.clone_dir(), review.base_head_commit(), IGNORE_REVS_FILE);
+        if let Some(contents) = contents_opt {
+            for rev in parse_ignore_revs(&contents) {
+                if !self.ignore_revs.contains(&rev) {
+                    self.ignore_revs.push(rev);
+                }
+            }
+        }
+        return self;
+    }
+
+    pub fn ignore_revs(&self) -> &[String] {
+        return &self.ignore_revs;
+    }
+
+    // Pair deleted and added files as renames when diffing the PR, like `-M`
+    pub fn detect_renames(&self) -> bool {
+        return self.detect_renames;
+    }
+
+    // Follow lines moved within a file to the commit that wrote them, like
+    // `git blame -M`
+    pub fn detect_moves(&self) -> bool {
+        return self.detect_moves;
+    }
+
+    // Follow lines moved or copied from other files changed in the same
+    // commit, like `git blame -C`
+    pub fn detect_copies(&self) -> bool {
+        return self.detect_copies;
+    }
+
+    pub fn context_lines(&self) -> usize {
+        return self.context_lines;
+    }
+
+    pub fn enclosing_function(&self) -> bool {
+        return self.enclosing_function;
+    }
+}
+
+impl Default for BlameSettings {
+    fn default() -> Self {
+        return Self::new(&[], None, None, None);
+    }
+}
+
+// One commit per line, `#` starts a comment
+pub fn parse_ignore_revs(contents: &str) -> Vec<String> {
+    return contents.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|rev| !rev.is_empty())
+        .map(|rev| rev.to_string())
+        .collect();
+}
@@ -0,0 +1,167 @@
+use super::gitops::{file_at_commit, StatItem};
+use super::review::Review;
+
+// Where GitHub, GitLab and Bitbucket look for the file, first match wins
+const CODEOWNERS_PATHS: [&str; 6] = [
+    ".github/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+    ".bitbucket/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    "codeowners",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+struct CodeOwnersRule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeOwners {
+    rules: Vec<CodeOwnersRule>,
+}
+
+impl CodeOwners {
+    // Parses `<pattern> <owner>...` lines. GitLab section headers like
+    // `[Docs] @docs-team` and Bitbucket's `CODEOWNERS` share the same syntax,
+    // headers are skipped and every rule is matched in file order.
+    pub fn parse(contents: &str) -> Self {
+        let mut rules = Vec::<CodeOwnersRule>::new();
+        for line in contents.lines() {
+            let line = line.split(" #").next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('[') || line.starts_with("^[") {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let pattern = words.next().unwrap_or_default().replace("\\ ", " ");
+            let owners: Vec<String> = words
+                .map(|owner| owner.trim_start_matches('@').to_string())
+                .filter(|owner| !owner.is_empty())
+                .collect();
+            rules.push(CodeOwnersRule { pattern, owners });
+        }
+        return Self { rules };
+    }
+
+    // Owners of the last rule matching the file, a rule without owners
+    // unsets ownership for the files it matches
+    pub fn owners_of(&self, filepath: &str) -> Vec<String> {
+        let filepath = filepath.trim_start_matches('/');
+        let rule_opt = self.rules.iter().rev()
+            .find(|rule| pattern_matches(&rule.pattern, filepath));
+        return rule_opt.map(|rule| rule.owners.to_owned()).unwrap_or_default();
+    }
+
+    // Owners of any of the files, in the order they are first found
+    pub fn owners_of_files(&self, filepaths: &[&str]) -> Vec<String> {
+        let mut owners = Vec::<String>::new();
+        for filepath in filepaths {
+            for owner in self.owners_of(filepath) {
+                if !owners.contains(&owner) {
+                    owners.push(owner);
+                }
+            }
+        }
+        return owners;
+    }
+}
+
+// Owners of the PR's changed files according to CODEOWNERS at the base commit
+pub fn review_code_owners(review: &Review, changed_files: &[&StatItem]) -> Vec<String> {
+    let contents_opt = CODEOWNERS_PATHS.iter()
+        .find_map(|path| file_at_commit(review.clone_dir(), review.base_head_commit(), path));
+    if contents_opt.is_none() {
+        log::debug!("[review_code_owners] No CODEOWNERS file in {}", review.repo_name());
+        return Vec::new();
+    }
+    let code_owners = CodeOwners::parse(&contents_opt.expect("Empty contents_opt"));
+    let filepaths: Vec<&str> = changed_files.iter().map(|item| item.filepath.as_str()).collect();
+    let owners = code_owners.owners_of_files(&filepaths);
+    log::debug!("[review_code_owners] owners = {:?}", &owners);
+    return owners;
+}
+
+// gitignore style matching: a pattern without a slash (other than a trailing
+// one) matches at any depth, a trailing slash only matches directories, and a
+// directory match covers everything below it.
+pub fn pattern_matches(pattern: &str, filepath: &str) -> bool {
+    let dir_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return false;
+    }
+    let anchored = trimmed.starts_with('/') || trimmed.contains('/');
+    let glob = match anchored {
+        true => trimmed.trim_start_matches('/').to_string(),
+        false => format!("**/{}", trimmed),
+    };
+    let segments: Vec<&str> = filepath.split('/').collect();
+    for end in 1..=segments.len() {
+        let is_file = end == segments.len();
+        if is_file && dir_only {
+            continue;
+        }
+        if glob_matches(glob.as_bytes(), segments[..end].join("/").as_bytes()) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// `*` and `?` stay within a path segment, `**` spans any number of segments
+fn glob_matches(glob: &[u8], path: &[u8]) -> bool {
+    if glob.starts_with(b"**/") {
+        let rest = &glob[3..];
+        if glob_matches(rest, path) {
+            return true;
+        }
+        return path.iter().enumerate()
+            .any(|(idx, c)| *c == b'/' && glob_matches(rest, &path[idx + 1..]));
+    }
+    if glob == b"**" {
+        return true;
+    }
+    match glob.first() {
+        None => path.is_empty(),
+        Some(b'*') => {
+            let rest = &glob[1..];
+            let segment_len = path.iter().position(|c| *c == b'/').unwrap_or(path.len());
+            return (0..=segment_len).any(|skip| glob_matches(rest, &path[skip..]));
+        }
+        Some(b'?') => !path.is_empty() && path[0] != b'/' && glob_matches(&glob[1..], &path[1..]),
+        Some(c) => !path.is_empty() && path[0] == *c && glob_matches(&glob[1..], &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+# Default owners
+*       @org/core @alice
+
+[Docs] @docs-team
+docs/   @bob
+*.md    @carol # markdown anywhere
+/build/logs/ @dave
+src/**/generated.rs
+apps/*/config.toml admin@example.com
+";
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let code_owners = CodeOwners::parse(CODEOWNERS);
+        assert_eq!(code_owners.owners_of("src/main.rs"), vec!["org/core", "alice"]);
+        assert_eq!(code_owners.owners_of("docs/setup/install.txt"), vec!["bob"]);
+        assert_eq!(code_owners.owners_of("docs/README.md"), vec!["carol"]);
+        assert_eq!(code_owners.owners_of("build/logs/out.txt"), vec!["dave"]);
+        assert_eq!(code_owners.owners_of("app/build/logs/out.txt"), vec!["org/core", "alice"]);
+        assert!(code_owners.owners_of("src/a/b/generated.rs").is_empty());
+        assert_eq!(code_owners.owners_of("apps/web/config.toml"), vec!["admin@example.com"]);
+        assert_eq!(code_owners.owners_of("apps/web/nested/config.toml"), vec!["org/core", "alice"]);
+        assert_eq!(code_owners.owners_of_files(&["README.md", "src/lib.rs", "docs/a.txt"]),
+            vec!["carol", "org/core", "alice", "bob"]);
+    }
+}
@@ -0,0 +1,29 @@
+use std::fmt;
+
+// The comments the DPU keeps on a PR. Each kind is posted once and edited in
+// place afterwards, so its id is stored per review.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentKind {
+    Relevance,
+    Coverage,
+    DiffGraph,
+}
+
+impl fmt::Display for CommentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CommentKind::Relevance => write!(f, "relevance"),
+            CommentKind::Coverage => write!(f, "coverage"),
+            CommentKind::DiffGraph => write!(f, "diff_graph"),
+        }
+    }
+}
+
+// Outcome of editing a previously posted comment
+#[derive(Debug, PartialEq)]
+pub enum CommentEdit {
+    Edited,
+    // Deleted on the provider, a new comment should be posted
+    Missing,
+    Failed,
+}
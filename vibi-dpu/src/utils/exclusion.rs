@@ -0,0 +1,113 @@
+use super::repo_config::RepoConfig;
+
+// Domains of the noreply commit emails providers give accounts and bots, e.g.
+// users.noreply.github.com. Their local part carries the account's handle.
+const NOREPLY_DOMAIN_PREFIX: &str = "users.noreply.";
+
+// Git aliases, emails and provider handles to leave out of relevance and
+// auto-assignment, like dependabot or release bots. Patterns are matched case
+// insensitively and may use `*` and `?` wildcards, e.g. `*[bot]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthorExclusions {
+    patterns: Vec<String>,
+}
+
+impl AuthorExclusions {
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns.iter()
+                .map(|pattern| pattern.trim().to_lowercase())
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        return Self::new(repo_config.relevance_exclusions().as_deref().unwrap_or_default());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.patterns.is_empty();
+    }
+
+    // Patterns without an `@` are also tried on the local part of noreply
+    // emails, so `*[bot]` catches 49699333+dependabot[bot]@users.noreply.github.com.
+    // Any other email only matches a pattern for the whole email.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        let handle_opt = name.split_once('@')
+            .filter(|(_, domain)| domain.starts_with(NOREPLY_DOMAIN_PREFIX))
+            .map(|(local_part, _)| local_part);
+        return self.patterns.iter()
+            .any(|pattern| wildcard_match(pattern, &name)
+                || (!pattern.contains('@') && handle_opt.is_some_and(|handle| wildcard_match(pattern, handle))));
+    }
+
+    // True if the git alias or any of the handles mapped to it is excluded
+    pub fn excludes(&self, git_alias: &str, handles: &Option<Vec<String>>) -> bool {
+        if self.matches(git_alias) {
+            return true;
+        }
+        return handles.as_ref().is_some_and(|handles| handles.iter().any(|handle| self.matches(handle)));
+    }
+}
+
+// `*` matches any run of characters and `?` a single one
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p_idx, mut n_idx) = (0, 0);
+    // Position of the last `*` and the name index it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n_idx < name.len() {
+        if p_idx < pattern.len() && (pattern[p_idx] == '?' || pattern[p_idx] == name[n_idx]) {
+            p_idx += 1;
+            n_idx += 1;
+        } else if p_idx < pattern.len() && pattern[p_idx] == '*' {
+            backtrack = Some((p_idx, n_idx));
+            p_idx += 1;
+        } else if let Some((star_idx, star_n_idx)) = backtrack {
+            p_idx = star_idx + 1;
+            n_idx = star_n_idx + 1;
+            backtrack = Some((star_idx, n_idx));
+        } else {
+            return false;
+        }
+    }
+    return pattern[p_idx..].iter().all(|c| *c == '*');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_and_handles_match_patterns() {
+        let exclusions = AuthorExclusions::new(&[
+            "*[bot]".to_string(),
+            "renovate*".to_string(),
+            "release-bot@example.com".to_string(),
+        ]);
+        assert!(exclusions.matches("dependabot[bot]"));
+        assert!(exclusions.matches("49699333+dependabot[bot]@users.noreply.github.com"));
+        assert!(exclusions.matches("Renovate-Bot"));
+        assert!(exclusions.matches("release-bot@example.com"));
+        assert!(!exclusions.matches("alice@example.com"));
+        assert!(exclusions.excludes("ci@example.com", &Some(vec!["github-actions[bot]".to_string()])));
+        assert!(!exclusions.excludes("alice@example.com", &Some(vec!["alice".to_string()])));
+        assert!(exclusions.matches("123-renovate[bot]@users.noreply.gitlab.example.com"));
+        assert!(!exclusions.matches("ops[bot]@example.com"));
+        assert!(wildcard_match("a?c*", "abcdef"));
+        assert!(!wildcard_match("a?c", "abcd"));
+    }
+
+    #[test]
+    fn local_parts_only_match_on_noreply_domains() {
+        let exclusions = AuthorExclusions::new(&["alice".to_string(), "*@users.noreply.github.com".to_string()]);
+        assert!(exclusions.matches("Alice"));
+        assert!(!exclusions.matches("alice@example.com"));
+        assert!(!exclusions.matches("alice@corp.example.org"));
+        assert!(exclusions.matches("alice@users.noreply.gitlab.com"));
+        assert!(exclusions.matches("1234+bob@users.noreply.github.com"));
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::codeowners::pattern_matches;
+use super::gitops::{file_at_commit, StatItem};
+use super::repo_config::RepoConfig;
+use super::review::Review;
+
+// Files changing more lines than this are too costly to blame
+const DEFAULT_MAX_CHANGED_LINES: i32 = 5500;
+
+// Dependency lockfiles, matched by file name at any depth
+const DEFAULT_LOCKFILES: [&str; 20] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "Podfile.lock",
+    "Package.resolved",
+    "pubspec.lock",
+    "mix.lock",
+    "packages.lock.json",
+    "gradle.lockfile",
+    "flake.lock",
+    "conan.lock",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExclusionReason {
+    Size,
+    Binary,
+    Pattern,
+    Lockfile,
+    Generated,
+    Vendored,
+}
+
+impl fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExclusionReason::Size => write!(f, "Large size"),
+            ExclusionReason::Binary => write!(f, "Binary"),
+            ExclusionReason::Pattern => write!(f, "Excluded by repo config"),
+            ExclusionReason::Lockfile => write!(f, "Lockfile"),
+            ExclusionReason::Generated => write!(f, "Generated (linguist-generated)"),
+            ExclusionReason::Vendored => write!(f, "Vendored (linguist-vendored)"),
+        }
+    }
+}
+
+// Which changed files are left out of blame and relevance. Size and binary
+// checks always apply, `include` globs win over every other rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileExclusionRules {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_changed_lines: i32,
+    exclude_lockfiles: bool,
+    exclude_generated: bool,
+}
+
+impl Default for FileExclusionRules {
+    fn default() -> Self {
+        return Self::new(&[], &[], None, None, None);
+    }
+}
+
+impl FileExclusionRules {
+    pub fn new(include: &[String], exclude: &[String], max_changed_lines: Option<i32>,
+            exclude_lockfiles: Option<bool>, exclude_generated: Option<bool>) -> Self {
+        return Self {
+            include: include.to_vec(),
+            exclude: exclude.to_vec(),
+            max_changed_lines: max_changed_lines.unwrap_or(DEFAULT_MAX_CHANGED_LINES),
+            exclude_lockfiles: exclude_lockfiles.unwrap_or(true),
+            exclude_generated: exclude_generated.unwrap_or(true),
+        };
+    }
+
+    pub fn from_repo_config(repo_config: &RepoConfig) -> Self {
+        return Self::new(repo_config.included_paths().as_deref().unwrap_or_default(),
+            repo_config.excluded_paths().as_deref().unwrap_or_default(),
+            repo_config.max_file_changed_lines(),
+            repo_config.exclude_lockfiles(),
+            repo_config.exclude_generated_files());
+    }
+
+    pub fn reads_gitattributes(&self) -> bool {
+        return self.exclude_generated;
+    }
+
+    pub fn exclusion_reason(&self, item: &StatItem, attributes: &GitAttributes) -> Option<ExclusionReason> {
+        if item.is_binary() {
+            return Some(ExclusionReason::Binary);
+        }
+        if item.changed_lines() > self.max_changed_lines {
+            return Some(ExclusionReason::Size);
+        }
+        let filepath = item.filepath.as_str();
+        if self.include.iter().any(|pattern| pattern_matches(pattern, filepath)) {
+            return None;
+        }
+        if self.exclude.iter().any(|pattern| pattern_matches(pattern, filepath)) {
+            return Some(ExclusionReason::Pattern);
+        }
+        let filename = filepath.rsplit('/').next().unwrap_or_default();
+        if self.exclude_lockfiles && DEFAULT_LOCKFILES.contains(&filename) {
+            return Some(ExclusionReason::Lockfile);
+        }
+        if self.exclude_generated && attributes.is_set(filepath, "linguist-generated") {
+            return Some(ExclusionReason::Generated);
+        }
+        if self.exclude_generated && attributes.is_set(filepath, "linguist-vendored") {
+            return Some(ExclusionReason::Vendored);
+        }
+        return None;
+    }
+
+    // Splits the changed files into (excluded, included), tagging the excluded ones
+    pub fn partition(&self, statvec: Vec<StatItem>, attributes: &GitAttributes) -> (Vec<StatItem>, Vec<StatItem>) {
+        let mut excluded_files = Vec::<StatItem>::new();
+        let mut included_files = Vec::<StatItem>::new();
+        for mut item in statvec {
+            let reason_opt = self.exclusion_reason(&item, attributes);
+            if reason_opt.is_none() {
+                included_files.push(item);
+                continue;
+            }
+            item.set_exclusion(reason_opt);
+            excluded_files.push(item);
+        }
+        return (excluded_files, included_files);
+    }
+}
+
+// Attributes from the root `.gitattributes`, only set/unset values are kept
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitAttributes {
+    rules: Vec<(String, Vec<(String, bool)>)>,
+}
+
+impl GitAttributes {
+    pub fn parse(contents: &str) -> Self {
+        let mut rules = Vec::<(String, Vec<(String, bool)>)>::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let pattern = words.next().unwrap_or_default().to_string();
+            let attrs: Vec<(String, bool)> = words.map(|word| {
+                if let Some(attr) = word.strip_prefix('-').or(word.strip_prefix('!')) {
+                    return (attr.to_string(), false);
+                }
+                match word.split_once('=') {
+                    Some((attr, value)) => (attr.to_string(), value != "false" && value != "0"),
+                    None => (word.to_string(), true),
+                }
+            }).collect();
+            rules.push((pattern, attrs));
+        }
+        return Self { rules };
+    }
+
+    // Reads `.gitattributes` as of the PR base, so a PR can't mark its own
+    // changes as generated to keep them out of blame
+    pub fn for_review(review: &Review) -> Self {
+        let contents_opt = file_at_commit(review.clone_dir(), review.base_head_commit(), ".gitattributes");
+        return contents_opt.map(|contents| Self::parse(&contents)).unwrap_or_default();
+    }
+
+    // The last line naming the attribute for a matching pattern wins
+    pub fn is_set(&self, filepath: &str, attr: &str) -> bool {
+        for (pattern, attrs) in self.rules.iter().rev() {
+            let value_opt = attrs.iter().rev().find(|(name, _)| name == attr);
+            if value_opt.is_none() || !pattern_matches(pattern, filepath) {
+                continue;
+            }
+            return value_opt.expect("Empty value_opt").1;
+        }
+        return false;
+    }
+}
+
+// Excluded files grouped by reason as a markdown list
+pub fn excluded_files_text(excluded_files: &[StatItem]) -> String {
+    let mut reasons: Vec<ExclusionReason> = excluded_files.iter()
+        .map(|item| item.exclusion().unwrap_or(ExclusionReason::Size))
+        .collect();
+    reasons.sort();
+    reasons.dedup();
+    let mut text = String::new();
+    for reason in reasons {
+        let files: Vec<String> = excluded_files.iter()
+            .filter(|item| item.exclusion().unwrap_or(ExclusionReason::Size) == reason)
+            .map(|item| format!("`{}`", item.filepath))
+            .collect();
+        text += &format!("- {}: {}\n", reason, files.join(", "));
+    }
+    return text;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_excluded_by_reason() {
+        let statvec = || vec![
+            StatItem::new("src/new_file.rs", 12, 0, false),
+            StatItem::new("Cargo.lock", 3, 4, false),
+            StatItem::new("assets/logo.png", 0, 0, true),
+            StatItem::new("data/fixtures.json", 6000, 0, false),
+            StatItem::new("src/proto/api.pb.rs", 5, 1, false),
+            StatItem::new("third_party/lib.c", 2, 2, false),
+            StatItem::new("docs/notes.md", 1, 1, false),
+            StatItem::new("docs/keep.md", 1, 1, false),
+        ];
+        let attributes = GitAttributes::parse("\
+            src/proto/** linguist-generated\n\
+            third_party/** linguist-vendored=true\n\
+            src/proto/hand_written.rs -linguist-generated\n");
+        assert!(!attributes.is_set("src/proto/hand_written.rs", "linguist-generated"));
+        let rules = FileExclusionRules::new(&["docs/keep.md".to_string()], &["docs/**".to_string()],
+            None, None, None);
+        let (excluded, included) = rules.partition(statvec(), &attributes);
+
+        let included: Vec<&str> = included.iter().map(|item| item.filepath.as_str()).collect();
+        assert_eq!(included, vec!["src/new_file.rs", "docs/keep.md"]);
+        let excluded: Vec<(&str, Option<ExclusionReason>)> = excluded.iter()
+            .map(|item| (item.filepath.as_str(), item.exclusion())).collect();
+        assert_eq!(excluded, vec![
+            ("Cargo.lock", Some(ExclusionReason::Lockfile)),
+            ("assets/logo.png", Some(ExclusionReason::Binary)),
+            ("data/fixtures.json", Some(ExclusionReason::Size)),
+            ("src/proto/api.pb.rs", Some(ExclusionReason::Generated)),
+            ("third_party/lib.c", Some(ExclusionReason::Vendored)),
+            ("docs/notes.md", Some(ExclusionReason::Pattern)),
+        ]);
+
+        let permissive = FileExclusionRules::new(&[], &[], Some(10000), Some(false), Some(false));
+        let (excluded, _) = permissive.partition(statvec(), &attributes);
+        assert_eq!(excluded.len(), 1);
+    }
+}
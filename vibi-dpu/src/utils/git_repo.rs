@@ -0,0 +1,931 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+
+use git2::{BlameOptions, BranchType, Delta, DiffFindOptions, DiffOptions, ObjectType, Oid, Patch, Repository, Sort, Tree,
+    WorktreeAddOptions, WorktreePruneOptions};
+
+use super::blame_settings::BlameSettings;
+use super::lineitem::LineItem;
+use crate::metrics::registry::observe_git_command;
+
+// Alphanumeric characters a run of lines needs to count as moved or copied,
+// git blame's defaults for -M and -C
+const MOVE_SCORE: usize = 20;
+const COPY_SCORE: usize = 40;
+// How often blame follows lines past an ignored or moving commit, and how many
+// of the files a commit changed are searched for copied lines
+const MAX_BLAME_DEPTH: usize = 8;
+const MAX_COPY_SOURCES: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Other,
+}
+
+// A `-U0` hunk, line numbers are 1-based like in the patch header
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    // Text after the closing `@@`, usually the enclosing function
+    pub function_context: Option<String>,
+}
+
+impl DiffHunk {
+    // Lines of the base version this hunk removes or rewrites
+    pub fn deleted_range(&self) -> Option<(usize, usize)> {
+        if self.old_lines == 0 {
+            return None;
+        }
+        return Some((self.old_start, self.old_start + self.old_lines - 1));
+    }
+
+    pub fn added_range(&self) -> Option<(usize, usize)> {
+        if self.new_lines == 0 {
+            return None;
+        }
+        return Some((self.new_start, self.new_start + self.new_lines - 1));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    // Path in the base version, set for renamed files
+    pub old_path: Option<String>,
+    pub status: FileStatus,
+    pub binary: bool,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+// Consecutive lines last changed by the same commit
+#[derive(Debug, Clone)]
+pub struct BlameRange {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub line_item: LineItem,
+}
+
+// A file as of a commit
+type FileAtCommit = (Oid, String);
+
+// Settings of one blame and the lookups shared by its spans
+struct BlameContext {
+    ignored: HashSet<Oid>,
+    detect_moves: bool,
+    detect_copies: bool,
+    committers: HashMap<Oid, String>,
+    file_lines: HashMap<FileAtCommit, Rc<Vec<String>>>,
+    // Lines each commit added or rewrote in a file, as start..=end ranges
+    added_lines: HashMap<FileAtCommit, Rc<Vec<(usize, usize)>>>,
+    // Base paths of the files each commit changed, where copies come from
+    changed_files: HashMap<Oid, Rc<Vec<String>>>,
+    // Commits blame has been followed past to reach the current span
+    depth: usize,
+}
+
+// Lines of a parent file searched for moved or copied lines, indexed by their
+// trimmed text so a match is only tried where the first line is equal
+struct MoveSource {
+    path: String,
+    score: usize,
+    lines: Rc<Vec<String>>,
+    starts: HashMap<String, Vec<usize>>,
+}
+
+impl MoveSource {
+    fn new(path: String, score: usize, lines: Rc<Vec<String>>) -> Self {
+        let mut starts = HashMap::<String, Vec<usize>>::new();
+        for (idx, line) in lines.iter().enumerate() {
+            if !line.trim().is_empty() {
+                starts.entry(line.trim().to_string()).or_default().push(idx);
+            }
+        }
+        return Self { path, score, lines, starts };
+    }
+}
+
+// Read access to a local clone through libgit2. Fetching and cloning still go
+// through the git cli, which owns the credentials in the remote url.
+pub struct GitRepo {
+    repo: Repository,
+}
+
+impl GitRepo {
+    pub fn open(directory: &str) -> Option<Self> {
+        let repo_res = Repository::open(directory);
+        if let Err(e) = &repo_res {
+            log::error!("[GitRepo::open] Unable to open repository at {}: {:?}", directory, e);
+            return None;
+        }
+        return Some(Self { repo: repo_res.expect("Uncaught error in repo_res") });
+    }
+
+    fn commit_id(&self, rev: &str) -> Option<Oid> {
+        let object_res = self.repo.revparse_single(rev)
+            .and_then(|object| object.peel(ObjectType::Commit));
+        if let Err(e) = &object_res {
+            log::debug!("[GitRepo::commit_id] Unable to resolve {}: {:?}", rev, e);
+            return None;
+        }
+        return Some(object_res.expect("Uncaught error in object_res").id());
+    }
+
+    pub fn commit_exists(&self, rev: &str) -> bool {
+        return self.commit_id(rev).is_some();
+    }
+
+    // Contents of a file as of commit, None if it doesn't exist there
+    pub fn file_at_commit(&self, rev: &str, filepath: &str) -> Option<String> {
+        let start = Instant::now();
+        let commit_id = self.commit_id(rev)?;
+        let blob_res = self.repo.find_commit(commit_id)
+            .and_then(|commit| commit.tree())
+            .and_then(|tree| tree.get_path(Path::new(filepath)))
+            .and_then(|entry| entry.to_object(&self.repo))
+            .and_then(|object| object.peel_to_blob());
+        observe_git_command("show", start.elapsed());
+        if blob_res.is_err() {
+            log::debug!("[GitRepo::file_at_commit] {}:{} not found", rev, filepath);
+            return None;
+        }
+        let blob = blob_res.expect("Uncaught error in blob_res");
+        return Some(String::from_utf8_lossy(blob.content()).to_string());
+    }
+
+    // Changes from the merge base of base and head to head, like `git diff
+    // base...head -U0`, with `-M` if detect_renames. An empty paths list diffs
+    // every file, a rename is only found if both of its paths are listed.
+    pub fn diff(&self, base: &str, head: &str, ignore_space_change: bool, detect_renames: bool,
+            paths: &[&str]) -> Option<Vec<FileDiff>> {
+        let start = Instant::now();
+        let base_id = self.commit_id(base)?;
+        let head_id = self.commit_id(head)?;
+        let merge_base_res = self.repo.merge_base(base_id, head_id);
+        if let Err(e) = &merge_base_res {
+            log::error!("[GitRepo::diff] No merge base for {} and {}: {:?}", base, head, e);
+            return None;
+        }
+        let merge_base = merge_base_res.expect("Uncaught error in merge_base_res");
+        let old_tree_res = self.repo.find_commit(merge_base).and_then(|commit| commit.tree());
+        let new_tree_res = self.repo.find_commit(head_id).and_then(|commit| commit.tree());
+        if old_tree_res.is_err() || new_tree_res.is_err() {
+            log::error!("[GitRepo::diff] Unable to read trees for {}...{}", base, head);
+            return None;
+        }
+        let file_diffs_opt = self.diff_trees(old_tree_res.as_ref().ok(), new_tree_res.as_ref().ok(),
+            ignore_space_change, detect_renames, paths);
+        observe_git_command("diff", start.elapsed());
+        return file_diffs_opt;
+    }
+
+    fn diff_trees(&self, old_tree: Option<&Tree>, new_tree: Option<&Tree>, ignore_space_change: bool,
+            detect_renames: bool, paths: &[&str]) -> Option<Vec<FileDiff>> {
+        let mut opts = DiffOptions::new();
+        opts.context_lines(0).ignore_whitespace_change(ignore_space_change);
+        for path in paths {
+            opts.pathspec(path);
+        }
+        if !paths.is_empty() {
+            opts.disable_pathspec_match(true);
+        }
+        let diff_res = self.repo.diff_tree_to_tree(old_tree, new_tree, Some(&mut opts));
+        if let Err(e) = &diff_res {
+            log::error!("[GitRepo::diff_trees] Unable to diff trees: {:?}", e);
+            return None;
+        }
+        let mut diff = diff_res.expect("Uncaught error in diff_res");
+        if detect_renames {
+            if let Err(e) = diff.find_similar(Some(DiffFindOptions::new().renames(true))) {
+                log::error!("[GitRepo::diff_trees] Unable to detect renames: {:?}", e);
+            }
+        }
+        let mut file_diffs = Vec::<FileDiff>::new();
+        for idx in 0..diff.deltas().len() {
+            let patch_res = Patch::from_diff(&diff, idx);
+            if let Err(e) = &patch_res {
+                log::error!("[GitRepo::diff_trees] Unable to generate patch {}: {:?}", idx, e);
+                continue;
+            }
+            let patch_opt = patch_res.expect("Uncaught error in patch_res");
+            if patch_opt.is_none() {
+                continue;
+            }
+            let patch = patch_opt.expect("Empty patch_opt");
+            file_diffs.push(file_diff_from_patch(&patch));
+        }
+        return Some(file_diffs);
+    }
+
+    // Commits the ignore revs resolve to. Resolved once per review and passed
+    // to every blame_ranges call.
+    pub fn ignored_commits(&self, blame_settings: &BlameSettings) -> HashSet<Oid> {
+        return blame_settings.ignore_revs().iter()
+            .filter_map(|ignore_rev| self.commit_id(ignore_rev))
+            .collect();
+    }
+
+    // Blame of each start..=end line range of the file as of commit, ignoring
+    // whitespace. The file is blamed once, over the span of all the ranges.
+    // Lines last changed by one of the ignored commits are blamed past that
+    // commit, and moved or copied lines past their mover if enabled.
+    pub fn blame_ranges(&self, rev: &str, filepath: &str, line_ranges: &[(usize, usize)],
+            blame_settings: &BlameSettings, ignored: &HashSet<Oid>) -> Option<Vec<Vec<BlameRange>>> {
+        let start = Instant::now();
+        let commit_id = self.commit_id(rev)?;
+        let min_line = line_ranges.iter().map(|(start_line, _)| *start_line).min()?;
+        let max_line = line_ranges.iter().map(|(_, end_line)| *end_line).max()?;
+        let mut context = BlameContext {
+            ignored: ignored.clone(),
+            detect_moves: blame_settings.detect_moves(),
+            detect_copies: blame_settings.detect_copies(),
+            committers: HashMap::new(),
+            file_lines: HashMap::new(),
+            added_lines: HashMap::new(),
+            changed_files: HashMap::new(),
+            depth: 0,
+        };
+        let hunks_opt = self.blame_span(commit_id, filepath, min_line, max_line, &mut context);
+        observe_git_command("blame", start.elapsed());
+        let hunks = hunks_opt?;
+        let ranges = line_ranges.iter()
+            .map(|(start_line, end_line)| slice_ranges(&hunks, *start_line, *end_line, *start_line))
+            .collect();
+        return Some(ranges);
+    }
+
+    fn blame_span(&self, commit_id: Oid, filepath: &str, min_line: usize, max_line: usize,
+            context: &mut BlameContext) -> Option<Vec<BlameRange>> {
+        let mut opts = BlameOptions::new();
+        opts.newest_commit(commit_id)
+            .min_line(min_line)
+            .max_line(max_line)
+            .ignore_whitespace(true)
+            .use_mailmap(true);
+        let blame_res = self.repo.blame_file(Path::new(filepath), Some(&mut opts));
+        if let Err(e) = &blame_res {
+            log::error!("[GitRepo::blame_span] Unable to blame {}:{} lines {}-{}: {:?}",
+                commit_id, filepath, min_line, max_line, e);
+            return None;
+        }
+        let blame = blame_res.expect("Uncaught error in blame_res");
+        let mut ranges = Vec::<BlameRange>::new();
+        for hunk in blame.iter() {
+            let start_line = hunk.final_start_line().max(min_line);
+            let end_line = (hunk.final_start_line() + hunk.lines_in_hunk() - 1).min(max_line);
+            if end_line < start_line {
+                continue;
+            }
+            let orig_line = hunk.orig_start_line() + start_line - hunk.final_start_line();
+            let orig_path = hunk.path()
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or(filepath.to_string());
+            let can_recurse = context.depth < MAX_BLAME_DEPTH;
+            if can_recurse && context.ignored.contains(&hunk.final_commit_id()) {
+                let past_ranges = self.blame_past(hunk.final_commit_id(), &orig_path, orig_line,
+                    start_line, end_line, context);
+                ranges.extend(past_ranges);
+                continue;
+            }
+            let signature = hunk.final_signature();
+            let author_email = signature.email().unwrap_or_default().to_string();
+            let author_name = signature.name().unwrap_or_default().to_string();
+            let committer = context.committers.entry(hunk.final_commit_id())
+                .or_insert_with(|| self.repo.find_commit(hunk.final_commit_id())
+                    .map(|commit| commit.committer().email().unwrap_or_default().to_string())
+                    .unwrap_or_default())
+                .to_string();
+            let line_item = LineItem::new(
+                if author_email.is_empty() { author_name.clone() } else { author_email },
+                author_name,
+                signature.when().seconds().to_string(),
+                hunk.final_commit_id().to_string(),
+                committer,
+                orig_line,
+                orig_path,
+            );
+            if can_recurse && (context.detect_moves || context.detect_copies) {
+                let moved_ranges_opt = self.blame_moved(hunk.final_commit_id(), start_line, end_line,
+                    &line_item, context);
+                if let Some(moved_ranges) = moved_ranges_opt {
+                    ranges.extend(moved_ranges);
+                    continue;
+                }
+            }
+            ranges.push(BlameRange { start_line, end_line, line_item });
+        }
+        return Some(ranges);
+    }
+
+    // Blames final lines start_line..=end_line, which commit_id wrote as
+    // line_item says, past that commit where lines it added match a run of
+    // lines in its first parent: in the same file for moves, in another file
+    // the commit changed for copies. The longest run wins, lines matching none
+    // stay on commit_id. None if no line matched.
+    fn blame_moved(&self, commit_id: Oid, start_line: usize, end_line: usize, line_item: &LineItem,
+            context: &mut BlameContext) -> Option<Vec<BlameRange>> {
+        let commit = self.repo.find_commit(commit_id).ok()?;
+        let parent_id = commit.parent_id(0).ok()?;
+        let filepath = line_item.orig_path();
+        let len = end_line - start_line + 1;
+        let first_line = line_item.orig_line() - 1;
+        let file_lines = self.file_lines(commit_id, filepath, context);
+        if file_lines.len() < first_line + len {
+            return None;
+        }
+        let written = &file_lines[first_line..first_line + len];
+        let added_lines = self.added_lines(commit_id, parent_id, filepath, context);
+        let is_added = |offset: usize| {
+            let line = line_item.orig_line() + offset;
+            return added_lines.iter().any(|(added_start, added_end)| *added_start <= line && line <= *added_end);
+        };
+        if !(0..len).any(is_added) {
+            return None;
+        }
+        let mut sources = Vec::<MoveSource>::new();
+        if context.detect_moves {
+            let lines = self.file_lines(parent_id, filepath, context);
+            sources.push(MoveSource::new(filepath.to_string(), MOVE_SCORE, lines));
+        }
+        if context.detect_copies {
+            let changed_files = self.changed_files(commit_id, parent_id, context);
+            if changed_files.len() > MAX_COPY_SOURCES {
+                log::debug!("[GitRepo::blame_moved] Searching {} of the {} files {} changed for copies",
+                    MAX_COPY_SOURCES, changed_files.len(), commit_id);
+            }
+            for path in changed_files.iter().filter(|path| *path != filepath).take(MAX_COPY_SOURCES) {
+                let lines = self.file_lines(parent_id, path, context);
+                sources.push(MoveSource::new(path.to_string(), COPY_SCORE, lines));
+            }
+        }
+        // (offset in written, source, parent start line, length)
+        let mut matches = Vec::<(usize, usize, usize, usize)>::new();
+        let mut offset = 0;
+        while offset < len {
+            if !is_added(offset) {
+                offset += 1;
+                continue;
+            }
+            let added_end = (offset..len).find(|end| !is_added(*end)).unwrap_or(len);
+            let best_opt = longest_match(&written[offset..added_end], &sources);
+            if best_opt.is_none() {
+                offset += 1;
+                continue;
+            }
+            let (source, parent_start, run) = best_opt.expect("Empty best_opt");
+            matches.push((offset, source, parent_start, run));
+            offset += run;
+        }
+        if matches.is_empty() {
+            return None;
+        }
+        let mut ranges = Vec::<BlameRange>::new();
+        let mut offset = 0;
+        let stay = |from: usize, to: usize| BlameRange {
+            start_line: start_line + from,
+            end_line: start_line + to - 1,
+            line_item: line_item.clone().with_orig_line(line_item.orig_line() + from),
+        };
+        for (match_offset, source, parent_start, run) in matches {
+            if match_offset > offset {
+                ranges.push(stay(offset, match_offset));
+            }
+            ranges.extend(self.blame_parent(parent_id, &sources[source].path,
+                &[(start_line + match_offset, parent_start, run)], context));
+            offset = match_offset + run;
+        }
+        if offset < len {
+            ranges.push(stay(offset, len));
+        }
+        return Some(ranges);
+    }
+
+    // Blames runs of (final start, parent start, length) lines of the parent,
+    // one commit further from the blamed one. The parent is blamed once over
+    // the span of all the runs.
+    fn blame_parent(&self, parent_id: Oid, filepath: &str, line_runs: &[(usize, usize, usize)],
+            context: &mut BlameContext) -> Vec<BlameRange> {
+        let min_line_opt = line_runs.iter().map(|(_, parent_start, _)| *parent_start).min();
+        let max_line_opt = line_runs.iter().map(|(_, parent_start, len)| parent_start + len - 1).max();
+        if min_line_opt.is_none() || max_line_opt.is_none() {
+            return Vec::new();
+        }
+        context.depth += 1;
+        let parent_ranges_opt = self.blame_span(parent_id, filepath, min_line_opt.expect("Empty min_line_opt"),
+            max_line_opt.expect("Empty max_line_opt"), context);
+        context.depth -= 1;
+        let parent_ranges = parent_ranges_opt.unwrap_or_default();
+        return line_runs.iter()
+            .flat_map(|(final_start, parent_start, len)| slice_ranges(&parent_ranges, *parent_start,
+                parent_start + len - 1, *final_start))
+            .collect();
+    }
+
+    fn file_lines(&self, commit_id: Oid, filepath: &str, context: &mut BlameContext) -> Rc<Vec<String>> {
+        return context.file_lines.entry((commit_id, filepath.to_string()))
+            .or_insert_with(|| Rc::new(self.file_at_commit(&commit_id.to_string(), filepath)
+                .map(|contents| contents.lines().map(|line| line.to_string()).collect())
+                .unwrap_or_default()))
+            .clone();
+    }
+
+    fn added_lines(&self, commit_id: Oid, parent_id: Oid, filepath: &str,
+            context: &mut BlameContext) -> Rc<Vec<(usize, usize)>> {
+        return context.added_lines.entry((commit_id, filepath.to_string()))
+            .or_insert_with(|| {
+                let commit_tree = self.repo.find_commit(commit_id).and_then(|commit| commit.tree()).ok();
+                let parent_tree = self.repo.find_commit(parent_id).and_then(|parent| parent.tree()).ok();
+                let added_lines = self.diff_trees(parent_tree.as_ref(), commit_tree.as_ref(), false, false, &[filepath])
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|file_diff| file_diff.hunks)
+                    .filter_map(|hunk| hunk.added_range())
+                    .collect();
+                return Rc::new(added_lines);
+            })
+            .clone();
+    }
+
+    fn changed_files(&self, commit_id: Oid, parent_id: Oid, context: &mut BlameContext) -> Rc<Vec<String>> {
+        return context.changed_files.entry(commit_id)
+            .or_insert_with(|| {
+                let commit_tree = self.repo.find_commit(commit_id).and_then(|commit| commit.tree()).ok();
+                let parent_tree = self.repo.find_commit(parent_id).and_then(|parent| parent.tree()).ok();
+                let changed_files = self.diff_trees(parent_tree.as_ref(), commit_tree.as_ref(), false, true, &[])
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|file_diff| file_diff.status != FileStatus::Added && !file_diff.binary)
+                    .map(|file_diff| file_diff.old_path.unwrap_or(file_diff.path))
+                    .collect();
+                return Rc::new(changed_files);
+            })
+            .clone();
+    }
+
+    // Blames final lines start_line..=end_line, which are lines from orig_line
+    // on in the ignored commit, at its first parent. Lines the commit added
+    // have no parent line and are dropped rather than credited to it.
+    fn blame_past(&self, commit_id: Oid, filepath: &str, orig_line: usize, start_line: usize,
+            end_line: usize, context: &mut BlameContext) -> Vec<BlameRange> {
+        let mut ranges = Vec::<BlameRange>::new();
+        let commit_res = self.repo.find_commit(commit_id);
+        if commit_res.is_err() {
+            return ranges;
+        }
+        let commit = commit_res.expect("Uncaught error in commit_res");
+        let parent_res = commit.parent(0);
+        if parent_res.is_err() {
+            log::debug!("[GitRepo::blame_past] Ignored commit {} has no parent", commit_id);
+            return ranges;
+        }
+        let parent = parent_res.expect("Uncaught error in parent_res");
+        let file_diffs_opt = self.diff_trees(parent.tree().ok().as_ref(), commit.tree().ok().as_ref(),
+            false, false, &[filepath]);
+        let hunks = file_diffs_opt.unwrap_or_default().into_iter().next()
+            .map(|file_diff| file_diff.hunks)
+            .unwrap_or_default();
+        // (final line, parent line, length) of runs that stay consecutive in both
+        let mut line_runs = Vec::<(usize, usize, usize)>::new();
+        for final_line in start_line..=end_line {
+            let parent_line_opt = parent_line(&hunks, orig_line + final_line - start_line);
+            if parent_line_opt.is_none() {
+                continue;
+            }
+            let parent_line = parent_line_opt.expect("Empty parent_line_opt");
+            if let Some(run) = line_runs.last_mut() {
+                if run.0 + run.2 == final_line && run.1 + run.2 == parent_line {
+                    run.2 += 1;
+                    continue;
+                }
+            }
+            line_runs.push((final_line, parent_line, 1));
+        }
+        ranges.extend(self.blame_parent(parent.id(), filepath, &line_runs, context));
+        return ranges;
+    }
+
+    // Author emails of every commit reachable from any ref
+    pub fn author_emails(&self) -> Option<Vec<String>> {
+        let start = Instant::now();
+        let revwalk_res = self.repo.revwalk();
+        if let Err(e) = &revwalk_res {
+            log::error!("[GitRepo::author_emails] Unable to walk history: {:?}", e);
+            return None;
+        }
+        let mut revwalk = revwalk_res.expect("Uncaught error in revwalk_res");
+        let _ = revwalk.set_sorting(Sort::NONE);
+        if let Err(e) = revwalk.push_glob("*") {
+            log::error!("[GitRepo::author_emails] Unable to push refs: {:?}", e);
+            return None;
+        }
+        let mut emails: Vec<String> = revwalk
+            .filter_map(|oid_res| oid_res.ok())
+            .filter_map(|oid| self.repo.find_commit(oid).ok())
+            .filter_map(|commit| commit.author().email().map(|email| email.to_string()))
+            .collect();
+        emails.sort();
+        emails.dedup();
+        observe_git_command("log", start.elapsed());
+        return Some(emails);
+    }
+
+    // Detached checkout of commit in the working directory
+    pub fn checkout(&self, rev: &str) -> bool {
+        let start = Instant::now();
+        let commit_id_opt = self.commit_id(rev);
+        if commit_id_opt.is_none() {
+            log::error!("[GitRepo::checkout] Unable to find commit {}", rev);
+            return false;
+        }
+        let commit_id = commit_id_opt.expect("Empty commit_id_opt");
+        let checkout_res = self.repo.find_object(commit_id, None)
+            .and_then(|object| self.repo.checkout_tree(&object, None))
+            .and_then(|_| self.repo.set_head_detached(commit_id));
+        observe_git_command("checkout", start.elapsed());
+        if let Err(e) = &checkout_res {
+            log::error!("[GitRepo::checkout] Unable to checkout {}: {:?}", rev, e);
+            return false;
+        }
+        return true;
+    }
+
+    // Linked worktree with a detached HEAD at rev. The temporary branch git
+    // needs to create it is deleted straight away, so no refs are left behind.
+    pub fn add_worktree(&self, name: &str, path: &Path, rev: &str) -> bool {
+        let start = Instant::now();
+        let commit_id_opt = self.commit_id(rev);
+        if commit_id_opt.is_none() {
+            log::error!("[GitRepo::add_worktree] Unable to find commit {}", rev);
+            return false;
+        }
+        let commit_id = commit_id_opt.expect("Empty commit_id_opt");
+        let branch_res = self.repo.find_commit(commit_id)
+            .and_then(|commit| self.repo.branch(name, &commit, false));
+        if let Err(e) = &branch_res {
+            log::error!("[GitRepo::add_worktree] Unable to create branch {}: {:?}", name, e);
+            return false;
+        }
+        let mut branch = branch_res.expect("Uncaught error in branch_res");
+        let worktree_res = self.repo.worktree(name, path,
+                Some(WorktreeAddOptions::new().reference(Some(branch.get()))))
+            .and_then(|worktree| Repository::open_from_worktree(&worktree))
+            .and_then(|worktree_repo| worktree_repo.set_head_detached(commit_id));
+        let delete_res = branch.delete();
+        observe_git_command("worktree", start.elapsed());
+        if let Err(e) = &worktree_res {
+            log::error!("[GitRepo::add_worktree] Unable to add worktree {} at {}: {:?}", name, rev, e);
+            self.remove_worktree(name);
+            return false;
+        }
+        if let Err(e) = &delete_res {
+            log::warn!("[GitRepo::add_worktree] Unable to delete branch {}: {:?}", name, e);
+        }
+        return true;
+    }
+
+    // Deletes the worktree's directory and its admin files under .git/worktrees
+    pub fn remove_worktree(&self, name: &str) -> bool {
+        let prune_res = self.repo.find_worktree(name)
+            .and_then(|worktree| worktree.prune(Some(WorktreePruneOptions::new()
+                .valid(true)
+                .locked(true)
+                .working_tree(true))));
+        if let Ok(mut branch) = self.repo.find_branch(name, BranchType::Local) {
+            let _ = branch.delete();
+        }
+        if let Err(e) = &prune_res {
+            log::error!("[GitRepo::remove_worktree] Unable to remove worktree {}: {:?}", name, e);
+            return false;
+        }
+        return true;
+    }
+
+    pub fn set_remote_url(&self, remote: &str, url: &str) -> bool {
+        let set_res = self.repo.remote_set_url(remote, url);
+        if let Err(e) = &set_res {
+            log::error!("[GitRepo::set_remote_url] Unable to set url of {}: {:?}", remote, e);
+            return false;
+        }
+        return true;
+    }
+}
+
+// Longest run of source lines equal to the start of written, ignoring
+// indentation, with enough alphanumeric characters for its source's score.
+// Returns (source, 1-based start line, length), earlier sources win ties.
+fn longest_match(written: &[String], sources: &[MoveSource]) -> Option<(usize, usize, usize)> {
+    let first_line = written.first()?.trim();
+    if first_line.is_empty() {
+        return None;
+    }
+    let mut best: Option<(usize, usize, usize)> = None;
+    for (source_idx, source) in sources.iter().enumerate() {
+        for start in source.starts.get(first_line).into_iter().flatten() {
+            let run = written.iter().zip(&source.lines[*start..])
+                .take_while(|(written_line, line)| written_line.trim() == line.trim())
+                .count();
+            if best.is_some_and(|(_, _, best_run)| run <= best_run) {
+                continue;
+            }
+            let alphanumeric: usize = written[..run].iter()
+                .map(|line| line.chars().filter(|c| c.is_alphanumeric()).count())
+                .sum();
+            if alphanumeric >= source.score {
+                best = Some((source_idx, start + 1, run));
+            }
+        }
+    }
+    return best;
+}
+
+// The parts of ranges within start_line..=end_line, moved so that start_line
+// becomes final_start
+fn slice_ranges(ranges: &[BlameRange], start_line: usize, end_line: usize, final_start: usize) -> Vec<BlameRange> {
+    return ranges.iter()
+        .filter_map(|range| {
+            let slice_start = range.start_line.max(start_line);
+            let slice_end = range.end_line.min(end_line);
+            if slice_end < slice_start {
+                return None;
+            }
+            let line_item = range.line_item.clone()
+                .with_orig_line(range.line_item.orig_line() + slice_start - range.start_line);
+            return Some(BlameRange {
+                start_line: slice_start - start_line + final_start,
+                end_line: slice_end - start_line + final_start,
+                line_item,
+            });
+        })
+        .collect();
+}
+
+// Line of the parent a line of the child maps to, going by the child's `-U0`
+// hunks. Rewritten lines are matched by their offset in the hunk, lines past
+// the end of the removed ones were added and map to nothing.
+fn parent_line(hunks: &[DiffHunk], line: usize) -> Option<usize> {
+    let mut parent_line = line as isize;
+    for hunk in hunks {
+        if hunk.new_lines > 0 && line >= hunk.new_start && line < hunk.new_start + hunk.new_lines {
+            let offset = line - hunk.new_start;
+            if offset >= hunk.old_lines {
+                return None;
+            }
+            return Some(hunk.old_start + offset);
+        }
+        // A pure deletion's new_start is the line before it
+        let hunk_end = if hunk.new_lines == 0 { hunk.new_start } else { hunk.new_start + hunk.new_lines - 1 };
+        if line <= hunk_end {
+            break;
+        }
+        parent_line += hunk.old_lines as isize - hunk.new_lines as isize;
+    }
+    return Some(parent_line as usize);
+}
+
+fn file_diff_from_patch(patch: &Patch) -> FileDiff {
+    let delta = patch.delta();
+    let status = match delta.status() {
+        Delta::Added => FileStatus::Added,
+        Delta::Deleted => FileStatus::Deleted,
+        Delta::Modified => FileStatus::Modified,
+        Delta::Renamed => FileStatus::Renamed,
+        _ => FileStatus::Other,
+    };
+    let old_path = match status {
+        FileStatus::Renamed => delta.old_file().path().map(|path| path.to_string_lossy().to_string()),
+        _ => None,
+    };
+    let path = delta.new_file().path().or(delta.old_file().path())
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (_, additions, deletions) = patch.line_stats().unwrap_or_default();
+    let mut hunks = Vec::<DiffHunk>::new();
+    for hunk_idx in 0..patch.num_hunks() {
+        if let Ok((hunk, _)) = patch.hunk(hunk_idx) {
+            let header = String::from_utf8_lossy(hunk.header()).to_string();
+            let function_context = header.rsplit_once("@@")
+                .map(|(_, context)| context.trim().to_string())
+                .filter(|context| !context.is_empty());
+            hunks.push(DiffHunk {
+                old_start: hunk.old_start() as usize,
+                old_lines: hunk.old_lines() as usize,
+                new_start: hunk.new_start() as usize,
+                new_lines: hunk.new_lines() as usize,
+                function_context,
+            });
+        }
+    }
+    return FileDiff {
+        path,
+        old_path,
+        status,
+        binary: delta.flags().is_binary(),
+        additions,
+        deletions,
+        hunks,
+    };
+}
+
+#[cfg(test)]
+pub mod fixture {
+    use std::path::PathBuf;
+
+    use git2::{Oid, Repository, Signature, Time};
+
+    // Throwaway repository for tests, removed on drop
+    pub struct FixtureRepo {
+        pub dir: PathBuf,
+        pub repo: Repository,
+    }
+
+    impl FixtureRepo {
+        pub fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("vibi-dpu-git-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            let repo = Repository::init(&dir).expect("Unable to init fixture repo");
+            return Self { dir, repo };
+        }
+
+        pub fn path(&self) -> &str {
+            return self.dir.to_str().expect("Non utf-8 fixture path");
+        }
+
+        // Commits the files on top of HEAD as the author at the given time
+        pub fn commit(&self, files: &[(&str, &str)], email: &str, time: i64) -> Oid {
+            let mut index = self.repo.index().expect("No index");
+            for (path, contents) in files {
+                let full_path = self.dir.join(path);
+                std::fs::create_dir_all(full_path.parent().expect("No parent"))
+                    .expect("Unable to create dirs");
+                std::fs::write(&full_path, contents).expect("Unable to write file");
+                index.add_path(std::path::Path::new(path)).expect("Unable to stage");
+            }
+            index.write().expect("Unable to write index");
+            let tree_id = index.write_tree().expect("Unable to write tree");
+            let tree = self.repo.find_tree(tree_id).expect("No tree");
+            let name = email.split('@').next().unwrap_or_default();
+            let signature = Signature::new(name, email, &Time::new(time, 0)).expect("Bad signature");
+            let parent_opt = self.repo.head().ok()
+                .and_then(|head| head.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent_opt.iter().collect();
+            return self.repo.commit(Some("HEAD"), &signature, &signature, "fixture", &tree, &parents)
+                .expect("Unable to commit");
+        }
+
+        // Stages the removal of a file, committed by the next commit
+        pub fn remove(&self, path: &str) {
+            let mut index = self.repo.index().expect("No index");
+            index.remove_path(std::path::Path::new(path)).expect("Unable to unstage");
+            index.write().expect("Unable to write index");
+            let _ = std::fs::remove_file(self.dir.join(path));
+        }
+    }
+
+    impl Drop for FixtureRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixture::FixtureRepo;
+    use super::*;
+
+    #[test]
+    fn diff_and_blame_are_typed() {
+        let fixture = FixtureRepo::new("typed");
+        let base = fixture.commit(&[
+            ("src/lib.rs", "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\n"),
+            ("logo.png", "\u{0}\u{1}binary"),
+        ], "alice@example.com", 1_000);
+        fixture.commit(&[("src/lib.rs", "fn a() {}\nfn b() { 1 }\nfn c() {}\nfn d() {}\n")],
+            "bob@example.com", 2_000);
+        let head = fixture.commit(&[
+            ("src/lib.rs", "fn a() {}\nfn b() { 2 }\nfn c() {}\n"),
+            ("src/new.rs", "fn e() {}\n"),
+        ], "carol@example.com", 3_000);
+        let git_repo = GitRepo::open(fixture.path()).expect("fixture should open");
+        let (base, head) = (base.to_string(), head.to_string());
+        assert!(git_repo.commit_exists(&head));
+        assert!(!git_repo.commit_exists("0123456789abcdef0123456789abcdef01234567"));
+        assert_eq!(git_repo.file_at_commit(&base, "src/lib.rs").map(|contents| contents.lines().count()), Some(4));
+        assert_eq!(git_repo.file_at_commit(&base, "src/new.rs"), None);
+
+        let diffs = git_repo.diff(&base, &head, true, false, &[]).expect("diff");
+        let summary: Vec<(&str, FileStatus, usize, usize)> = diffs.iter()
+            .map(|diff| (diff.path.as_str(), diff.status, diff.additions, diff.deletions)).collect();
+        assert_eq!(summary, vec![("src/lib.rs", FileStatus::Modified, 1, 2), ("src/new.rs", FileStatus::Added, 1, 0)]);
+        let deleted: Vec<(usize, usize)> = diffs[0].hunks.iter().filter_map(|hunk| hunk.deleted_range()).collect();
+        assert_eq!(deleted, vec![(2, 2), (4, 4)]);
+        let only_new = git_repo.diff(&base, &head, true, false, &["src/new.rs"]).expect("diff");
+        assert_eq!(only_new.len(), 1);
+
+        let blame = git_repo.blame_ranges(&head, "src/lib.rs", &[(1, 3), (2, 2)], &BlameSettings::default(),
+            &HashSet::new()).expect("blame");
+        type BlamedLines<'a> = Vec<(usize, usize, &'a str, &'a str, usize)>;
+        let authors: Vec<BlamedLines> = blame.iter()
+            .map(|ranges| ranges.iter().map(|range| (range.start_line, range.end_line,
+                range.line_item.author_id().as_str(), range.line_item.author_name().as_str(),
+                range.line_item.orig_line())).collect())
+            .collect();
+        assert_eq!(authors, vec![
+            vec![(1, 1, "alice@example.com", "alice", 1), (2, 2, "carol@example.com", "carol", 2),
+                (3, 3, "alice@example.com", "alice", 3)],
+            vec![(2, 2, "carol@example.com", "carol", 2)],
+        ]);
+        assert_eq!(blame[0][1].line_item.committer(), "carol@example.com");
+        assert_eq!(blame[0][0].line_item.timestamp(), "1000");
+        assert_eq!(git_repo.author_emails().expect("emails"),
+            vec!["alice@example.com", "bob@example.com", "carol@example.com"]);
+    }
+
+    #[test]
+    fn ignored_commits_are_blamed_past() {
+        let fixture = FixtureRepo::new("ignore-revs");
+        fixture.commit(&[("lib.py", "a = 1\nb = 2\nc = 3\nd = 4\ne = 5\n")], "alice@example.com", 1_000);
+        let format = fixture.commit(&[("lib.py", "a = 1,\nb = 2,\nc = 3\nnew = 0\nd = 4\ne = 5,\n")],
+            "formatter@example.com", 2_000);
+        let head = fixture.commit(&[("lib.py", "a = 1,\nb = 2,\nc = 3\nnew = 0\nd = 40\ne = 5,\n")],
+            "bob@example.com", 3_000);
+        let git_repo = GitRepo::open(fixture.path()).expect("fixture should open");
+        let blame_lines = |ignore_revs: &[String]| -> Vec<(usize, usize, String, usize)> {
+            let settings = BlameSettings::new(ignore_revs, None, None, None);
+            let ranges = git_repo.blame_ranges(&head.to_string(), "lib.py", &[(1, 6)], &settings,
+                &git_repo.ignored_commits(&settings)).expect("blame");
+            return ranges[0].iter().map(|range| (range.start_line, range.end_line,
+                range.line_item.author_name().to_string(), range.line_item.orig_line())).collect();
+        };
+        assert_eq!(blame_lines(&[]).iter().filter(|line| line.2 == "formatter").count(), 3);
+        assert_eq!(blame_lines(&[format.to_string()]), vec![
+            (1, 2, "alice".to_string(), 1),
+            (3, 3, "alice".to_string(), 3),
+            (5, 5, "bob".to_string(), 5),
+            (6, 6, "alice".to_string(), 5),
+        ]);
+
+        let hunks = vec![
+            DiffHunk { old_start: 1, old_lines: 2, new_start: 1, new_lines: 2, function_context: None },
+            DiffHunk { old_start: 3, old_lines: 0, new_start: 4, new_lines: 1, function_context: None },
+            DiffHunk { old_start: 6, old_lines: 1, new_start: 6, new_lines: 0, function_context: None },
+        ];
+        let parent_lines: Vec<Option<usize>> = (1..=7).map(|line| parent_line(&hunks, line)).collect();
+        assert_eq!(parent_lines, vec![Some(1), Some(2), Some(3), None, Some(4), Some(5), Some(7)]);
+    }
+
+    #[test]
+    fn renames_moves_and_copies_keep_their_author() {
+        let fixture = FixtureRepo::new("moves");
+        let parse = "def parse_config(path):\n    with open(path) as handle:\n        return json.load(handle)\n";
+        let (first, second) = ("def first_block():\n    return load_everything()\n",
+            "def second_block():\n    return save_everything()\n");
+        let base = fixture.commit(&[
+            ("app.py", &format!("def main():\n    pass\n{}", parse)),
+            ("config.py", "import json\n"),
+            ("blocks.py", &format!("{}{}", first, second)),
+            ("old_name.py", "one = 1\ntwo = 2\nthree = 3\nfour = 4\n"),
+        ], "alice@example.com", 1_000);
+        fixture.remove("old_name.py");
+        let head = fixture.commit(&[
+            ("app.py", "def main():\n    pass\n"),
+            ("config.py", &format!("import json\n{}", parse)),
+            ("blocks.py", &format!("{}{}", second, first)),
+            ("new_name.py", "one = 1\ntwo = 2\nthree = 3\nfour = 40\n"),
+        ], "bob@example.com", 2_000);
+        let git_repo = GitRepo::open(fixture.path()).expect("fixture should open");
+        let (base, head) = (base.to_string(), head.to_string());
+
+        let renamed = git_repo.diff(&base, &head, false, true, &["new_name.py", "old_name.py"]).expect("diff");
+        assert_eq!(renamed.iter().map(|diff| (diff.path.as_str(), diff.old_path.as_deref(), diff.status)).collect::<Vec<_>>(),
+            vec![("new_name.py", Some("old_name.py"), FileStatus::Renamed)]);
+        assert_eq!(git_repo.diff(&base, &head, false, false, &["new_name.py", "old_name.py"]).expect("diff").len(), 2);
+
+        let blame_lines = |filepath: &str, settings: &BlameSettings| -> Vec<(usize, usize, String, String)> {
+            let ranges = git_repo.blame_ranges(&head, filepath, &[(1, 4)], settings,
+                &git_repo.ignored_commits(settings)).expect("blame");
+            return ranges[0].iter().map(|range| (range.start_line, range.end_line,
+                range.line_item.author_name().to_string(), range.line_item.orig_path().to_string())).collect();
+        };
+        let line = |start: usize, end: usize, author: &str, path: &str| (start, end, author.to_string(), path.to_string());
+        let plain = BlameSettings::default();
+        let moves = BlameSettings::new(&[], None, Some(true), None);
+        let copies = BlameSettings::new(&[], None, None, Some(true));
+        assert_eq!(blame_lines("config.py", &plain), vec![line(1, 1, "alice", "config.py"), line(2, 4, "bob", "config.py")]);
+        assert_eq!(blame_lines("config.py", &moves), blame_lines("config.py", &plain));
+        assert_eq!(blame_lines("config.py", &copies), vec![line(1, 1, "alice", "config.py"), line(2, 4, "alice", "app.py")]);
+        assert!(blame_lines("blocks.py", &plain).iter().any(|blamed| blamed.2 == "bob"));
+        assert_eq!(blame_lines("blocks.py", &moves), vec![line(1, 2, "alice", "blocks.py"), line(3, 4, "alice", "blocks.py")]);
+        assert_eq!(blame_lines("new_name.py", &plain), vec![line(1, 3, "alice", "old_name.py"), line(4, 4, "bob", "new_name.py")]);
+    }
+}
@@ -5,6 +5,8 @@ use std::io::Read;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::db::encryption::{open_bytes, seal_bytes};
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GithubAuthInfo {
 	token: String,
@@ -39,18 +41,27 @@ impl GithubAuthInfo {
 			return None;
 		}
 		let mut file = file_res.expect("Uncaught error in file_res");
-		let mut contents = String::new();
-		let read_res = file.read_to_string(&mut contents);
+		let mut contents = Vec::<u8>::new();
+		let read_res = file.read_to_end(&mut contents);
 		if let Err(err) = read_res {
 			log::error!("[github_auth_info/load_from_file] Unable to read from file: {:?}", &err);
 			return None;
 		}
-		let auth_info_res = serde_json::from_str(&contents);
+		let opened_opt = open_bytes(&contents);
+		if opened_opt.is_none() {
+			log::error!("[github_auth_info/load_from_file] Unable to decrypt file contents");
+			return None;
+		}
+		let (plaintext, needs_reseal) = opened_opt.expect("Empty opened_opt");
+		let auth_info_res = serde_json::from_slice(&plaintext);
 		if let Err(err) = auth_info_res {
 			log::error!("[github_auth_info/load_from_file] Unable to parse file contents: {:?}", &err);
 			return None;            
 		}
 		let auth_info: GithubAuthInfo = auth_info_res.expect("Uncaught error in auth_info_res");
+		if needs_reseal {
+			auth_info.save_to_file();
+		}
 		Some(auth_info)
 	}
 
@@ -62,13 +73,20 @@ impl GithubAuthInfo {
 			return;
 		}
 		let json_str = json_str_res.expect("Uncaught error in json_str_res");
+		// Sealed like the credentials in the database when a key is configured
+		let sealed_opt = seal_bytes(json_str.as_bytes());
+		if sealed_opt.is_none() {
+			log::error!("[github_auth_info/save_to_file] Unable to encrypt auth info");
+			return;
+		}
+		let sealed = sealed_opt.expect("Empty sealed_opt");
 		let file_res = File::create(&PATH);
 		if let Err(err) = file_res {
 			log::error!("[github_auth_info/save_to_file] Unable to create/open file: {:?}", err);
 			return;
 		}
 		let mut file = file_res.expect("Uncaught error in file_res");
-		let write_res = file.write_all(json_str.as_bytes());
+		let write_res = file.write_all(&sealed);
 		if let Err(err) = write_res {
 			log::error!("[github_auth_info/save_to_file] Unable to write to file: {:?}", err);
 		}
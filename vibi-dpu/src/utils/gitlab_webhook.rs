@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Webhook {
+    id: String,
+    project_id: String,
+    created_at: String,
+    merge_requests_events: bool,
+    url: String,
+}
+
+impl Webhook {
+    // Constructor
+    pub fn new(
+        id: String,
+        project_id: String,
+        created_at: String,
+        merge_requests_events: bool,
+        url: String,
+    ) -> Self {
+        Self {
+            id,
+            project_id,
+            created_at,
+            merge_requests_events,
+            url,
+        }
+    }
+
+    // Public getter methods
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    pub fn merge_requests_events(&self) -> bool {
+        self.merge_requests_events
+    }
+}
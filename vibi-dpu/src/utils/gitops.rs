@@ -1,6 +1,10 @@
-use std::collections::HashMap;
-use std::process::Command;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::process::{Command, Output};
+use std::time::Instant;
 use std::str;
+use futures_util::{stream, StreamExt};
+use once_cell::sync::Lazy;
 use serde::Deserialize;
 use serde::Serialize;
 use sha256::digest;
@@ -10,70 +14,110 @@ use tokio::fs;
 use tokio::task;
 use std::io::ErrorKind;
 
-use super::hunk::BlameItem;
+use super::blame_settings::BlameSettings;
+use super::file_exclusion::{ExclusionReason, FileExclusionRules, GitAttributes};
+use super::hunk::{BlameItem, LineKind};
 use super::review::Review;
-use super::lineitem::LineItem;
+use super::git_repo::{BlameRange, DiffHunk, FileDiff, FileStatus, GitRepo};
 use crate::db::repo::save_repo_to_db;
+use crate::graph::code_parser::ParsedFile;
+use crate::graph::utils::detect_language;
+use crate::metrics::registry::{observe_git_command, record_blame_lines};
 use crate::utils::repo::Repository;
 
+const DEFAULT_BLAME_WORKERS: usize = 4;
+
 #[derive(Debug, Serialize, Default, Deserialize, Clone)]
 pub struct StatItem {
 	pub filepath: String,
 	additions: i32,
 	deletions: i32,
+	#[serde(default)]
+	binary: bool,
+	#[serde(default)]
+	exclusion: Option<ExclusionReason>,
+	#[serde(default)]
+	old_filepath: Option<String>,
 }
 
-pub fn commit_exists(commit: &str, directory: &str) -> bool {
-	let output_res = Command::new("git")
-		.arg("rev-list")
-		.arg(commit)
-		.current_dir(directory)
-		.output();
-	if output_res.is_err() {
-		let e = output_res.expect_err("No error in output_res");
-		log::debug!("[commit_exists] Failed to start git rev-list: {:?}", e);
-		return false;
-	}
-	let output = output_res.expect("Uncaught error in output_res");
-	if !output.status.success() {
-		log::debug!("[commit_exists] git rev-list, exit code: {:?}",
-			output.status.code());
-		// for debugging
-		match str::from_utf8(&output.stderr) {
-			Ok(v) => log::error!("[commit_exists] git rev-list stderr = {:?}", v),
-			Err(e) => {/* error handling */ log::error!("[commit_exists] git rev-list stderr error {}", e)}, 
+impl StatItem {
+	pub fn new(filepath: &str, additions: i32, deletions: i32, binary: bool) -> Self {
+		return Self {
+			filepath: filepath.to_string(),
+			additions,
+			deletions,
+			binary,
+			exclusion: None,
+			old_filepath: None,
 		};
-		return false;
 	}
-	log::debug!("[commit_exists] Execute git rev-list, exit code: {:?}", output.status.code());
-	if output.status.code() == Some(128) {
-		// for debugging
-		match str::from_utf8(&output.stderr) {
-			Ok(v) => log::error!("[commit_exists] git rev-list stderr = {:?}", v),
-			Err(e) => {/* error handling */ log::error!("[commit_exists] git rev-list stderr error {}", e)}, 
-		};
+
+	pub fn from_file_diff(file_diff: &FileDiff) -> Self {
+		let mut item = Self::new(&file_diff.path, file_diff.additions as i32,
+			file_diff.deletions as i32, file_diff.binary);
+		item.old_filepath = file_diff.old_path.clone();
+		return item;
+	}
+
+	// Path in the PR's base commit, differs from filepath for renamed files
+	pub fn base_filepath(&self) -> &str {
+		return self.old_filepath.as_deref().unwrap_or(&self.filepath);
+	}
+
+	pub fn changed_lines(&self) -> i32 {
+		return self.additions + self.deletions;
+	}
+
+	pub fn is_binary(&self) -> bool {
+		return self.binary;
+	}
+
+	// Why the file was left out of blame, None for included files
+	pub fn exclusion(&self) -> Option<ExclusionReason> {
+		return self.exclusion;
+	}
+
+	pub fn set_exclusion(&mut self, exclusion: Option<ExclusionReason>) {
+		self.exclusion = exclusion;
+	}
+}
+
+// Runs a git command, recording its duration under the git subcommand
+pub fn git_output(command: &mut Command) -> std::io::Result<Output> {
+	let subcommand = command.get_args().next()
+		.map(|arg| arg.to_string_lossy().to_string())
+		.unwrap_or_default();
+	let start = Instant::now();
+	let output_res = command.output();
+	observe_git_command(&subcommand, start.elapsed());
+	return output_res;
+}
+
+pub fn commit_exists(commit: &str, directory: &str) -> bool {
+	let git_repo_opt = GitRepo::open(directory);
+	if git_repo_opt.is_none() {
 		return false;
 	}
-	// for debugging
-	match str::from_utf8(&output.stderr) {
-		Ok(v) => log::debug!("[commit_exists] git rev-list stderr = {:?}", v),
-		Err(e) => {/* error handling */ log::error!("[commit_exists] git rev-list stderr error {}", e)}, 
-	};
-	match str::from_utf8(&output.stdout) {
-		Ok(v) => log::debug!("[commit_exists] git rev-list stdout = {:?}", v),
-		Err(e) => {/* error handling */ log::error!("[commit_exists] git rev-list stdout error {}", e)}, 
-	};
-	return true;
+	return git_repo_opt.expect("Empty git_repo_opt").commit_exists(commit);
+}
+
+// One lock per clone dir, held while fetching so reviews of the same repo
+// don't pull into it at the same time
+static REPO_LOCKS: Lazy<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+	Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn repo_lock(clone_dir: &str) -> Arc<tokio::sync::Mutex<()>> {
+	let mut locks = REPO_LOCKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+	return locks.entry(clone_dir.to_string()).or_default().clone();
 }
 
 pub async fn git_pull(review: &Review, access_token: &str) {
 	let directory = review.clone_dir();
 	log::debug!("[git_pull] directory = {}", &directory);
     set_git_url(review.clone_url(), directory, &access_token, review.provider());
-	let output_res = Command::new("git")
+	let output_res = git_output(Command::new("git")
 		.arg("pull")
-		.current_dir(directory)
-		.output();
+		.current_dir(directory));
 	if output_res.is_err() {
 		let e = output_res.expect_err("No error in output_res");
 		log::error!("[git_pull] failed to execute git pull: {:?}", e);
@@ -91,440 +135,267 @@ pub async fn git_pull(review: &Review, access_token: &str) {
 }
 
 pub fn git_checkout_commit(review: &Review, commit_id: &str) {
-	let directory = review.clone_dir();
-	let output_res = Command::new("git")
-		.arg("checkout")
-		.arg(commit_id)
-		.current_dir(directory)
-		.output();
-	if output_res.is_err() {
-		let e = output_res.expect_err("No error in output_res");
-		log::error!("[git_checkout_commit] failed to execute git checkout: {:?}", e);
+	let git_repo_opt = GitRepo::open(review.clone_dir());
+	if git_repo_opt.is_none() {
+		log::error!("[git_checkout_commit] Unable to open repo at {}", review.clone_dir());
 		return;
 	}
-	let output = output_res.expect("Uncaught error in output_res");
-	match str::from_utf8(&output.stderr) {
-		Ok(v) => log::debug!("[git_checkout_commit] git checkout stderr = {:?}", v),
-		Err(e) => {/* error handling */ log::error!("[git_checkout_commit] git checkout stderr error {}", e)}, 
-	};
-	match str::from_utf8(&output.stdout) {
-		Ok(v) => log::debug!("[git_checkout_commit] git checkout stdout = {:?}", v),
-		Err(e) => {/* error handling */ log::error!("[git_checkout_commit] git checkout stdout error {}", e)}, 
-	};
+	git_repo_opt.expect("Empty git_repo_opt").checkout(commit_id);
 }
 
 fn set_git_url(git_url: &str, directory: &str, access_token: &str, repo_provider: &str) {
-    let clone_url_opt = create_clone_url(git_url, access_token, repo_provider);
+	let clone_url_opt = create_clone_url(git_url, access_token, repo_provider);
 	if clone_url_opt.is_none(){
 		return
 	}
 	let clone_url = clone_url_opt.expect("empty clone_url_opt");
-    let output_res = Command::new("git")
-		.arg("remote").arg("set-url").arg("origin")
-		.arg(clone_url)
-		.current_dir(directory)
-		.output();
-	if output_res.is_err() {
-		let e = output_res.expect_err("No error in output_res");
-		log::error!("[set_git_url] failed to execute set_git_url: {:?}", e);
+	let git_repo_opt = GitRepo::open(directory);
+	if git_repo_opt.is_none() {
+		log::error!("[set_git_url] Unable to open repo at {}", directory);
 		return;
 	}
-	let output = output_res.expect("Uncaught error in output_res");
-	if !output.status.success() {
-		log::error!("[set_git_url] set_git_url failed with exit code: {}", output.status);
-		return;
-	}
-	match str::from_utf8(&output.stderr) {
-		Ok(v) => log::debug!("[set_git_url] set_git_url stderr = {:?}", v),
-		Err(e) => {/* error handling */ log::error!("[set_git_url] stderr error {}", e)}, 
-	};
-	match str::from_utf8(&output.stdout) {
-		Ok(v) => log::debug!("[set_git_url] stdout = {:?}", v),
-		Err(e) => {/* error handling */ log::error!("[set_git_url] stdout error {}", e)}, 
-	};
-	log::debug!("[set_git_url] set_git_url output = {:?}, {:?}", &output.stdout, &output.stderr);
+	git_repo_opt.expect("Empty git_repo_opt").set_remote_url("origin", &clone_url);
+}
+
+// Contents of a file as of commit, None if it doesn't exist there
+pub fn file_at_commit(clone_dir: &str, commit: &str, filepath: &str) -> Option<String> {
+	let git_repo = GitRepo::open(clone_dir)?;
+	return git_repo.file_at_commit(commit, filepath);
 }
 
-pub fn get_excluded_files(review: &Review) -> Option<(Vec<StatItem>, Vec<StatItem>)> {
+pub fn get_excluded_files(review: &Review, rules: &FileExclusionRules, blame_settings: &BlameSettings) -> Option<(Vec<StatItem>, Vec<StatItem>)> {
 	let prev_commit = review.base_head_commit();
 	let next_commit = review.pr_head_commit();
 	let clone_dir = review.clone_dir();
 	log::debug!("[get_excluded_files] prev_commit = {}, next commit = {}, clone_dir = {}",
 		prev_commit, next_commit, clone_dir);
-	let commit_range = format!("{}...{}", prev_commit, next_commit);
-	let git_res = Command::new("git")
-		.args(&["diff", &commit_range, "--numstat"])
-		.current_dir(clone_dir)
-		.output();
-	if git_res.is_err() {
-		let commanderr = git_res.expect_err("No error in git command");
-		log::error!("[get_excluded_files] git diff stat command failed to start : {:?}", commanderr);
+	let git_repo_opt = GitRepo::open(clone_dir);
+	if git_repo_opt.is_none() {
+		log::error!("[get_excluded_files] Unable to open repo, review: {:#?}", review);
 		return None;
 	}
-	let resultstat = git_res.expect("Uncaught error in git_res");
-	let stat = resultstat.stdout;
-	// parse the output
-	let stat_res = str::from_utf8(&stat);
-	if stat_res.is_err() {
-		let staterr = stat_res.expect_err("No error in git command");
-		log::error!("[get_excluded_files] git diff stat command parse error : {:?}, review: {:#?}", staterr, review);
+	let git_repo = git_repo_opt.expect("Empty git_repo_opt");
+	let file_diffs_opt = git_repo.diff(prev_commit, next_commit, false, blame_settings.detect_renames(), &[]);
+	if file_diffs_opt.is_none() {
+		log::error!("[get_excluded_files] Unable to diff {}...{}", prev_commit, next_commit);
 		return None;
 	}
-	let statstr = stat_res.expect("Uncaught error in stat_res");
-	log::debug!("[get_excluded_files] statstr = {}", statstr);
-	return process_statoutput(statstr);
-}
-
-fn process_statoutput(statstr: &str) -> Option<(Vec<StatItem>, Vec<StatItem>)>{
-    let statvec = process_statitems(statstr);
-    let mut excluded_files = Vec::<StatItem>::new();
-    let mut filtered_files = Vec::<StatItem>::new();
-    let line_threshold = 5500;
-    for item in statvec {
-        // logic for exclusion
-        if (item.additions > line_threshold) || 
-        (item.deletions > line_threshold) || 
-        (item.additions + item.deletions > line_threshold) ||
-		(item.deletions < 1) {
-            excluded_files.push(item);
-        }
-        else {
-            filtered_files.push(item);
-        }
-    }
-    return Some((excluded_files, filtered_files));
-}
-
-fn generate_statitem(statitems: &Vec<&str>) -> StatItem {
-	let statitem = StatItem {
-		filepath: statitems[2].to_string(),
-		additions: match statitems[0].to_string().parse() {
-			Ok(adds) => {adds}
-			Err(e) => {
-				log::error!("[generate_statitem] Unable to parse additions: {:?}", e);
-				0 // default value
-			}
-		},
-		deletions: match statitems[1].to_string().parse() {
-			Ok(dels) => {dels}
-			Err(e) => {
-				log::error!("[generate_statitem] Unable to parse deletions: {:?}", e);
-				0 // default value
-			}
-		},
+	let statvec: Vec<StatItem> = file_diffs_opt.expect("Empty file_diffs_opt")
+		.iter().map(StatItem::from_file_diff).collect();
+	log::debug!("[get_excluded_files] statvec = {:?}", &statvec);
+	let attributes = match rules.reads_gitattributes() {
+		true => GitAttributes::for_review(review),
+		false => GitAttributes::default(),
 	};
-	return statitem;
+	return Some(rules.partition(statvec, &attributes));
 }
 
-fn process_statitem(line: &str) -> Option<StatItem> {
-	let statitems: Vec<&str> = line.split("\t").collect();
-	if statitems.len() >= 3 {
-		let statitem = generate_statitem(&statitems);
-		return Some(statitem);
+// Hunks of each file, keyed by its path in the base commit where it's blamed
+pub fn generate_diff(review: &Review, smallfiles: &Vec<StatItem>, blame_settings: &BlameSettings) -> HashMap<String, Vec<DiffHunk>> {
+	let mut diffmap = HashMap::<String, Vec<DiffHunk>>::new();
+	if smallfiles.is_empty() {
+		return diffmap;
 	}
-	return None;
-}
-
-fn process_statitems(statstr: &str) -> Vec<StatItem> {
-    let statlines = statstr.split("\n");
-    let mut statvec = Vec::<StatItem>::new();
-    for line in statlines {
-		let statitem_opt = process_statitem(line);
-		if statitem_opt.is_none() {
-			continue;
-		}
-		let statitem = statitem_opt.expect("statitem is empty");
-		statvec.push(statitem);
+	let git_repo_opt = GitRepo::open(review.clone_dir());
+	if git_repo_opt.is_none() {
+		log::error!("[generate_diff] Unable to open repo at {}", review.clone_dir());
+		return diffmap;
 	}
-    return statvec;
-}
-
-pub fn generate_diff(review: &Review, smallfiles: &Vec<StatItem>) -> HashMap<String, String> {
-	let mut diffmap = HashMap::<String, String>::new();
-	let prev_commit = review.base_head_commit();
-	let curr_commit = review.pr_head_commit();
-	let clone_dir = review.clone_dir();
-	for item in smallfiles {
-		let filepath = item.filepath.as_str();
-		let commit_range = format!("{}...{}", prev_commit, curr_commit);
-		log::debug!("[generate_diff] | clone_dir = {:?}, filepath = {:?}", clone_dir, filepath);
-		let output_res = Command::new("git")
-			.arg("diff")
-			.arg("-U0")
-			.arg("--ignore-space-change")
-			.arg(&commit_range)
-			.arg(&filepath)
-			.current_dir(clone_dir)
-			.output();
-		if output_res.is_err() {
-			let commanderr = output_res.expect_err("No error in output_res");
-			log::error!("[generate_diff] git diff command failed to start : {:?}", commanderr);
-			continue;
-		}
-		let result = output_res.expect("Uncaught error in output_res");
-		let diff = result.stdout;
-		let diffstr_res = str::from_utf8(&diff);
-		if diffstr_res.is_err() {
-			let e = diffstr_res.expect_err("No error in diffstr_res");
-			log::error!("[generate_diff] Unable to deserialize diff: {:?}", e);
-			continue;
-		}
-		let diffstr = diffstr_res.expect("Uncaught error in diffstr_res");
-		log::debug!("[generate_diff] diffstr = {}", &diffstr);
-		diffmap.insert(filepath.to_string(), diffstr.to_string());
+	let git_repo = git_repo_opt.expect("Empty git_repo_opt");
+	// Both sides of a rename have to be in the pathspec for the diff to pair them
+	let paths: Vec<&str> = smallfiles.iter()
+		.flat_map(|item| [item.filepath.as_str(), item.base_filepath()])
+		.collect();
+	let file_diffs_opt = git_repo.diff(review.base_head_commit(), review.pr_head_commit(), true,
+		blame_settings.detect_renames(), &paths);
+	if file_diffs_opt.is_none() {
+		log::error!("[generate_diff] Unable to diff files: {:?}", &paths);
+		return diffmap;
+	}
+	for file_diff in file_diffs_opt.expect("Empty file_diffs_opt") {
+		diffmap.insert(file_diff.old_path.unwrap_or(file_diff.path), file_diff.hunks);
 	}
 	return diffmap;
 }
 
-fn process_diff(filepath: &str, diff: &str, linemap: &mut HashMap<String, Vec<String>> ) -> HashMap<String, Vec<String>> {
-	let mut limiterpos = Vec::new();
-	let delimitter = "@@";
-	for (idx, _) in diff.match_indices(delimitter) {
-		if has_deletions(&diff[idx..]) {
-            limiterpos.push(idx);
-        }
+// Line ranges of the base version to blame: the lines each hunk removes or
+// rewrites, and if the settings ask for them the lines around pure insertions
+// and the rest of the enclosing functions. A line gets the first of these
+// kinds that applies. Reading and parsing base files runs on a blocking thread.
+pub async fn process_diffmap(review: &Review, diffmap: &HashMap<String, Vec<DiffHunk>>,
+	blame_settings: &BlameSettings) -> HashMap<String, Vec<(usize, usize, LineKind)>> {
+	let review = review.clone();
+	let diffmap = diffmap.clone();
+	let blame_settings = blame_settings.clone();
+	let linemap_res = task::spawn_blocking(move || diffmap_lines(&review, &diffmap, &blame_settings)).await;
+	if let Err(e) = &linemap_res {
+		log::error!("[process_diffmap] Line range task failed: {:?}", e);
+		return HashMap::new();
 	}
-	let mut idx: usize = 0;
-	let len = limiterpos.len();
-	while (idx + 1) < len {
-		let line_res = diff.get(
-			(limiterpos[idx]+delimitter.len())..limiterpos[idx+1]
-		);
-		if line_res.is_none() {
-			log::error!("[process_diff] Unable to format diff line");
-			continue;
-		}
-		let line = line_res.expect("Empty line_res");
-		let sublines: Vec<&str> = line.split(" ").collect();
-		if line.contains("\n") || sublines.len() != 4 {
-			idx += 1;
-			continue;
+	return linemap_res.expect("Uncaught error in linemap_res");
+}
+
+fn diffmap_lines(review: &Review, diffmap: &HashMap<String, Vec<DiffHunk>>,
+	blame_settings: &BlameSettings) -> HashMap<String, Vec<(usize, usize, LineKind)>> {
+	let mut linemap: HashMap<String, Vec<(usize, usize, LineKind)>> = HashMap::new();
+	let surrounding = blame_settings.context_lines() > 0 || blame_settings.enclosing_function();
+	for (filepath, hunks) in diffmap {
+		let mut line_kinds = BTreeMap::<usize, LineKind>::new();
+		for (start_line, end_line) in hunks.iter().filter_map(|hunk| hunk.deleted_range()) {
+			for line in start_line..=end_line {
+				line_kinds.insert(line, LineKind::Changed);
+			}
 		}
-		let mut deletionstr = sublines[1].to_owned();
-		// let additionstr = sublines[1];
-		if deletionstr.contains("-") {
-			deletionstr = deletionstr.replace("-", "");
-			if deletionstr.contains(",") {
-				let delsplit: Vec<&str> = deletionstr.split(",").collect();
-				let delidx_res = delsplit[0].parse::<i32>();
-				let deldiff_res = delsplit[1].parse::<i32>();
-				if delidx_res.is_err() || deldiff_res.is_err() {
-					log::error!("[process_diff] Unable to parse delidx_res or deldiff_res: {:?} {:?}",
-						delidx_res, deldiff_res);
-					continue;
+		let base_contents_opt = match surrounding {
+			true => file_at_commit(review.clone_dir(), review.base_head_commit(), filepath),
+			false => None,
+		};
+		if let Some(base_contents) = base_contents_opt {
+			for (start_line, end_line, kind) in surrounding_ranges(filepath, &base_contents, hunks, blame_settings) {
+				for line in start_line..=end_line {
+					line_kinds.entry(line).or_insert(kind);
 				}
-				let delidx = delidx_res.expect("Uncaught error in delidx_res");
-				let deldiff = deldiff_res.expect("Uncaught error in deldiff_res");
-				deletionstr = format!("{delidx},{}", delidx+deldiff);
 			}
-			else {
-				let delidx_res = deletionstr.parse::<i32>();
-				if delidx_res.is_err() {
-					log::error!("[process_diff] Unable to parse delidx_res {:?}",
-						delidx_res);
+		}
+		let mut ranges = Vec::<(usize, usize, LineKind)>::new();
+		for (line, kind) in line_kinds {
+			if let Some(last) = ranges.last_mut() {
+				if last.1 + 1 == line && last.2 == kind {
+					last.1 = line;
 					continue;
 				}
-				let delidx = delidx_res.expect("Uncaught error in delidx_res");
-				deletionstr.push_str(format!(",{}", delidx).as_str());
 			}
+			ranges.push((line, line, kind));
 		}
-		else {
-			idx += 1;
+		if ranges.is_empty() {
 			continue;
 		}
-		if linemap.contains_key(filepath) {
-			let linemap_mut_res = linemap.get_mut(filepath);//.unwrap().push(deletionstr);
-			if linemap_mut_res.is_none() {
-				log::error!("[process_diff] Unable to get mutable ref for linemap: {:?}", linemap);
-				continue;
-			}
-			let linemap_mut = linemap_mut_res.expect("Empty linemap_mut_res");
-			linemap_mut.push(deletionstr);
-		}
-		else {
-			linemap.insert(filepath.to_string(), vec!(deletionstr));
-		}
-		idx += 1;
-	}
-	return linemap.to_owned();
-}
-
-fn has_deletions(hunk: &str) -> bool {
-    // Split the hunk into lines
-    let lines = hunk.split('\n').collect::<Vec<&str>>();
-    
-    // Iterate through the lines to check for deletions
-    for line in lines.iter() {
-        if line.starts_with('-') && !line.starts_with("---") {
-            return true;
-        }
-    }
-    
-    return false;
-}
-
-pub fn process_diffmap(diffmap: &HashMap<String, String>) -> HashMap<String, Vec<String>> {
-	let mut linemap: HashMap<String, Vec<String>> = HashMap::new();
-	for (filepath, diff) in diffmap {
-		linemap = process_diff(filepath, diff, &mut linemap);
+		linemap.insert(filepath.to_string(), ranges);
 	}
 	return linemap;
 }
 
-pub async fn generate_blame(review: &Review, linemap: &HashMap<String, Vec<String>>) ->  Vec<BlameItem>{
-	let mut blamevec = Vec::<BlameItem>::new();
-	let commit = review.base_head_commit();
-	let clone_dir = review.clone_dir();
-	for (path, linevec) in linemap {
-		for line in linevec {
-			let linenumvec: Vec<&str> = line.split(",").collect();
-			let linenum = linenumvec[0];
-			let paramvec: Vec<&str> = vec!(
-				"blame",
-				commit,
-				"-L",
-				line.as_str(),
-				"-w",
-				"-e",
-				"--date=unix",
-				"-l",
-				path.as_str(),
-			);
-			let blame_res = Command::new("git").args(paramvec)
-				.current_dir(clone_dir)
-				.output();
-			if blame_res.is_err() {
-				let e = blame_res.expect_err("No error in blame_res");
-				log::error!("[generate_blame] git blame command failed to start : {e}");
-				continue;
-			}
-			let blame_output = blame_res.expect("Uncaught error in blame_res");
-			if !blame_output.status.success() {
-                log::error!("[generate_blame] git blame command failed with exit code {:?} and error: {:?}",
-					blame_output.status.code(), String::from_utf8_lossy(&blame_output.stderr));
-                continue;
-            }
-			let blame = blame_output.stdout;
-			let parse_res = str::from_utf8(&blame);
-			if parse_res.is_err() {
-				let e = parse_res.expect_err("No error in parse_res");
-				log::error!("[generate_blame] Unable to deserialize blame: {e}");
-			}
-			let blamestr = parse_res.expect("Uncaught error in parse_res");
-			log::debug!("[generate_blame] blamestr = {}", blamestr);
-			let blamelines: Vec<&str> = blamestr.lines().collect();
-			if blamelines.len() == 0 {
-				continue;
-			}
-			let blamitems_opt = process_blameitem(path, linenum, blamelines).await;
-			if blamitems_opt.is_some() {
-				let blameitems = blamitems_opt.expect("blameitem not found in blameitem_opt");
-				blamevec.extend(blameitems);
+// Context lines around pure insertions and the functions enclosing each hunk,
+// within the base file. Functions are only found in languages with a grammar.
+fn surrounding_ranges(filepath: &str, base_contents: &str, hunks: &[DiffHunk],
+	blame_settings: &BlameSettings) -> Vec<(usize, usize, LineKind)> {
+	let mut ranges = Vec::<(usize, usize, LineKind)>::new();
+	let line_count = base_contents.lines().count();
+	let context_lines = blame_settings.context_lines();
+	if context_lines > 0 {
+		// A pure insertion's old_start is the base line it follows
+		for hunk in hunks.iter().filter(|hunk| hunk.old_lines == 0) {
+			let start_line = (hunk.old_start + 1).saturating_sub(context_lines).max(1);
+			let end_line = (hunk.old_start + context_lines).min(line_count);
+			if start_line <= end_line {
+				ranges.push((start_line, end_line, LineKind::Context));
 			}
 		}
 	}
-	return blamevec;
-}
-
-async fn process_blameitem(path: &str, linenum: &str, blamelines: Vec<&str>) -> Option<Vec<BlameItem>> {
-	let linenumint_res = linenum.parse::<usize>();
-	let mut blamevec = Vec::<BlameItem>::new();
-	if linenumint_res.is_err() {
-		let e = linenumint_res.expect_err("No error found in linenumint_res");
-		log::error!("[generate_blame] Unable to parse linenum : {:?}", e);
-		return None;
+	if !blame_settings.enclosing_function() {
+		return ranges;
 	}
-	let linenumint = linenumint_res.expect("Uncaught error in linenumint_res");
-	let lineauthormap = process_blamelines(&blamelines, linenumint).await;
-	let mut linebreak = linenumint;
-	for lidx in linenumint..(linenumint + blamelines.len()-1) {
-		if lineauthormap.contains_key(&lidx) && lineauthormap.contains_key(&(lidx+1)) {
-			let lineitem = lineauthormap.get(&lidx).expect("lidx checked");
-			if lineitem.author_id() == 
-			lineauthormap.get(&(lidx+1)).expect("lidx+1 checked").author_id() {
-				continue;
-			}
-			else {
-				blamevec.push(BlameItem::new(
-					lineitem.author_id().to_string(),
-					lineitem.timestamp().to_string(),
-					linebreak.to_string(),
-					lidx.to_string(),
-					digest(path),
-					lineitem.commit().trim_matches('"').to_string(),
-					path.to_string())
-				);
-				linebreak = lidx + 1;
+	let parsed_file_opt = detect_language(filepath)
+		.and_then(|lang| ParsedFile::from_source(base_contents.to_string(), &lang));
+	if parsed_file_opt.is_none() {
+		return ranges;
+	}
+	let parsed_file = parsed_file_opt.expect("Empty parsed_file_opt");
+	for hunk in hunks {
+		// Parser rows are zero based. An insertion has to fall inside the
+		// function, not right after its last line.
+		let (first_row, last_row) = match hunk.deleted_range() {
+			Some((start_line, end_line)) => (start_line - 1, end_line - 1),
+			None if hunk.old_start > 0 => (hunk.old_start - 1, hunk.old_start),
+			None => continue,
+		};
+		for row in [first_row, last_row] {
+			if let Some(func_def) = parsed_file.enclosing_function(row) {
+				if *func_def.line_start() <= first_row && last_row <= *func_def.line_end() {
+					ranges.push((func_def.line_start() + 1, (func_def.line_end() + 1).min(line_count), LineKind::Function));
+				}
 			}
 		}
 	}
-	let lastidx = linenumint + blamelines.len()-1;
-	if lineauthormap.contains_key(&lastidx) {
-		let lineitem = lineauthormap.get(&lastidx).expect("lastidx checked");
-		blamevec.push(BlameItem::new(
-			lineitem.author_id().to_string(),
-			lineitem.timestamp().to_string(),
-			linebreak.to_string(),
-			lastidx.to_string(),
-			digest(path),
-			lineitem.commit().to_string(),
-			path.to_string(),
-		));
-	}
-	return Some(blamevec);
+	return ranges;
 }
 
-async fn process_blamelines(blamelines: &Vec<&str>, linenum: usize) -> HashMap<usize, LineItem> {
-	let mut linemap = HashMap::<usize, LineItem>::new();
-	for lnum  in 0..blamelines.len() {
-		let blame_line = blamelines[lnum];
-		let blame_line_words: Vec<&str> = blame_line.split(" ").collect();
-		let commit = blame_line_words[0].to_string();
-		let (author, idx) = extract_author(&blame_line_words);
-		let timestamp = extract_timestamp(&blame_line_words, idx);
-		let lineitem = LineItem::new(author, timestamp, commit);
-		linemap.insert(
-			linenum + lnum,
-			lineitem
-		);
-	}
-	return linemap;
+// Files blamed at once, each on its own blocking thread
+fn blame_workers() -> usize {
+	return std::env::var("DPU_BLAME_WORKERS")
+		.ok()
+		.and_then(|value| value.parse::<usize>().ok())
+		.filter(|workers| *workers > 0)
+		.unwrap_or(DEFAULT_BLAME_WORKERS);
 }
 
-fn extract_author(blame_line_words: &Vec<&str>) -> (String, usize) {
-	let mut author = blame_line_words[1];
-	let mut idx = 1;
-	// Check if the second value is an email address (enclosed in angle brackets)
-	if !author.starts_with('(') && !author.ends_with('>') {
-		// Shift the index to the next non-empty value
-		while idx < blame_line_words.len() && (blame_line_words[idx] == "" || !blame_line_words[idx].starts_with('(')){
-			idx += 1;
+pub async fn generate_blame(review: &Review, linemap: &HashMap<String, Vec<(usize, usize, LineKind)>>, blame_settings: &BlameSettings) ->  Vec<BlameItem>{
+	let mut blamevec = Vec::<BlameItem>::new();
+	let mut files: Vec<_> = linemap.iter()
+		.map(|(path, linevec)| (path.to_string(), linevec.to_vec()))
+		.collect();
+	files.sort_by(|(path, _), (other_path, _)| path.cmp(other_path));
+	// Resolved once here rather than in every file's task
+	let ignored = GitRepo::open(review.clone_dir())
+		.map(|git_repo| git_repo.ignored_commits(blame_settings))
+		.unwrap_or_default();
+	let blame_tasks = files.into_iter().map(|(path, linevec)| {
+		let clone_dir = review.clone_dir().to_string();
+		let commit = review.base_head_commit().to_string();
+		let blame_settings = blame_settings.clone();
+		let ignored = ignored.clone();
+		// git2 repositories can't be shared across threads, each task opens its own
+		return task::spawn_blocking(move || {
+			let line_ranges: Vec<(usize, usize)> = linevec.iter()
+				.map(|(start_line, end_line, _)| (*start_line, *end_line))
+				.collect();
+			let ranges_opt = GitRepo::open(&clone_dir)
+				.and_then(|git_repo| git_repo.blame_ranges(&commit, &path, &line_ranges, &blame_settings, &ignored));
+			return (path, linevec, ranges_opt);
+		});
+	});
+	let mut blame_results = stream::iter(blame_tasks).buffered(blame_workers());
+	while let Some(blame_res) = blame_results.next().await {
+		if let Err(e) = &blame_res {
+			log::error!("[generate_blame] Blame task failed: {:?}", e);
+			continue;
+		}
+		let (path, linevec, ranges_opt) = blame_res.expect("Uncaught error in blame_res");
+		if ranges_opt.is_none() {
+			continue;
 		}
-		if idx < blame_line_words.len() {
-			author = blame_line_words[idx];
+		let ranges = ranges_opt.expect("Empty ranges_opt");
+		for ((start_line, end_line, kind), line_ranges) in linevec.iter().zip(ranges) {
+			record_blame_lines(end_line - start_line + 1);
+			blamevec.extend(process_blameitem(&path, line_ranges).into_iter()
+				.map(|blame_item| blame_item.with_line_kind(*kind)));
 		}
-	} else {
-		// Remove the angle brackets from the email address
-		author = author.trim_start_matches('<').trim_end_matches('>');
-	}
-	let authorstr = author.replace("(", "")
-		.replace("<", "")
-		.replace(">", "");
-	return (authorstr, idx)
+	}
+	return blamevec;
 }
 
-fn extract_timestamp(wordvec: &Vec<&str>, mut idx: usize) -> String {
-	let mut timestamp = wordvec[2];
-	if timestamp == "" || timestamp.starts_with('(') {
-		idx = idx + 1;
-		while idx < wordvec.len() && (wordvec[idx] == "" || wordvec[idx].starts_with('(')) {
-			idx = idx + 1;
-		}
-		if idx < wordvec.len() {
-			timestamp = wordvec[idx];
+// Merges neighbouring blame ranges of the same author into one BlameItem,
+// which keeps the commit and original lines of the first range
+fn process_blameitem(path: &str, ranges: Vec<BlameRange>) -> Vec<BlameItem> {
+	let mut blamevec = Vec::<BlameItem>::new();
+	// (first range, end line of the merged ranges)
+	let mut merged: Vec<(BlameRange, usize)> = Vec::new();
+	for range in ranges {
+		if let Some((first, end_line)) = merged.last_mut() {
+			if first.line_item.author_id() == range.line_item.author_id()
+				&& *end_line + 1 == range.start_line {
+				*end_line = range.end_line;
+				continue;
+			}
 		}
+		let end_line = range.end_line;
+		merged.push((range, end_line));
+	}
+	for (first, end_line) in merged {
+		blamevec.push(BlameItem::new(&first.line_item, first.start_line, first.end_line,
+			digest(path), path.to_string()).with_line_end(end_line));
 	}
-	return timestamp.to_string();
+	return blamevec;
 }
 
 pub fn create_clone_url(git_url: &str, access_token: &str, repo_provider: &str) -> Option<String> {
@@ -538,6 +409,11 @@ pub fn create_clone_url(git_url: &str, access_token: &str, repo_provider: &str)
 		clone_url = Some(git_url.to_string()
 			.replace("git@", format!("https://x-token-auth:{{{access_token}}}@").as_str())
 			.replace("bitbucket.org:", "bitbucket.org/"));
+	} else if repo_provider == "gitlab" {
+		// git@host:group/project.git, host may be self-managed
+		clone_url = git_url.strip_prefix("git@")
+			.and_then(|url| url.split_once(':'))
+			.map(|(host, path)| format!("https://oauth2:{access_token}@{host}/{path}"));
 	}
 	log::debug!("[create_clone_url] clone URL: {:?}", clone_url);
 	return clone_url;
@@ -551,26 +427,12 @@ pub fn set_git_remote_url(review: &Review, access_token: &str, repo_provider: &s
         return;
     }
     let clone_url = clone_url_opt.expect("empty clone_url_opt");
-    let output_res = Command::new("git")
-		.arg("remote").arg("set-url").arg("origin")
-		.arg(clone_url)
-		.current_dir(review.clone_dir())
-		.output();
-	if let Err(e) = output_res {
-		log::error!("[set_git_remote_url] Unable to execute git pull: {:?}", e);
+	let git_repo_opt = GitRepo::open(review.clone_dir());
+	if git_repo_opt.is_none() {
+		log::error!("[set_git_remote_url] Unable to open repo at {}", review.clone_dir());
 		return;
 	}
-	let output = output_res.expect("Uncaught error in output_res");
-    // Only for debug purposes
-	match str::from_utf8(&output.stderr) {
-		Ok(v) => log::debug!("[set_git_remote_url] stderr = {:?}", v),
-		Err(e) => log::error!("[set_git_remote_url] stderr error: {}", e), 
-	};
-	match str::from_utf8(&output.stdout) {
-		Ok(v) => log::debug!("[set_git_remote_url] stdout = {:?}", v),
-		Err(e) => log::error!("[set_git_remote_url] stdout error: {}", e), 
-	};
-	log::debug!("[set_git_remote_url] git pull output = {:?}, {:?}", &output.stdout, &output.stderr);
+	git_repo_opt.expect("Empty git_repo_opt").set_remote_url("origin", &clone_url);
 }
 
 pub async fn clone_git_repo(repo: &mut Repository, access_token: &str, repo_provider: &str) {
@@ -623,7 +485,7 @@ pub async fn clone_git_repo(repo: &mut Repository, access_token: &str, repo_prov
     log::debug!("[clone_git_repo] directory exists? {}", fs::metadata(&directory).await.is_ok());
     let mut cmd = std::process::Command::new("git");
     cmd.arg("clone").arg(clone_url).current_dir(&directory);
-    let output_res = cmd.output();
+    let output_res = git_output(&mut cmd);
     if output_res.is_err() {
         let e = output_res.expect_err("No error in output_res in git clone");
         log::error!("[clone_git_repo] Executing in directory: {:?}, git clone: {:?}",
@@ -654,68 +516,89 @@ pub fn get_git_aliases(repo: &Repository) -> Option<Vec<String>> {
 		return None;
 	}
 	let local_dir = local_dir_opt.expect("Empty local_dir");
-	let output_res = Command::new("git")
-		.arg("log")
-		.arg("--all")
-		.arg("--format=%ae")
-		.current_dir(&local_dir)
-		.output();
-	if output_res.is_err() {
-		let e = output_res.expect_err("No error in output_res");
-		log::error!("[get_git_aliases] git alias command error {:?}", e);
-		return None;
-	}
-	let output = output_res.expect("Unable to catch error in output_res");
-	let emails_res = str::from_utf8(&output.stdout);
-	if emails_res.is_err() {
-		let e = emails_res.expect_err("Empty error in emails_res");
-		log::error!("[get_git_aliases] Unable to parse git alias command output {:?}", e);
+	let git_repo_opt = GitRepo::open(&local_dir);
+	if git_repo_opt.is_none() {
+		log::error!("[get_git_aliases] Unable to open repo at {}", &local_dir);
 		return None;
 	}
-	let emails_str = emails_res.expect("Uncaught error in eails_res");
-	log::debug!("[get_git_aliases] Extracted git aliases: {:?}", &emails_str);
-	let emails: Vec<String> = emails_str
-            .lines()
-            .map(|line| line.trim().to_string())
-            .collect();
-	// Sort and remove duplicates
-    let mut unique_emails: Vec<String> = emails.into_iter().collect();
-    unique_emails.sort();
-    unique_emails.dedup();
-	log::debug!("[get_git_aliases] Extracted unique git aliases: {:?}", &unique_emails);
-	return Some(unique_emails);
+	let unique_emails_opt = git_repo_opt.expect("Empty git_repo_opt").author_emails();
+	log::debug!("[get_git_aliases] Extracted unique git aliases: {:?}", &unique_emails_opt);
+	return unique_emails_opt;
 }
 
-
-pub fn get_file_modification_status(clone_dir: &str, commit_range: &str) -> Option<HashMap<String, Vec<String>>> {
-	let git_res = Command::new("git")
-		.args(&["diff", "--name-status", &commit_range])
-		.current_dir(clone_dir)
-		.output();
-	if git_res.is_err() {
-		let e = git_res.expect_err("No error in git_res");
-		log::error!("[get_file_modification_status] git diff name status cmd error {:?}", e);
+pub fn get_file_modification_status(clone_dir: &str, base_commit: &str, head_commit: &str) -> Option<HashMap<String, Vec<String>>> {
+	let git_repo = GitRepo::open(clone_dir)?;
+	let file_diffs_opt = git_repo.diff(base_commit, head_commit, false, false, &[]);
+	if file_diffs_opt.is_none() {
+		log::error!("[get_file_modification_status] Unable to diff {}...{}", base_commit, head_commit);
 		return None;
 	}
-	let mod_status = git_res.expect("Unable to catch error in git_res");
-	let mod_lines_res = String::from_utf8(mod_status.stdout);
-	if mod_lines_res.is_err() {
-		let e = mod_lines_res.expect_err("No error in mod_lines_res");
-		log::error!("[get_file_modification_status] Unable to parse git diff name cmd output {:?}", e);
-		return None;
-	}
-	let mod_lines = mod_lines_res.expect("Unable to catch error in mod_lines_res");
 	let mut mod_map = HashMap::<String, Vec<String>>::new();
-	for line in mod_lines.lines() {
-		let parts: Vec<&str> = line.split_whitespace().collect();
-		if parts.len() == 2 {
-			match parts[0] {
-				"A" => mod_map.entry("green".to_string()).or_insert_with(Vec::new).push(parts[1].to_string()),
-				"D" => mod_map.entry("red".to_string()).or_insert_with(Vec::new).push(parts[1].to_string()),
-				"M" => mod_map.entry("yellow".to_string()).or_insert_with(Vec::new).push(parts[1].to_string()),
-				_ => {}
-			}
-		}
+	for file_diff in file_diffs_opt.expect("Empty file_diffs_opt") {
+		let color = match file_diff.status {
+			FileStatus::Added => "green",
+			FileStatus::Deleted => "red",
+			FileStatus::Modified | FileStatus::Renamed => "yellow",
+			FileStatus::Other => continue,
+		};
+		mod_map.entry(color.to_string()).or_default().push(file_diff.path);
 	}
 	return Some(mod_map);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::utils::git_repo::fixture::FixtureRepo;
+	use crate::utils::lineitem::LineItem;
+
+	#[tokio::test]
+	async fn surrounding_lines_are_kept_apart_from_changed_ones() {
+		let fixture = FixtureRepo::new("surrounding");
+		let base = fixture.commit(&[("lib.py",
+			"import os\n\ndef load(path):\n    data = read(path)\n    return data\n\ndef other():\n    return 1\n")],
+			"alice@example.com", 1_000);
+		let head = fixture.commit(&[("lib.py",
+			"import os\n\ndef load(path):\n    data = read(path)\n    check(data)\n    return data\n\ndef other():\n    return 2\n")],
+			"bob@example.com", 2_000);
+		let review = Review::new(base.to_string(), head.to_string(), "7".to_string(), "repo".to_string(),
+			"owner".to_string(), "github".to_string(), "github/owner/repo/7".to_string(),
+			fixture.path().to_string(), String::new(), String::new(), None);
+		let plain = BlameSettings::default();
+		let surrounding = BlameSettings::default().with_surrounding_lines(Some(1), Some(true));
+		let diffmap = generate_diff(&review, &vec![StatItem::new("lib.py", 2, 1, false)], &plain);
+
+		assert_eq!(process_diffmap(&review, &diffmap, &plain).await["lib.py"], vec![(8, 8, LineKind::Changed)]);
+		assert_eq!(process_diffmap(&review, &diffmap, &surrounding).await["lib.py"], vec![
+			(3, 3, LineKind::Function),
+			(4, 5, LineKind::Context),
+			(7, 7, LineKind::Function),
+			(8, 8, LineKind::Changed),
+		]);
+	}
+
+	#[test]
+	fn blame_ranges_are_merged_by_author() {
+		let range = |start_line: usize, end_line: usize, author: &str, commit: &str, orig_line: usize| BlameRange {
+			start_line,
+			end_line,
+			line_item: LineItem::new(author.to_string(), author.to_string(), "1000".to_string(),
+				commit.to_string(), author.to_string(), orig_line, "lib.py".to_string()),
+		};
+		let blamevec = process_blameitem("lib.py", vec![
+			range(1, 2, "alice", "c1", 10),
+			range(3, 3, "alice", "c2", 4),
+			range(4, 4, "bob", "c3", 4),
+			range(6, 6, "bob", "c3", 6),
+		]);
+		let merged: Vec<serde_json::Value> = blamevec.iter()
+			.map(|blame_item| serde_json::to_value(blame_item).expect("serialize"))
+			.collect();
+		let lines: Vec<(&str, &str, &str, &str)> = merged.iter()
+			.map(|item| (item["line_start"].as_str().unwrap_or_default(), item["line_end"].as_str().unwrap_or_default(),
+				item["orig_line_start"].as_str().unwrap_or_default(), item["orig_line_end"].as_str().unwrap_or_default()))
+			.collect();
+		assert_eq!(lines, vec![("1", "3", "10", "11"), ("4", "4", "4", "4"), ("6", "6", "6", "6")]);
+		assert_eq!(blamevec.iter().map(|blame_item| blame_item.commit().as_str()).collect::<Vec<_>>(), vec!["c1", "c3", "c3"]);
+	}
+}
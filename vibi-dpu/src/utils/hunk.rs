@@ -5,12 +5,18 @@ pub struct HunkMap {
     repo_name: String,
     prhunkvec: Vec<PrHunkItem>,
     db_key: String,
+    // How the blame in prhunkvec was computed
+    #[serde(default)]
+    blame_settings: BlameSettings,
 }
 
 
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::blame_settings::BlameSettings;
+use super::lineitem::LineItem;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PrHunkItem {
     pr_number: String,
@@ -19,6 +25,19 @@ pub struct PrHunkItem {
 }
 
 
+// Why a line of the PR's base was blamed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    // Removed or rewritten by the PR
+    #[default]
+    Changed,
+    // Next to lines the PR inserts
+    Context,
+    // Elsewhere in a function the PR changes
+    Function,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BlameItem {
     author: String,
@@ -30,35 +49,69 @@ pub struct BlameItem {
     commit: String, // This variable will be ignored during serialization
     #[serde(skip_serializing)]
     filepath_raw: String, // This variable will be ignored during serialization
+    #[serde(default)]
+    author_name: String,
+    #[serde(default)]
+    committer: String,
+    // Lines in the introducing commit of the first merged range, same as
+    // line_start/line_end if unmoved and unmerged
+    #[serde(default)]
+    orig_line_start: String,
+    #[serde(default)]
+    orig_line_end: String,
+    // File the lines were written in, differs from filepath_raw when they
+    // were renamed, moved or copied since
+    #[serde(default)]
+    orig_filepath: String,
+    #[serde(default)]
+    line_kind: LineKind,
 }
 
 impl BlameItem {
-    // Constructor
-    pub fn new(
-        author: String,
-        timestamp: String,
-        line_start: String,
-        line_end: String,
-        filepath: String,
-        commit: String,
-        filepath_raw: String,
-    ) -> Self {
+    // Lines line_start..=line_end of filepath_raw, last changed as line_item says
+    pub fn new(line_item: &LineItem, line_start: usize, line_end: usize, filepath: String,
+            filepath_raw: String) -> Self {
+        let orig_line_end = line_item.orig_line() + line_end - line_start;
         Self {
-            author,
-            timestamp,
-            line_start,
-            line_end,
+            author: line_item.author_id().to_string(),
+            timestamp: line_item.timestamp().to_string(),
+            line_start: line_start.to_string(),
+            line_end: line_end.to_string(),
             filepath,
-            commit,
+            commit: line_item.commit().to_string(),
             filepath_raw,
+            author_name: line_item.author_name().to_string(),
+            committer: line_item.committer().to_string(),
+            orig_line_start: line_item.orig_line().to_string(),
+            orig_line_end: orig_line_end.to_string(),
+            orig_filepath: line_item.orig_path().to_string(),
+            line_kind: LineKind::default(),
         }
     }
 
+    // Covers lines up to line_end, which neighbouring ranges of the same
+    // author were blamed for. Commit and original lines stay those of the
+    // range the item was created from.
+    pub fn with_line_end(mut self, line_end: usize) -> Self {
+        self.line_end = line_end.to_string();
+        self
+    }
+
+    pub fn with_line_kind(mut self, line_kind: LineKind) -> Self {
+        self.line_kind = line_kind;
+        self
+    }
+
     // Public getter methods
+    // Author email, or the name for commits without one
     pub fn author(&self) -> &String {
         &self.author
     }
 
+    pub fn timestamp(&self) -> &String {
+        &self.timestamp
+    }
+
     pub fn line_start(&self) -> &String {
         &self.line_start
     }
@@ -74,6 +127,14 @@ impl BlameItem {
     pub fn filepath_raw(&self) -> &String {
         &self.filepath_raw
     }
+
+    pub fn author_name(&self) -> &String {
+        &self.author_name
+    }
+
+    pub fn line_kind(&self) -> LineKind {
+        self.line_kind
+    }
 }
 
 impl PrHunkItem {
@@ -105,6 +166,7 @@ impl HunkMap {
         repo_name: String,
         prhunkvec: Vec<PrHunkItem>,
         db_key: String,
+        blame_settings: BlameSettings,
     ) -> Self {
         Self {
             repo_provider,
@@ -112,18 +174,11 @@ impl HunkMap {
             repo_name,
             prhunkvec,
             db_key,
+            blame_settings,
         }
     }
 
     // Public getter methods
-    pub fn repo_owner(&self) -> &String {
-        &self.repo_owner
-    }
-
-    pub fn repo_name(&self) -> &String {
-        &self.repo_name
-    }
-
     pub fn prhunkvec(&self) -> &Vec<PrHunkItem> {
         &self.prhunkvec
     }
@@ -1,23 +1,37 @@
 #[derive(Debug, Clone)]
 pub struct LineItem {
     author_id: String,
+    author_name: String,
     timestamp: String,
     commit: String,
+    committer: String,
+    orig_line: usize,
+    orig_path: String,
 }
 
 impl LineItem {
-    pub fn new(author_id: String, timestamp: String, commit: String) -> Self {
+    pub fn new(author_id: String, author_name: String, timestamp: String, commit: String,
+            committer: String, orig_line: usize, orig_path: String) -> Self {
         Self {
             author_id,
+            author_name,
             timestamp,
-            commit
+            commit,
+            committer,
+            orig_line,
+            orig_path,
         }
     }
 
+    // Author email, the stable key for relevance and aliases
     pub fn author_id(&self) -> &String {
         &self.author_id
     }
 
+    pub fn author_name(&self) -> &String {
+        &self.author_name
+    }
+
     pub fn timestamp(&self) -> &String {
         &self.timestamp
     }
@@ -25,4 +39,24 @@ impl LineItem {
     pub fn commit(&self) -> &String {
         &self.commit
     }
-}
\ No newline at end of file
+
+    // Committer email, differs from the author for rebased or applied patches
+    pub fn committer(&self) -> &String {
+        &self.committer
+    }
+
+    // Line number in the commit that introduced the line
+    pub fn orig_line(&self) -> usize {
+        self.orig_line
+    }
+
+    // File the line was written in, as of the commit that introduced it
+    pub fn orig_path(&self) -> &String {
+        &self.orig_path
+    }
+
+    pub fn with_orig_line(mut self, orig_line: usize) -> Self {
+        self.orig_line = orig_line;
+        self
+    }
+}
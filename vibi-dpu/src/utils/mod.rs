@@ -4,6 +4,7 @@ pub mod bitbucket_auth_info;
 pub mod github_auth_info;
 pub mod bitbucket_webhook;
 pub mod github_webhook;
+pub mod gitlab_webhook;
 pub mod hunk;
 pub mod review;
 pub mod gitops;
@@ -16,4 +17,11 @@ pub mod setup_info;
 pub mod aliases;
 pub mod relevance;
 pub mod coverage;
-pub mod parsing;
\ No newline at end of file
+pub mod parsing;
+pub mod comment;
+pub mod exclusion;
+pub mod codeowners;
+pub mod file_exclusion;
+pub mod git_repo;
+pub mod worktree;
+pub mod blame_settings;
@@ -7,7 +7,13 @@ pub struct Relevance {
     git_alias: String,
     relevance_str: String,
     relevance_num: f32,
+    // Share of blamed lines before any time decay, relevance_num is the weighted share
+    #[serde(default)]
+    raw_relevance_num: f32,
     handles: Option<Vec<String>>,
+    // Listed in CODEOWNERS for a changed file
+    #[serde(default)]
+    code_owner: bool,
 }
 
 impl Relevance {
@@ -17,6 +23,7 @@ impl Relevance {
         git_alias: String,
         relevance_str: String,
         relevance_num: f32,
+        raw_relevance_num: f32,
         handles: Option<Vec<String>>,
     ) -> Self {
         Self {
@@ -24,15 +31,13 @@ impl Relevance {
             git_alias,
             relevance_str,
             relevance_num,
+            raw_relevance_num,
             handles,
+            code_owner: false,
         }
     }
 
     // Public getter methods
-    pub fn provider(&self) -> &String {
-        &self.provider
-    }
-
     pub fn git_alias(&self) -> &String {
         &self.git_alias
     }
@@ -45,7 +50,19 @@ impl Relevance {
         self.relevance_num
     }
 
+    pub fn raw_relevance_num(&self) -> f32 {
+        self.raw_relevance_num
+    }
+
     pub fn handles(&self) -> &Option<Vec<String>> {
         &self.handles
     }
+
+    pub fn code_owner(&self) -> bool {
+        self.code_owner
+    }
+
+    pub fn set_code_owner(&mut self, code_owner: bool) {
+        self.code_owner = code_owner;
+    }
 }
\ No newline at end of file
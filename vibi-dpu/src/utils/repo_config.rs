@@ -4,7 +4,53 @@ use serde::{Serialize, Deserialize};
 pub struct RepoConfig {
     comment: bool,
     auto_assign: bool,
-    diff_graph: bool
+    diff_graph: bool,
+    #[serde(default)]
+    diff_graph_backend: Option<String>,
+    #[serde(default)]
+    diff_graph_format: Option<String>,
+    #[serde(default)]
+    relevance_half_life_days: Option<f32>,
+    #[serde(default)]
+    relevance_inactive_days: Option<f32>,
+    #[serde(default)]
+    relevance_exclusions: Option<Vec<String>>,
+    #[serde(default)]
+    codeowners_policy: Option<String>,
+    #[serde(default)]
+    auto_assign_max_reviewers: Option<usize>,
+    #[serde(default)]
+    auto_assign_max_open_reviews: Option<usize>,
+    #[serde(default)]
+    auto_assign_min_relevance: Option<f32>,
+    #[serde(default)]
+    included_paths: Option<Vec<String>>,
+    #[serde(default)]
+    excluded_paths: Option<Vec<String>>,
+    #[serde(default)]
+    max_file_changed_lines: Option<i32>,
+    #[serde(default)]
+    exclude_lockfiles: Option<bool>,
+    #[serde(default)]
+    exclude_generated_files: Option<bool>,
+    #[serde(default)]
+    blame_ignore_revs: Option<Vec<String>>,
+    #[serde(default)]
+    detect_renames: Option<bool>,
+    #[serde(default)]
+    blame_detect_moves: Option<bool>,
+    #[serde(default)]
+    blame_detect_copies: Option<bool>,
+    #[serde(default)]
+    relevance_context_lines: Option<usize>,
+    #[serde(default)]
+    relevance_enclosing_function: Option<bool>,
+    #[serde(default)]
+    relevance_changed_weight: Option<f32>,
+    #[serde(default)]
+    relevance_context_weight: Option<f32>,
+    #[serde(default)]
+    relevance_function_weight: Option<f32>,
 }
 
 impl RepoConfig {
@@ -21,12 +67,157 @@ impl RepoConfig {
         self.diff_graph
     }
 
+    // "parser" or "llm", unset means parser where a grammar exists
+    pub fn diff_graph_backend(&self) -> &Option<String> {
+        &self.diff_graph_backend
+    }
+
+    // "table", "text" or "image" for providers that don't render mermaid,
+    // unset means table
+    pub fn diff_graph_format(&self) -> &Option<String> {
+        &self.diff_graph_format
+    }
+
+    // Blamed lines lose half their weight every this many days, unset means
+    // every line counts the same regardless of age
+    pub fn relevance_half_life_days(&self) -> Option<f32> {
+        self.relevance_half_life_days
+    }
+
+    // Authors with no blamed line newer than this many days are left out
+    pub fn relevance_inactive_days(&self) -> Option<f32> {
+        self.relevance_inactive_days
+    }
+
+    // Git aliases, emails, handles or `*` globs left out of relevance and auto-assign
+    pub fn relevance_exclusions(&self) -> &Option<Vec<String>> {
+        &self.relevance_exclusions
+    }
+
+    // "always", "tiebreaker" or "fallback" to use CODEOWNERS next to blame,
+    // unset or "off" ignores them
+    pub fn codeowners_policy(&self) -> &Option<String> {
+        &self.codeowners_policy
+    }
+
+    // At most this many reviewers are auto-assigned per PR, unset means no cap
+    pub fn auto_assign_max_reviewers(&self) -> Option<usize> {
+        self.auto_assign_max_reviewers
+    }
+
+    // Reviewers with more open review requests than this are not auto-assigned
+    pub fn auto_assign_max_open_reviews(&self) -> Option<usize> {
+        self.auto_assign_max_open_reviews
+    }
+
+    // Relevance percentage a reviewer needs to be auto-assigned, unset means any
+    pub fn auto_assign_min_relevance(&self) -> Option<f32> {
+        self.auto_assign_min_relevance
+    }
+
+    // Globs of files always blamed, even if another rule would exclude them
+    pub fn included_paths(&self) -> &Option<Vec<String>> {
+        &self.included_paths
+    }
+
+    // Globs of files left out of blame and relevance
+    pub fn excluded_paths(&self) -> &Option<Vec<String>> {
+        &self.excluded_paths
+    }
+
+    // Files with more added plus deleted lines are skipped, unset means 5500
+    pub fn max_file_changed_lines(&self) -> Option<i32> {
+        self.max_file_changed_lines
+    }
+
+    // Skip Cargo.lock, package-lock.json and other lockfiles, unset means true
+    pub fn exclude_lockfiles(&self) -> Option<bool> {
+        self.exclude_lockfiles
+    }
+
+    // Skip files marked linguist-generated or linguist-vendored in
+    // .gitattributes, unset means true
+    pub fn exclude_generated_files(&self) -> Option<bool> {
+        self.exclude_generated_files
+    }
+
+    // Commits blame looks past, on top of .git-blame-ignore-revs
+    pub fn blame_ignore_revs(&self) -> &Option<Vec<String>> {
+        &self.blame_ignore_revs
+    }
+
+    // Diff renamed files as renames instead of a delete and an add, unset
+    // means true
+    pub fn detect_renames(&self) -> Option<bool> {
+        self.detect_renames
+    }
+
+    // Blame lines moved within a file on their author, unset means false
+    pub fn blame_detect_moves(&self) -> Option<bool> {
+        self.blame_detect_moves
+    }
+
+    // Blame lines moved or copied from other files changed in the same
+    // commit on their author, unset means false
+    pub fn blame_detect_copies(&self) -> Option<bool> {
+        self.blame_detect_copies
+    }
+
+    // Lines above and below code the PR only adds that are blamed too, unset
+    // means 0
+    pub fn relevance_context_lines(&self) -> Option<usize> {
+        self.relevance_context_lines
+    }
+
+    // Blame the rest of the functions the PR changes, unset means false
+    pub fn relevance_enclosing_function(&self) -> Option<bool> {
+        self.relevance_enclosing_function
+    }
+
+    // Weight of a removed or rewritten line, unset means 1
+    pub fn relevance_changed_weight(&self) -> Option<f32> {
+        self.relevance_changed_weight
+    }
+
+    // Weight of a line around an insertion, unset means 0.5
+    pub fn relevance_context_weight(&self) -> Option<f32> {
+        self.relevance_context_weight
+    }
+
+    // Weight of a line elsewhere in a changed function, unset means 0.25
+    pub fn relevance_function_weight(&self) -> Option<f32> {
+        self.relevance_function_weight
+    }
+
     // Function to create a default RepoConfig
     pub fn default() -> Self {
         RepoConfig {
             comment: true,
             auto_assign: true,
-            diff_graph: false
+            diff_graph: false,
+            diff_graph_backend: None,
+            diff_graph_format: None,
+            relevance_half_life_days: None,
+            relevance_inactive_days: None,
+            relevance_exclusions: None,
+            codeowners_policy: None,
+            auto_assign_max_reviewers: None,
+            auto_assign_max_open_reviews: None,
+            auto_assign_min_relevance: None,
+            included_paths: None,
+            excluded_paths: None,
+            max_file_changed_lines: None,
+            exclude_lockfiles: None,
+            exclude_generated_files: None,
+            blame_ignore_revs: None,
+            detect_renames: None,
+            blame_detect_moves: None,
+            blame_detect_copies: None,
+            relevance_context_lines: None,
+            relevance_enclosing_function: None,
+            relevance_changed_weight: None,
+            relevance_context_weight: None,
+            relevance_function_weight: None,
         }
     }
 }
\ No newline at end of file
@@ -96,4 +96,8 @@ impl Review {
     pub fn set_relevance(&mut self, relevance: Option<Vec<Relevance>>) {
         self.relevance = relevance;
     }
+
+    pub fn set_clone_dir(&mut self, clone_dir: &str) {
+        self.clone_dir = clone_dir.to_string();
+    }
 }
@@ -8,6 +8,7 @@ use serde::de::Deserializer;
 pub enum ProviderEnum {
     Bitbucket,
     Github,
+    Gitlab,
 }
 
 impl fmt::Display for ProviderEnum {
@@ -15,6 +16,7 @@ impl fmt::Display for ProviderEnum {
         match *self {
             ProviderEnum::Bitbucket => write!(f, "bitbucket"),
             ProviderEnum::Github => write!(f, "github"),
+            ProviderEnum::Gitlab => write!(f, "gitlab"),
         }
     }
 }
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::git_repo::GitRepo;
+use super::gitops::repo_lock;
+use super::review::Review;
+
+static WORKTREE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A private checkout of the review's repo, so code that reads the working
+// tree doesn't race with other reviews of the same repo. Removed by remove,
+// or on drop without waiting for the repo lock.
+pub struct ReviewWorktree {
+    review: Review,
+    repo_dir: String,
+    name: String,
+    removed: bool,
+}
+
+impl ReviewWorktree {
+    pub async fn create(review: &Review, commit: &str) -> Option<Self> {
+        let repo_dir = review.clone_dir().to_string();
+        // Adding a worktree writes to the repo's .git, as does a concurrent pull
+        let repo_lock = repo_lock(&repo_dir);
+        let _repo_guard = repo_lock.lock().await;
+        let git_repo = GitRepo::open(&repo_dir)?;
+        let review_id: String = review.id().chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let name = format!("vibi-review-{}-{}-{}", review_id, std::process::id(),
+            WORKTREE_COUNTER.fetch_add(1, Ordering::Relaxed));
+        let path = worktrees_dir().join(&name);
+        if let Err(e) = std::fs::create_dir_all(worktrees_dir()) {
+            log::error!("[ReviewWorktree::create] Unable to create {:?}: {:?}", worktrees_dir(), e);
+            return None;
+        }
+        if !git_repo.add_worktree(&name, &path, commit) {
+            log::error!("[ReviewWorktree::create] Unable to create worktree for review {}", review.id());
+            return None;
+        }
+        let mut worktree_review = review.clone();
+        worktree_review.set_clone_dir(&path.to_string_lossy());
+        log::debug!("[ReviewWorktree::create] Created {:?} at {}", &path, commit);
+        return Some(Self { review: worktree_review, repo_dir, name, removed: false });
+    }
+
+    // The review with clone_dir pointing at the worktree
+    pub fn review(&self) -> &Review {
+        return &self.review;
+    }
+
+    pub async fn remove(mut self) {
+        let repo_lock = repo_lock(&self.repo_dir);
+        let _repo_guard = repo_lock.lock().await;
+        self.remove_worktree();
+    }
+
+    fn remove_worktree(&mut self) {
+        if self.removed {
+            return;
+        }
+        self.removed = true;
+        let git_repo_opt = GitRepo::open(&self.repo_dir);
+        if git_repo_opt.is_none() || !git_repo_opt.expect("Empty git_repo_opt").remove_worktree(&self.name) {
+            log::error!("[ReviewWorktree::drop] Unable to remove worktree {}", &self.name);
+            let _ = std::fs::remove_dir_all(self.review.clone_dir());
+        }
+    }
+}
+
+impl Drop for ReviewWorktree {
+    fn drop(&mut self) {
+        self.remove_worktree();
+    }
+}
+
+fn worktrees_dir() -> PathBuf {
+    return std::env::temp_dir().join("vibi-dpu-worktrees");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::git_repo::fixture::FixtureRepo;
+    use crate::utils::gitops::git_checkout_commit;
+
+    #[tokio::test]
+    async fn worktrees_are_isolated_and_cleaned_up() {
+        let fixture = FixtureRepo::new("worktree");
+        let base = fixture.commit(&[("src/lib.rs", "base\n")], "alice@example.com", 1_000).to_string();
+        let head = fixture.commit(&[("src/lib.rs", "head\n")], "bob@example.com", 2_000).to_string();
+        let review = Review::new(base.clone(), head.clone(), "42".to_string(), "repo".to_string(),
+            "owner".to_string(), "github".to_string(), "github/owner/repo/42".to_string(),
+            fixture.path().to_string(), String::new(), String::new(), None);
+        let read = |worktree: &ReviewWorktree| {
+            std::fs::read_to_string(PathBuf::from(worktree.review().clone_dir()).join("src/lib.rs"))
+                .expect("file is checked out")
+        };
+
+        let base_worktree = ReviewWorktree::create(&review, &base).await.expect("base worktree");
+        let head_worktree = ReviewWorktree::create(&review, &head).await.expect("head worktree");
+        assert_ne!(base_worktree.review().clone_dir(), head_worktree.review().clone_dir());
+        assert_eq!((read(&base_worktree), read(&head_worktree)), ("base\n".to_string(), "head\n".to_string()));
+        git_checkout_commit(head_worktree.review(), &base);
+        assert_eq!(read(&head_worktree), "base\n");
+        assert_eq!(std::fs::read_to_string(fixture.dir.join("src/lib.rs")).expect("main checkout"), "head\n");
+
+        let base_dir = PathBuf::from(base_worktree.review().clone_dir());
+        let head_dir = PathBuf::from(head_worktree.review().clone_dir());
+        let repo_lock = repo_lock(fixture.path());
+        let repo_guard = repo_lock.lock().await;
+        let remove_handle = tokio::spawn(base_worktree.remove());
+        tokio::task::yield_now().await;
+        assert!(base_dir.exists(), "removal waits for the repo lock");
+        drop(repo_guard);
+        remove_handle.await.expect("remove task");
+        assert!(!base_dir.exists());
+        drop(head_worktree);
+        assert!(!head_dir.exists());
+        assert!(fixture.repo.worktrees().expect("worktrees").is_empty());
+        assert_eq!(fixture.repo.branches(None).expect("branches").count(), 1);
+    }
+}
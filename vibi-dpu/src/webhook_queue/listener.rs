@@ -0,0 +1,296 @@
+use crate::db::repo_config::get_repo_config_from_db;
+use crate::metrics::registry::record_message_outcome;
+use crate::pubsub::listener::process_message;
+use crate::utils::repo_config::RepoConfig;
+use crate::utils::user::ProviderEnum;
+use hmac::{Hmac, Mac};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc};
+use tokio::task::JoinHandle;
+
+const DEFAULT_WEBHOOK_PORT: u16 = 8080;
+// GitHub caps webhook payloads at 25 MB
+const MAX_BODY_BYTES: u64 = 25 * 1024 * 1024;
+
+struct WebhookState {
+	secret: String,
+}
+
+// Shared secret providers sign deliveries with, or send as the GitLab token
+pub fn webhook_secret() -> Option<String> {
+	std::env::var("DPU_WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty())
+}
+
+pub async fn serve_webhooks(secret: &str) {
+	let port = std::env::var("DPU_WEBHOOK_PORT")
+		.ok()
+		.and_then(|value| value.parse::<u16>().ok())
+		.unwrap_or(DEFAULT_WEBHOOK_PORT);
+	let state = Arc::new(WebhookState {
+		secret: secret.to_string(),
+	});
+	let make_svc = make_service_fn(move |_conn| {
+		let state = state.clone();
+		async move {
+			Ok::<_, Infallible>(service_fn(move |req| {
+				let state = state.clone();
+				async move { Ok::<_, Infallible>(handle_request(req, &state).await) }
+			}))
+		}
+	});
+	let addr = SocketAddr::from(([0, 0, 0, 0], port));
+	log::info!("[webhook_queue] Listening for webhooks on {}", addr);
+	if let Err(err) = Server::bind(&addr).serve(make_svc).await {
+		log::error!("[webhook_queue] Webhook server stopped: {:?}", err);
+	}
+}
+
+async fn handle_request(req: Request<Body>, state: &WebhookState) -> Response<Body> {
+	if req.method() != Method::POST {
+		return respond(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+	}
+	let provider = match provider_from_path(req.uri().path()) {
+		Some(provider) => provider,
+		None => return respond(StatusCode::NOT_FOUND, "not found"),
+	};
+	let content_length = req
+		.headers()
+		.get(hyper::header::CONTENT_LENGTH)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.parse::<u64>().ok());
+	if content_length.is_some_and(|length| length > MAX_BODY_BYTES) {
+		return respond(StatusCode::PAYLOAD_TOO_LARGE, "payload too large");
+	}
+	let headers = req.headers().clone();
+	let body = match read_body(req.into_body()).await {
+		Ok(Some(body)) => body,
+		Ok(None) => return respond(StatusCode::PAYLOAD_TOO_LARGE, "payload too large"),
+		Err(err) => {
+			log::error!("[webhook_queue] Could not read request body: {:?}", err);
+			return respond(StatusCode::BAD_REQUEST, "unreadable body");
+		}
+	};
+	if !verify_delivery(&provider, &headers, &body, &state.secret) {
+		log::error!("[webhook_queue] Rejected {} delivery with invalid signature", provider);
+		return respond(StatusCode::UNAUTHORIZED, "invalid signature");
+	}
+	let event_type = match event_type_header(&provider, &headers) {
+		Some(event_type) => event_type,
+		None => return respond(StatusCode::BAD_REQUEST, "missing event header"),
+	};
+	if event_type == "ping" {
+		return respond(StatusCode::OK, "pong");
+	}
+	let payload = match serde_json::from_slice::<Value>(&body) {
+		Ok(payload) => payload,
+		Err(err) => {
+			log::error!("[webhook_queue] Could not parse {} payload: {:?}", provider, err);
+			return respond(StatusCode::BAD_REQUEST, "invalid payload");
+		}
+	};
+	log::info!("[webhook_queue] Received {} event {}", provider, event_type);
+	let msg_bytes = webhook_message(&provider, &event_type, payload);
+	let mut attributes = HashMap::new();
+	attributes.insert("msgtype".to_string(), "webhook_callback".to_string());
+	// The delivery is only acknowledged once processing finished, a failed one
+	// is answered with a 5xx so the provider redelivers it. Reviews of the
+	// same repo serialise their pulls on the per-repo lock.
+	let handles = process_message(&attributes, &msg_bytes).await;
+	if !await_handles(handles).await {
+		log::error!("[webhook_queue] Processing failed for {} event {}", provider, event_type);
+		record_message_outcome("webhook", "nack");
+		return respond(StatusCode::INTERNAL_SERVER_ERROR, "processing failed");
+	}
+	record_message_outcome("webhook", "ack");
+	respond(StatusCode::OK, "processed")
+}
+
+// False if any of the processing tasks panicked or was cancelled
+async fn await_handles(handles: Vec<JoinHandle<()>>) -> bool {
+	let mut all_ok = true;
+	for handle in handles {
+		if let Err(e) = handle.await {
+			log::error!("[webhook_queue] Processing task failed: {:?}", e);
+			all_ok = false;
+		}
+	}
+	all_ok
+}
+
+// The body, or None once it grows past MAX_BODY_BYTES, which also covers
+// requests without a Content-Length
+async fn read_body(mut body: Body) -> Result<Option<Vec<u8>>, hyper::Error> {
+	let mut bytes = Vec::<u8>::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = chunk?;
+		if (bytes.len() + chunk.len()) as u64 > MAX_BODY_BYTES {
+			return Ok(None);
+		}
+		bytes.extend_from_slice(&chunk);
+	}
+	Ok(Some(bytes))
+}
+
+fn respond(status: StatusCode, body: &'static str) -> Response<Body> {
+	let mut response = Response::new(Body::from(body));
+	*response.status_mut() = status;
+	response
+}
+
+fn provider_from_path(path: &str) -> Option<String> {
+	let providers = [ProviderEnum::Github, ProviderEnum::Bitbucket, ProviderEnum::Gitlab];
+	providers
+		.iter()
+		.map(|provider| provider.to_string())
+		.find(|provider| path.trim_end_matches('/') == format!("/api/{}/callbacks/webhook", provider))
+}
+
+fn event_type_header(provider: &str, headers: &HeaderMap) -> Option<String> {
+	let header_name = if provider == ProviderEnum::Github.to_string() {
+		"x-github-event"
+	} else if provider == ProviderEnum::Bitbucket.to_string() {
+		"x-event-key"
+	} else {
+		"x-gitlab-event"
+	};
+	headers
+		.get(header_name)
+		.and_then(|value| value.to_str().ok())
+		.map(str::to_string)
+}
+
+pub(crate) fn verify_delivery(provider: &str, headers: &HeaderMap, body: &[u8], secret: &str) -> bool {
+	if provider == ProviderEnum::Gitlab.to_string() {
+		// GitLab sends the configured secret token as is instead of signing the body
+		let token = headers.get("x-gitlab-token").and_then(|value| value.to_str().ok());
+		return token.is_some_and(|token| constant_time_eq(token.as_bytes(), secret.as_bytes()));
+	}
+	let header_name = if provider == ProviderEnum::Github.to_string() {
+		"x-hub-signature-256"
+	} else {
+		"x-hub-signature"
+	};
+	match headers.get(header_name).and_then(|value| value.to_str().ok()) {
+		Some(signature) => verify_signature(secret.as_bytes(), body, signature),
+		None => false,
+	}
+}
+
+fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+	let hex_digest = match signature.strip_prefix("sha256=") {
+		Some(hex_digest) => hex_digest,
+		None => return false,
+	};
+	let expected = match hex::decode(hex_digest) {
+		Ok(expected) => expected,
+		Err(_) => return false,
+	};
+	let mut mac = match Hmac::<Sha256>::new_from_slice(secret) {
+		Ok(mac) => mac,
+		Err(_) => return false,
+	};
+	mac.update(body);
+	mac.verify_slice(&expected).is_ok()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	if a.len() != b.len() {
+		return false;
+	}
+	a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn webhook_message(provider: &str, event_type: &str, payload: Value) -> Vec<u8> {
+	let repo_config = repo_key(provider, &payload)
+		.and_then(|(repo_owner, repo_name)| get_repo_config_from_db(&repo_name, &repo_owner, provider))
+		.unwrap_or_else(RepoConfig::default);
+	let message = json!({
+		"repositoryProvider": provider,
+		"eventType": event_type,
+		"eventPayload": payload,
+		"repoConfig": repo_config,
+	});
+	serde_json::to_vec(&message).expect("webhook message is always serializable")
+}
+
+fn repo_key(provider: &str, payload: &Value) -> Option<(String, String)> {
+	if provider == ProviderEnum::Github.to_string() {
+		let repo_owner = payload["repository"]["owner"]["login"].as_str()?;
+		let repo_name = payload["repository"]["name"].as_str()?;
+		return Some((repo_owner.to_string(), repo_name.to_string()));
+	}
+	if provider == ProviderEnum::Bitbucket.to_string() {
+		let workspace_slug = payload["repository"]["workspace"]["slug"].as_str()?;
+		let repo_slug = payload["repository"]["name"].as_str()?.to_lowercase();
+		return Some((workspace_slug.to_string(), repo_slug));
+	}
+	let path_with_namespace = payload["project"]["path_with_namespace"].as_str()?;
+	let (repo_owner, repo_name) = path_with_namespace.rsplit_once('/')?;
+	Some((repo_owner.to_string(), repo_name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sign(secret: &str, body: &[u8]) -> String {
+		let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key");
+		mac.update(body);
+		format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+	}
+
+	#[test]
+	fn signature_must_match_body_and_secret() {
+		let body = br#"{"action":"opened"}"#;
+		let signature = sign("topsecret", body);
+
+		assert!(verify_signature(b"topsecret", body, &signature));
+		assert!(!verify_signature(b"othersecret", body, &signature));
+		assert!(!verify_signature(b"topsecret", br#"{"action":"closed"}"#, &signature));
+		assert!(!verify_signature(b"topsecret", body, signature.trim_start_matches("sha256=")));
+	}
+
+	#[test]
+	fn provider_headers_select_the_signature_scheme() {
+		let body = br#"{}"#;
+		let mut github_headers = HeaderMap::new();
+		github_headers.insert("x-hub-signature-256", sign("s3cret", body).parse().expect("valid header"));
+		let mut bitbucket_headers = HeaderMap::new();
+		bitbucket_headers.insert("x-hub-signature", sign("s3cret", body).parse().expect("valid header"));
+		let mut gitlab_headers = HeaderMap::new();
+		gitlab_headers.insert("x-gitlab-token", "s3cret".parse().expect("valid header"));
+
+		assert!(verify_delivery("github", &github_headers, body, "s3cret"));
+		assert!(!verify_delivery("github", &bitbucket_headers, body, "s3cret"));
+		assert!(verify_delivery("bitbucket", &bitbucket_headers, body, "s3cret"));
+		assert!(verify_delivery("gitlab", &gitlab_headers, body, "s3cret"));
+		assert!(!verify_delivery("gitlab", &gitlab_headers, body, "s3cret2"));
+		assert_eq!(provider_from_path("/api/bitbucket/callbacks/webhook"), Some("bitbucket".to_string()));
+		assert_eq!(provider_from_path("/api/unknown/callbacks/webhook"), None);
+	}
+
+	#[tokio::test]
+	async fn bodies_are_read_up_to_the_limit() {
+		let (mut sender, body) = Body::channel();
+		tokio::spawn(async move {
+			let chunk = vec![b'x'; 1024 * 1024];
+			for _ in 0..=MAX_BODY_BYTES / (1024 * 1024) {
+				if sender.send_data(chunk.clone().into()).await.is_err() {
+					return;
+				}
+			}
+		});
+		assert_eq!(read_body(body).await.expect("readable body"), None);
+		assert_eq!(read_body(Body::from("{}")).await.expect("readable body"), Some(b"{}".to_vec()));
+	}
+
+	#[tokio::test]
+	async fn deliveries_are_only_acked_once_their_tasks_finish() {
+		assert!(await_handles(vec![tokio::spawn(async {}), tokio::spawn(async {})]).await);
+		assert!(!await_handles(vec![tokio::spawn(async {}), tokio::spawn(async { panic!("review failed") })]).await);
+	}
+}
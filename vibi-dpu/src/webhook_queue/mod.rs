@@ -0,0 +1 @@
+pub mod listener;
\ No newline at end of file